@@ -0,0 +1,112 @@
+//! Time `prover::create_revocation_states` on rayon thread pools of increasing
+//! size. Run with:
+//!
+//! ```sh
+//! cargo bench --features parallel --bench revocation_states
+//! ```
+//!
+//! The registry size and the number of revocation states in the batch are set by
+//! `ANONCREDS_BENCH_MAX_CRED_NUM` (100000 by default) and `ANONCREDS_BENCH_BATCH`
+//! (1000 by default). Each witness accumulates `max_cred_num - 1` tails, so the
+//! running time grows with the product of the two: a batch covering every index
+//! of a 100000 credential registry takes hours on a single thread.
+
+use std::env;
+use std::time::Instant;
+
+use anoncreds::{
+    issuer, prover,
+    tails::{TailsFileReader, TailsFileWriter},
+    types::{CredentialDefinitionConfig, RegistryType, SignatureType},
+};
+
+fn env_count(name: &str, default: u32) -> u32 {
+    env::var(name)
+        .ok()
+        .map(|val| val.parse().expect("Invalid count"))
+        .unwrap_or(default)
+}
+
+fn main() {
+    let max_cred_num = env_count("ANONCREDS_BENCH_MAX_CRED_NUM", 100_000);
+    let batch = env_count("ANONCREDS_BENCH_BATCH", 1_000).min(max_cred_num);
+
+    let schema = issuer::create_schema("bench", "1.0", "mock:issuer", ["name"][..].into())
+        .expect("Error creating schema");
+    let (cred_def, _, _) = issuer::create_credential_definition(
+        "mock:schema",
+        &schema,
+        "mock:issuer",
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(true),
+    )
+    .expect("Error creating credential definition");
+
+    let start = Instant::now();
+    let mut tails_writer = TailsFileWriter::new(None);
+    let (rev_reg_def, _) = issuer::create_revocation_registry_def(
+        &cred_def,
+        "mock:cred_def",
+        "mock:issuer",
+        "tag",
+        RegistryType::CL_ACCUM,
+        max_cred_num,
+        &mut tails_writer,
+    )
+    .expect("Error creating revocation registry definition");
+    let status_list =
+        issuer::create_revocation_status_list("mock:rev_reg", &rev_reg_def, Some(1), true)
+            .expect("Error creating revocation status list");
+    println!(
+        "registry of {} credentials created in {:.1?}",
+        max_cred_num,
+        start.elapsed()
+    );
+
+    // Spread the batch evenly over the registry
+    let indices: Vec<u32> = (0..batch)
+        .map(|i| 1 + (u64::from(i) * u64::from(max_cred_num) / u64::from(batch)) as u32)
+        .collect();
+
+    let max_threads = std::thread::available_parallelism()
+        .map(usize::from)
+        .unwrap_or(1);
+    let mut threads = 1;
+    let mut baseline = None;
+    loop {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("Error creating thread pool");
+        let start = Instant::now();
+        let rev_states = pool
+            .install(|| {
+                let tails_reader =
+                    TailsFileReader::new_tails_reader(&rev_reg_def.value.tails_location);
+                prover::create_revocation_states(
+                    &rev_reg_def,
+                    &status_list,
+                    &indices,
+                    &tails_reader,
+                )
+            })
+            .expect("Error creating revocation states");
+        let elapsed = start.elapsed();
+        assert_eq!(rev_states.len(), indices.len());
+
+        let baseline = *baseline.get_or_insert(elapsed);
+        println!(
+            "{} revocation states on {} threads: {:.1?} (speedup {:.2})",
+            indices.len(),
+            threads,
+            elapsed,
+            baseline.as_secs_f64() / elapsed.as_secs_f64()
+        );
+
+        if threads == max_threads {
+            break;
+        }
+        threads = (threads * 2).min(max_threads);
+    }
+}
@@ -0,0 +1,98 @@
+//! Compare `prover::create_revocation_states` with creating the same revocation
+//! states one at a time on the current thread. Run with:
+//!
+//! ```sh
+//! cargo bench --bench revocation_states_batch
+//! ```
+//!
+//! The registry size and the number of revocation states are set by
+//! `ANONCREDS_BENCH_MAX_CRED_NUM` (10000 by default) and `ANONCREDS_BENCH_BATCH`
+//! (100 by default). Both paths accumulate every witness on its own, so only the
+//! cost of reading the tails file is saved by the batch. With the `parallel` feature
+//! the batch also runs on the rayon thread pool, see the `revocation_states` bench.
+
+use std::env;
+use std::time::Instant;
+
+use anoncreds::{
+    issuer, prover,
+    tails::{TailsFileReader, TailsFileWriter},
+    types::{CredentialDefinitionConfig, RegistryType, SignatureType},
+};
+
+fn env_count(name: &str, default: u32) -> u32 {
+    env::var(name)
+        .ok()
+        .map(|val| val.parse().expect("Invalid count"))
+        .unwrap_or(default)
+}
+
+fn main() {
+    let max_cred_num = env_count("ANONCREDS_BENCH_MAX_CRED_NUM", 10_000);
+    let batch = env_count("ANONCREDS_BENCH_BATCH", 100).min(max_cred_num);
+
+    let schema = issuer::create_schema("bench", "1.0", "mock:issuer", ["name"][..].into())
+        .expect("Error creating schema");
+    let (cred_def, _, _) = issuer::create_credential_definition(
+        "mock:schema",
+        &schema,
+        "mock:issuer",
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(true),
+    )
+    .expect("Error creating credential definition");
+
+    let mut tails_writer = TailsFileWriter::new(None);
+    let (rev_reg_def, _) = issuer::create_revocation_registry_def(
+        &cred_def,
+        "mock:cred_def",
+        "mock:issuer",
+        "tag",
+        RegistryType::CL_ACCUM,
+        max_cred_num,
+        &mut tails_writer,
+    )
+    .expect("Error creating revocation registry definition");
+    let status_list =
+        issuer::create_revocation_status_list("mock:rev_reg", &rev_reg_def, Some(1), true)
+            .expect("Error creating revocation status list");
+
+    // Spread the batch evenly over the registry
+    let indices: Vec<u32> = (0..batch)
+        .map(|i| 1 + (u64::from(i) * u64::from(max_cred_num) / u64::from(batch)) as u32)
+        .collect();
+
+    let start = Instant::now();
+    for &idx in indices.iter() {
+        prover::create_or_update_revocation_state(
+            &rev_reg_def.value.tails_location,
+            &rev_reg_def,
+            &status_list,
+            idx,
+            None,
+            None,
+        )
+        .expect("Error creating revocation state");
+    }
+    let single = start.elapsed();
+    println!(
+        "{} revocation states one at a time: {:.1?}",
+        indices.len(),
+        single
+    );
+
+    let start = Instant::now();
+    let tails_reader = TailsFileReader::new_tails_reader(&rev_reg_def.value.tails_location);
+    let rev_states =
+        prover::create_revocation_states(&rev_reg_def, &status_list, &indices, &tails_reader)
+            .expect("Error creating revocation states");
+    let batched = start.elapsed();
+    assert_eq!(rev_states.len(), indices.len());
+    println!(
+        "{} revocation states in a batch: {:.1?} (speedup {:.2})",
+        indices.len(),
+        batched,
+        single.as_secs_f64() / batched.as_secs_f64()
+    );
+}
@@ -0,0 +1,99 @@
+//! Measure the time and the peak heap memory of parsing a large revocation status
+//! list, compared with parsing the same JSON into one byte per entry. Run with:
+//!
+//! ```sh
+//! cargo bench --bench status_list_parse
+//! ```
+//!
+//! The number of entries is set by `ANONCREDS_BENCH_MAX_CRED_NUM` (1000000 by
+//! default). The peak is measured by a counting global allocator, and only counts
+//! the allocations made while parsing, not the JSON input.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use anoncreds::data_types::rev_reg::RevocationStatusList;
+use serde::Deserialize;
+
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// The status list entries as one byte each
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct ByteStatusList {
+    rev_reg_def_id: String,
+    revocation_list: Vec<u8>,
+}
+
+fn env_count(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .map(|val| val.parse().expect("Invalid count"))
+        .unwrap_or(default)
+}
+
+// Parse the JSON, returning the result with the time taken and the peak heap memory
+// allocated during parsing
+fn measure<T>(name: &str, parse: impl FnOnce() -> T) -> T {
+    let base = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let start = Instant::now();
+    let result = parse();
+    let elapsed = start.elapsed();
+    println!(
+        "{}: {:.1?}, peak {} bytes, retained {} bytes",
+        name,
+        elapsed,
+        PEAK.load(Ordering::Relaxed) - base,
+        ALLOCATED.load(Ordering::Relaxed) - base
+    );
+    result
+}
+
+fn main() {
+    let size = env_count("ANONCREDS_BENCH_MAX_CRED_NUM", 1_000_000);
+
+    let mut json = String::with_capacity(size * 2 + 64);
+    json.push_str(r#"{"revRegDefId": "mock:rev_reg", "revocationList": ["#);
+    for idx in 0..size {
+        if idx > 0 {
+            json.push(',');
+        }
+        json.push(if idx % 3 == 0 { '1' } else { '0' });
+    }
+    json.push_str("]}");
+    println!("{} entries, {} bytes of JSON", size, json.len());
+
+    let bytes = measure("one byte per entry", || {
+        serde_json::from_str::<ByteStatusList>(&json).expect("Error parsing status list")
+    });
+    let list = measure("RevocationStatusList", || {
+        serde_json::from_str::<RevocationStatusList>(&json).expect("Error parsing status list")
+    });
+    assert_eq!(list.len(), bytes.revocation_list.len());
+}
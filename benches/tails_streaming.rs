@@ -0,0 +1,117 @@
+//! Compare creating a revocation state from a tails file opened with
+//! `TailsFileReader::open_streaming` with the same computation over the whole tails
+//! file loaded in memory. Run with:
+//!
+//! ```sh
+//! cargo bench --bench tails_streaming
+//! ```
+//!
+//! The registry size is set by `ANONCREDS_BENCH_MAX_CRED_NUM` (100000 by default).
+//! The streaming reader seeks to each tail it needs and holds a single tail at a
+//! time, while the in-memory reader holds the whole file, whose size is printed.
+
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+
+use anoncreds::{
+    issuer, prover,
+    tails::{TailsFileReader, TailsFileWriter, TailsReader, TailsReaderImpl},
+    types::{CredentialDefinitionConfig, RegistryType, SignatureType},
+    Error, ErrorKind,
+};
+
+fn env_count(name: &str, default: u32) -> u32 {
+    env::var(name)
+        .ok()
+        .map(|val| val.parse().expect("Invalid count"))
+        .unwrap_or(default)
+}
+
+// The whole tails file, read into memory up front
+#[derive(Debug)]
+struct LoadedTails(Vec<u8>);
+
+impl TailsReaderImpl for LoadedTails {
+    fn hash(&mut self) -> Result<Vec<u8>, Error> {
+        Ok(Sha256::digest(&self.0).to_vec())
+    }
+
+    fn read(&mut self, size: usize, offset: usize) -> Result<Vec<u8>, Error> {
+        self.0
+            .get(offset..offset + size)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| Error::from_msg(ErrorKind::IOError, "Read past the end of the tails"))
+    }
+}
+
+fn main() {
+    let max_cred_num = env_count("ANONCREDS_BENCH_MAX_CRED_NUM", 100_000);
+
+    let schema = issuer::create_schema("bench", "1.0", "mock:issuer", ["name"][..].into())
+        .expect("Error creating schema");
+    let (cred_def, _, _) = issuer::create_credential_definition(
+        "mock:schema",
+        &schema,
+        "mock:issuer",
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(true),
+    )
+    .expect("Error creating credential definition");
+
+    let mut tails_writer = TailsFileWriter::new(None);
+    let (rev_reg_def, _) = issuer::create_revocation_registry_def(
+        &cred_def,
+        "mock:cred_def",
+        "mock:issuer",
+        "tag",
+        RegistryType::CL_ACCUM,
+        max_cred_num,
+        &mut tails_writer,
+    )
+    .expect("Error creating revocation registry definition");
+    let status_list =
+        issuer::create_revocation_status_list("mock:rev_reg", &rev_reg_def, Some(1), true)
+            .expect("Error creating revocation status list");
+    let tails_location = &rev_reg_def.value.tails_location;
+
+    let create_state = |tails_reader: &TailsReader| {
+        prover::create_or_update_revocation_state_with_tails_reader(
+            tails_reader,
+            &rev_reg_def,
+            &status_list,
+            1,
+            None,
+            None,
+            false,
+        )
+        .expect("Error creating revocation state")
+    };
+
+    let start = Instant::now();
+    let tails_reader =
+        TailsFileReader::open_streaming(tails_location).expect("Error opening tails file");
+    let streamed = create_state(&tails_reader);
+    let streaming = start.elapsed();
+    println!("streaming tails reader: {:.1?}", streaming);
+
+    let start = Instant::now();
+    let tails = fs::read(tails_location).expect("Error reading tails file");
+    let tails_size = tails.len();
+    let loaded = create_state(&TailsReader::new(LoadedTails(tails)));
+    let in_memory = start.elapsed();
+    println!(
+        "tails loaded in memory ({} bytes): {:.1?} (streaming takes {:.2}x as long)",
+        tails_size,
+        in_memory,
+        streaming.as_secs_f64() / in_memory.as_secs_f64()
+    );
+
+    assert_eq!(
+        serde_json::to_value(&streamed.witness).unwrap(),
+        serde_json::to_value(&loaded.witness).unwrap()
+    );
+}
@@ -1,12 +1,22 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 
-use crate::{error::ConversionError, impl_anoncreds_object_identifier};
+use sha2::{Digest, Sha256};
+
+use crate::utils::{base58, json::to_canonical_json};
+use crate::{
+    error::{ConversionError, Error},
+    impl_anoncreds_object_identifier,
+};
 
 use super::{issuer_id::IssuerId, schema::SchemaId};
 
 pub const CL_SIGNATURE_TYPE: &str = "CL";
 
-impl_anoncreds_object_identifier!(CredentialDefinitionId);
+impl_anoncreds_object_identifier!(
+    CredentialDefinitionId,
+    crate::utils::validation::is_legacy_cred_def_id
+);
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SignatureType {
@@ -25,6 +35,7 @@ impl FromStr for SignatureType {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct CredentialDefinitionData {
     pub primary: ursa::cl::CredentialPrimaryPublicKey,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,6 +44,7 @@ pub struct CredentialDefinitionData {
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct CredentialDefinition {
     pub schema_id: SchemaId,
     #[serde(rename = "type")]
@@ -51,6 +63,45 @@ impl CredentialDefinition {
         .map_err(|e| e.to_string())?;
         Ok(key)
     }
+
+    /// Serialize the credential definition to canonical JSON, for example to
+    /// derive a content-addressed identifier
+    pub fn to_canonical_json(&self) -> Result<Vec<u8>, Error> {
+        to_canonical_json(self)
+    }
+
+    /// A short fingerprint of the public key material, which does not depend on the
+    /// identifiers or the tag of the credential definition.
+    ///
+    /// The fingerprint is the SHA-256 multihash of the canonical JSON of the public
+    /// keys, multibase encoded as base58btc (with the `z` prefix).
+    ///
+    /// The keys are serialized by their ursa `Serialize` implementations, which can
+    /// report an error. That error is returned with the `Unexpected` kind rather
+    /// than turned into a panic. Any other input has a fingerprint.
+    pub fn fingerprint(&self) -> Result<String, Error> {
+        let digest = Sha256::digest(to_canonical_json(&self.value)?);
+        let mut multihash = Vec::with_capacity(digest.len() + 2);
+        // sha2-256 multihash code and digest length
+        multihash.extend_from_slice(&[0x12, 0x20]);
+        multihash.extend_from_slice(&digest);
+        Ok(format!("z{}", base58::encode(multihash)))
+    }
+
+    /// The names of the attributes signed by the credential definition,
+    /// excluding the master secret
+    pub fn attribute_names(&self) -> Result<HashSet<String>, ConversionError> {
+        let primary = serde_json::to_value(&self.value.primary)?;
+        let attrs = primary
+            .get("r")
+            .and_then(|r| r.as_object())
+            .ok_or_else(|| ConversionError::from_msg("Invalid credential primary public key"))?
+            .keys()
+            .filter(|attr| attr.as_str() != "master_secret")
+            .cloned()
+            .collect();
+        Ok(attrs)
+    }
 }
 
 impl Validatable for CredentialDefinition {
@@ -59,6 +110,10 @@ impl Validatable for CredentialDefinition {
     }
 }
 
+/// The issuer's private key for a credential definition.
+///
+/// The key is owned by ursa, and clearing its memory on drop is left to ursa: this
+/// crate does not zeroize it.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CredentialDefinitionPrivate {
     pub value: ursa::cl::CredentialPrivateKey,
@@ -77,3 +132,83 @@ impl CredentialKeyCorrectnessProof {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::data_types::fixtures;
+
+    use super::*;
+
+    fn _cred_def(tag: &str) -> CredentialDefinition {
+        fixtures::cred_def(tag).0
+    }
+
+    #[test]
+    fn fingerprint_depends_on_the_keys_only() {
+        let cred_def = _cred_def("tag");
+        let fingerprint = cred_def.fingerprint().unwrap();
+        assert!(fingerprint.starts_with("zQm"));
+        assert_eq!(cred_def.fingerprint().unwrap(), fingerprint);
+
+        let mut json = serde_json::to_value(&cred_def).unwrap();
+        json["tag"] = "other".into();
+        json["schemaId"] = "mock:other".into();
+        json["issuerId"] = "mock:other".into();
+        let retagged: CredentialDefinition = serde_json::from_value(json).unwrap();
+        assert_eq!(retagged.fingerprint().unwrap(), fingerprint);
+
+        assert_ne!(_cred_def("tag").fingerprint().unwrap(), fingerprint);
+    }
+
+    #[test]
+    fn legacy_identifiers_are_typed() {
+        use crate::data_types::schema::SchemaId;
+
+        const DID: &str = "NcYxiDXkpYi6ov5FcYDi1e";
+        const SCHEMA_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e:2:test:1.0";
+        const CRED_DEF_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag";
+
+        let id = CredentialDefinitionId::new(CRED_DEF_ID).unwrap();
+        assert!(id.is_legacy());
+        assert!(CredentialDefinitionId::new("6cgbu8ZPoWTnR5Rv5JcSMB:3:CL:1:tag").is_ok());
+        assert!(CredentialDefinitionId::new("did:indy:sovrin:6cgbu8ZPoWTnR5Rv5JcSMB").is_ok());
+        assert!(CredentialDefinitionId::new(DID).is_err());
+        assert!(CredentialDefinitionId::new(SCHEMA_ID).is_err());
+
+        assert!(SchemaId::new(SCHEMA_ID).unwrap().is_legacy());
+        assert!(SchemaId::new(DID).is_err());
+        assert!(SchemaId::new(CRED_DEF_ID).is_err());
+
+        assert!(IssuerId::new(DID).unwrap().is_legacy());
+        assert!(IssuerId::new(SCHEMA_ID).is_err());
+        assert!(!IssuerId::new("mock:uri").unwrap().is_legacy());
+    }
+
+    mod unknown_fields {
+        use super::*;
+
+        #[cfg(not(feature = "serde_strict"))]
+        #[test]
+        fn credential_definition_ignores_unknown_fields() {
+            let mut json = serde_json::to_value(_cred_def("tag")).unwrap();
+            json["schemaID"] = "mock:uri".into();
+            json["value"]["revocaton"] = json!({});
+            serde_json::from_value::<CredentialDefinition>(json).unwrap();
+        }
+
+        #[cfg(feature = "serde_strict")]
+        #[test]
+        fn credential_definition_rejects_unknown_fields() {
+            let json = serde_json::to_value(_cred_def("tag")).unwrap();
+            serde_json::from_value::<CredentialDefinition>(json.clone()).unwrap();
+
+            let mut top_level = json.clone();
+            top_level["schemaID"] = "mock:uri".into();
+            serde_json::from_value::<CredentialDefinition>(top_level).unwrap_err();
+
+            let mut nested = json;
+            nested["value"]["revocaton"] = json!({});
+            serde_json::from_value::<CredentialDefinition>(nested).unwrap_err();
+        }
+    }
+}
@@ -4,6 +4,7 @@ use crate::utils::validation::Validatable;
 use super::{cred_def::CredentialDefinitionId, nonce::Nonce, schema::SchemaId};
 
 #[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct CredentialOffer {
     pub schema_id: SchemaId,
     pub cred_def_id: CredentialDefinitionId,
@@ -11,6 +12,33 @@ pub struct CredentialOffer {
     pub nonce: Nonce,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub method_name: Option<String>,
+    /// The time the offer was created, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+}
+
+impl CredentialOffer {
+    /// Check whether more than `ttl` seconds have passed between the creation of the
+    /// offer and `now`. Offers without a creation time never expire here, but are
+    /// rejected by `issuer::create_credential` when an offer TTL is given.
+    pub fn is_expired(&self, now: u64, ttl: u64) -> bool {
+        self.created
+            .map_or(false, |created| now.saturating_sub(created) > ttl)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl CredentialOffer {
+    /// Encode the credential offer as compact CBOR, which round-trips
+    /// with the JSON representation
+    pub fn to_cbor(&self) -> crate::error::Result<Vec<u8>> {
+        crate::utils::cbor::to_cbor(self)
+    }
+
+    /// Decode the credential offer from CBOR produced by `to_cbor`
+    pub fn from_cbor(bytes: &[u8]) -> crate::error::Result<Self> {
+        crate::utils::cbor::from_cbor(bytes)
+    }
 }
 
 impl Validatable for CredentialOffer {
@@ -20,3 +48,79 @@ impl Validatable for CredentialOffer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::data_types::fixtures;
+
+    use super::*;
+
+    fn _cred_offer() -> CredentialOffer {
+        fixtures::cred_offer(&fixtures::cred_def("tag").1)
+    }
+
+    #[test]
+    fn offer_expiry() {
+        let mut offer = _cred_offer();
+        let created = offer.created.expect("Offer creation time not set");
+
+        // fresh
+        assert!(!offer.is_expired(created, 60));
+        assert!(!offer.is_expired(created + 60, 60));
+        assert!(!offer.is_expired(created - 1, 60));
+
+        // expired
+        assert!(offer.is_expired(created + 61, 60));
+
+        // no timestamp
+        offer.created = None;
+        assert!(!offer.is_expired(u64::MAX, 0));
+    }
+
+    #[test]
+    fn deserialize_offer_without_timestamp() {
+        let mut json = serde_json::to_value(_cred_offer()).unwrap();
+        assert!(json["created"].is_u64());
+        json.as_object_mut().unwrap().remove("created");
+
+        let offer: CredentialOffer = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(offer.created, None);
+        assert_eq!(serde_json::to_value(&offer).unwrap(), json);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        let offer = _cred_offer();
+        let json = serde_json::to_value(&offer).unwrap();
+        let cbor = offer.to_cbor().unwrap();
+        assert!(cbor.len() < json.to_string().len());
+
+        let decoded = CredentialOffer::from_cbor(&cbor).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+        assert!(CredentialOffer::from_cbor(json.to_string().as_bytes()).is_err());
+    }
+
+    mod unknown_fields {
+        use super::*;
+
+        // a misspelled field next to the correct one
+        fn _offer_json() -> serde_json::Value {
+            let mut json = serde_json::to_value(_cred_offer()).unwrap();
+            json["key_correctnes_proof"] = json["key_correctness_proof"].clone();
+            json
+        }
+
+        #[cfg(not(feature = "serde_strict"))]
+        #[test]
+        fn credential_offer_ignores_unknown_fields() {
+            serde_json::from_value::<CredentialOffer>(_offer_json()).unwrap();
+        }
+
+        #[cfg(feature = "serde_strict")]
+        #[test]
+        fn credential_offer_rejects_unknown_fields() {
+            serde_json::from_value::<CredentialOffer>(_offer_json()).unwrap_err();
+        }
+    }
+}
@@ -4,6 +4,7 @@ use crate::utils::validation::Validatable;
 use super::{cred_def::CredentialDefinitionId, nonce::Nonce};
 
 #[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct CredentialRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prover_did: Option<String>,
@@ -13,6 +14,20 @@ pub struct CredentialRequest {
     pub nonce: Nonce,
 }
 
+#[cfg(feature = "cbor")]
+impl CredentialRequest {
+    /// Encode the credential request as compact CBOR, which round-trips
+    /// with the JSON representation
+    pub fn to_cbor(&self) -> crate::error::Result<Vec<u8>> {
+        crate::utils::cbor::to_cbor(self)
+    }
+
+    /// Decode the credential request from CBOR produced by `to_cbor`
+    pub fn from_cbor(bytes: &[u8]) -> crate::error::Result<Self> {
+        crate::utils::cbor::from_cbor(bytes)
+    }
+}
+
 impl Validatable for CredentialRequest {
     fn validate(&self) -> Result<(), ValidationError> {
         self.cred_def_id.validate()?;
@@ -28,3 +43,38 @@ pub struct CredentialRequestMetadata {
 }
 
 impl Validatable for CredentialRequestMetadata {}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_types::fixtures;
+
+    use super::*;
+
+    fn _cred_request_json() -> serde_json::Value {
+        serde_json::to_value(fixtures::cred_request()).unwrap()
+    }
+
+    mod unknown_fields {
+        use super::*;
+
+        // a misspelled field next to the correct one
+        fn _request_json() -> serde_json::Value {
+            let mut json = _cred_request_json();
+            json["blinded_ms_corectness_proof"] = json["blinded_ms_correctness_proof"].clone();
+            json
+        }
+
+        #[cfg(not(feature = "serde_strict"))]
+        #[test]
+        fn credential_request_ignores_unknown_fields() {
+            serde_json::from_value::<CredentialRequest>(_request_json()).unwrap();
+        }
+
+        #[cfg(feature = "serde_strict")]
+        #[test]
+        fn credential_request_rejects_unknown_fields() {
+            serde_json::from_value::<CredentialRequest>(_request_json()).unwrap_err();
+            serde_json::from_value::<CredentialRequest>(_cred_request_json()).unwrap();
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::issuer::{create_credential_definition, create_credential_offer, create_schema};
+use crate::prover::{create_credential_request, create_master_secret};
+use crate::types::{CredentialDefinitionConfig, SignatureType};
+
+use super::cred_def::{CredentialDefinition, CredentialKeyCorrectnessProof};
+use super::cred_offer::CredentialOffer;
+use super::cred_request::CredentialRequest;
+
+/// A credential definition over a two-attribute schema, without revocation support.
+/// New keys are generated on each call.
+pub(crate) fn cred_def(tag: &str) -> (CredentialDefinition, CredentialKeyCorrectnessProof) {
+    let schema = create_schema("test", "1.0", "mock:uri", ["a", "b"][..].into()).unwrap();
+    let (cred_def, _, key_correctness_proof) = create_credential_definition(
+        "mock:uri",
+        &schema,
+        "mock:uri",
+        tag,
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(false),
+    )
+    .unwrap();
+    (cred_def, key_correctness_proof)
+}
+
+pub(crate) fn cred_offer(key_correctness_proof: &CredentialKeyCorrectnessProof) -> CredentialOffer {
+    create_credential_offer("mock:uri", "mock:uri", key_correctness_proof, None).unwrap()
+}
+
+pub(crate) fn cred_request() -> CredentialRequest {
+    let (cred_def, key_correctness_proof) = cred_def("tag");
+    let offer = cred_offer(&key_correctness_proof);
+    let master_secret = create_master_secret().unwrap();
+    let (request, _) =
+        create_credential_request(None, &cred_def, &master_secret, "default", &offer, None)
+            .unwrap();
+    request
+}
@@ -31,7 +31,10 @@ fn should_validate_new_and_legacy_identifiers() {
     assert!(IssuerId::new(valid_uri_identifier_5).is_ok());
     assert!(IssuerId::new(valid_uri_identifier_6).is_ok());
 
-    assert!(IssuerId::new(invalid_uri_identifier).is_err());
+    assert_eq!(
+        IssuerId::new(invalid_uri_identifier).unwrap_err().kind(),
+        crate::error::ValidationErrorKind::IdentifierFormat
+    );
 
     assert!(IssuerId::new(valid_legacy_identifier_1).is_ok());
     assert!(IssuerId::new(valid_legacy_identifier_2).is_ok());
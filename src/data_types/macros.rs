@@ -1,8 +1,11 @@
 #[macro_export]
 macro_rules! impl_anoncreds_object_identifier {
     ($i:ident) => {
+        $crate::impl_anoncreds_object_identifier!($i, $crate::utils::validation::is_legacy_did);
+    };
+    ($i:ident, $is_legacy:path) => {
         use $crate::error::ValidationError;
-        use $crate::utils::validation::{Validatable, LEGACY_IDENTIFIER, URI_IDENTIFIER};
+        use $crate::utils::validation::Validatable;
 
         #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, Default)]
         pub struct $i(pub String);
@@ -19,31 +22,30 @@ macro_rules! impl_anoncreds_object_identifier {
             }
 
             pub fn is_legacy(&self) -> bool {
-                LEGACY_IDENTIFIER.captures(&self.0).is_some()
+                $is_legacy(&self.0)
             }
 
             pub fn is_uri(&self) -> bool {
-                URI_IDENTIFIER.captures(&self.0).is_some()
+                $crate::utils::validation::is_uri_identifier(&self.0)
             }
         }
 
         impl Validatable for $i {
             fn validate(&self) -> Result<(), ValidationError> {
-                if $crate::utils::validation::URI_IDENTIFIER
-                    .captures(&self.0)
-                    .is_some()
-                {
+                if $is_legacy(&self.0) {
                     return Ok(());
                 }
 
-                if $crate::utils::validation::LEGACY_IDENTIFIER
-                    .captures(&self.0)
-                    .is_some()
+                // An identifier led by a legacy DID must have the legacy structure for its
+                // type, otherwise it would also be accepted as a URI with that DID as the scheme
+                if !$crate::utils::validation::has_legacy_did_prefix(&self.0)
+                    && $crate::utils::validation::is_uri_identifier(&self.0)
                 {
                     return Ok(());
                 }
 
                 Err($crate::invalid!(
+                    IdentifierFormat,
                     "type: {}, identifier: {} is invalid. It MUST be a URI or legacy identifier.",
                     stringify!($i),
                     self.0
@@ -4,6 +4,10 @@ use crate::error::ConversionError;
 use serde::{Deserialize, Serialize};
 use ursa::cl::{prover::Prover as UrsaProver, MasterSecret as UrsaMasterSecret};
 
+/// The holder's link secret.
+///
+/// The secret value is owned by ursa, and clearing its memory on drop is left to
+/// ursa: this crate does not zeroize it.
 #[derive(Serialize, Deserialize)]
 pub struct MasterSecret {
     pub value: UrsaMasterSecret,
@@ -31,8 +31,15 @@ pub mod rev_reg_def;
 /// V1 credential schemas
 pub mod schema;
 
+/// W3C verifiable credential representation
+pub mod w3c;
+
 /// Macros for the data types
 pub mod macros;
 
 /// Identifier wrapper for the issuer
 pub mod issuer_id;
+
+/// Test fixtures shared by the data types
+#[cfg(test)]
+pub(crate) mod fixtures;
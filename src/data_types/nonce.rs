@@ -1,22 +1,38 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
 
 use crate::error::ConversionError;
-use crate::ursa::cl::{new_nonce, Nonce as UrsaNonce};
+use crate::ursa::cl::Nonce as UrsaNonce;
+use rand::{rngs::OsRng, RngCore};
 use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
+use subtle::{Choice, ConstantTimeEq};
 
 pub struct Nonce {
     strval: String,
     native: UrsaNonce,
 }
 
+/// Size of a CL nonce in bits
+pub const NONCE_SIZE_BITS: usize = 80;
+
 impl Nonce {
+    /// Generate a new nonce using the operating system's secure random number generator
     #[inline]
     pub fn new() -> Result<Self, ConversionError> {
-        let native = new_nonce()
+        Self::from_rng(&mut OsRng)
+    }
+
+    /// Generate a new nonce from the provided random number generator. The result
+    /// falls within the range of an 80-bit CL nonce. Only use a deterministic
+    /// generator to produce reproducible test vectors.
+    pub fn from_rng<R: RngCore + ?Sized>(rng: &mut R) -> Result<Self, ConversionError> {
+        let mut bytes = [0u8; 16];
+        rng.try_fill_bytes(&mut bytes[(128 - NONCE_SIZE_BITS) / 8..])
             .map_err(|err| ConversionError::from_msg(format!("Error creating nonce: {err}")))?;
-        Self::from_native(native)
+        Self::try_from(u128::from_be_bytes(bytes))
     }
 
     #[inline]
@@ -61,9 +77,24 @@ impl Hash for Nonce {
     }
 }
 
+/// Nonces are compared in constant time, so that checking a received nonce
+/// against an expected one does not reveal how many leading digits match.
+/// Only the length of the decimal representation may leak, which is not secret.
+///
+/// `PartialEq` goes through this comparison, which covers every `==` on a nonce,
+/// including those of derived implementations such as the one of
+/// `PresentationRequestPayload`. The nonces passed to ursa are compared there, as
+/// part of the proof verification. Lookups in a `NonceRegistry` are not constant
+/// time, see its documentation.
+impl ConstantTimeEq for Nonce {
+    fn ct_eq(&self, other: &Nonce) -> Choice {
+        self.strval.as_bytes().ct_eq(other.strval.as_bytes())
+    }
+}
+
 impl PartialEq for Nonce {
     fn eq(&self, other: &Nonce) -> bool {
-        self.strval == other.strval
+        self.ct_eq(other).into()
     }
 }
 
@@ -191,10 +222,144 @@ impl<'a> Deserialize<'a> for Nonce {
     }
 }
 
+/// Check-and-consume access to the nonces which have already been used, as a
+/// defense against replayed credential requests and presentations.
+pub trait NonceSeen {
+    /// Whether `nonce` has already been used, without recording it
+    fn seen(&self, nonce: &Nonce) -> bool;
+
+    /// Record `nonce` as used, returning `false` if it had already been used
+    fn consume(&mut self, nonce: &Nonce) -> bool;
+}
+
+/// An in-memory record of used nonces.
+///
+/// Nonces are forgotten once they are older than the configured time to live, after
+/// which they would be accepted again. The time to live must therefore be at least
+/// as long as offers or presentation requests are accepted for. Expired nonces are
+/// evicted in the order they were registered, so registering a nonce only visits the
+/// nonces which have expired since the previous registration.
+///
+/// Used nonces are looked up by hash, and the hash and the string comparison of a
+/// matching bucket both take variable time. This reveals at most how much of a
+/// nonce matches one which was used already: the nonces of offers and presentation
+/// requests are sent in the clear to the other party, so the registry only guards
+/// against their reuse, and a recorded nonce is no secret to an attacker.
+#[derive(Debug)]
+pub struct NonceRegistry {
+    ttl: Duration,
+    seen: HashMap<String, Instant>,
+    expiry: VecDeque<(Instant, String)>,
+}
+
+impl NonceRegistry {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            seen: HashMap::new(),
+            expiry: VecDeque::new(),
+        }
+    }
+
+    /// Register a nonce, returning `false` if it has already been seen
+    pub fn register(&mut self, nonce: Nonce) -> bool {
+        self.register_value(nonce.strval)
+    }
+
+    /// Whether the nonce has been seen and has not expired yet
+    pub fn contains(&self, nonce: &Nonce) -> bool {
+        self.seen
+            .get(nonce.as_ref())
+            .map_or(false, |seen| seen.elapsed() < self.ttl)
+    }
+
+    /// Forget the nonces which are older than the time to live
+    pub fn evict_expired(&mut self) {
+        while let Some((seen, _)) = self.expiry.front() {
+            if seen.elapsed() < self.ttl {
+                break;
+            }
+            if let Some((_, value)) = self.expiry.pop_front() {
+                self.seen.remove(&value);
+            }
+        }
+    }
+
+    /// The number of nonces held, including expired ones which have not been evicted
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    fn register_value(&mut self, value: String) -> bool {
+        self.evict_expired();
+        match self.seen.entry(value) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                let now = Instant::now();
+                self.expiry.push_back((now, entry.key().clone()));
+                entry.insert(now);
+                true
+            }
+        }
+    }
+}
+
+impl NonceSeen for NonceRegistry {
+    fn seen(&self, nonce: &Nonce) -> bool {
+        self.contains(nonce)
+    }
+
+    fn consume(&mut self, nonce: &Nonce) -> bool {
+        self.register_value(nonce.strval.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn nonce_registry_rejects_replays() {
+        let mut registry = NonceRegistry::new(Duration::from_secs(600));
+        let nonce = Nonce::try_from("12345").unwrap();
+        assert!(registry.register(nonce.try_clone().unwrap()));
+        assert!(registry.contains(&nonce));
+        assert!(!registry.register(nonce.try_clone().unwrap()));
+        assert!(!registry.consume(&nonce));
+        assert!(registry.seen(&nonce));
+        assert!(!registry.seen(&Nonce::try_from("54321").unwrap()));
+        assert!(registry.consume(&Nonce::try_from("54321").unwrap()));
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn nonce_registry_evicts_expired() {
+        let mut registry = NonceRegistry::new(Duration::ZERO);
+        let nonce = Nonce::try_from("12345").unwrap();
+        assert!(registry.consume(&nonce));
+        assert!(!registry.contains(&nonce));
+        assert!(registry.consume(&nonce));
+        registry.evict_expired();
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn nonce_registry_evicts_in_registration_order() {
+        let mut registry = NonceRegistry::new(Duration::from_millis(100));
+        assert!(registry.consume(&Nonce::try_from("1").unwrap()));
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(registry.consume(&Nonce::try_from("2").unwrap()));
+        // the first nonce was evicted when the second one was registered
+        assert_eq!(registry.len(), 1);
+        assert!(!registry.consume(&Nonce::try_from("2").unwrap()));
+        assert!(registry.consume(&Nonce::try_from("1").unwrap()));
+        assert_eq!(registry.len(), 2);
+    }
+
     #[test]
     fn nonce_validate() {
         let valid = ["0", "1000000000000000000000000000000000"];
@@ -239,4 +404,34 @@ mod tests {
         let unonce = nonce.into_native();
         assert_eq!(strval, unonce.to_dec().unwrap());
     }
+
+    #[test]
+    fn nonce_from_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let nonce = Nonce::from_rng(&mut StdRng::seed_from_u64(1)).unwrap();
+        let repeat = Nonce::from_rng(&mut StdRng::seed_from_u64(1)).unwrap();
+        let other = Nonce::from_rng(&mut StdRng::seed_from_u64(2)).unwrap();
+        assert_eq!(nonce, repeat);
+        assert_ne!(nonce, other);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let nonce = Nonce::from_rng(&mut rng).unwrap();
+            assert!(nonce.parse::<u128>().unwrap() < 1u128 << NONCE_SIZE_BITS);
+        }
+    }
+
+    #[test]
+    fn nonce_ct_eq() {
+        let nonce = Nonce::from_dec("1234567890").unwrap();
+        assert!(bool::from(nonce.ct_eq(&nonce.try_clone().unwrap())));
+        assert!(!bool::from(
+            nonce.ct_eq(&Nonce::from_dec("1234567891").unwrap())
+        ));
+        assert!(!bool::from(
+            nonce.ct_eq(&Nonce::from_dec("123456789").unwrap())
+        ));
+        assert_eq!(nonce, nonce.try_clone().unwrap());
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
@@ -8,23 +8,138 @@ use super::credential::Credential;
 use super::nonce::Nonce;
 use crate::error::ValidationError;
 use crate::invalid;
-use crate::utils::{
-    query::Query,
-    validation::{self, Validatable},
-};
+use crate::services::helpers::attr_common_view;
+use crate::utils::validation::{self, Validatable};
+
+pub use crate::utils::query::{AbstractQuery, Query};
+
+/// The restriction keys supported by the verifier, in addition to the
+/// `attr::<name>::value` and `attr::<name>::marker` attribute tags
+pub const RESTRICTION_TAGS: [&str; 8] = [
+    "schema_id",
+    "schema_issuer_did",
+    "schema_issuer_id",
+    "schema_name",
+    "schema_version",
+    "cred_def_id",
+    "issuer_did",
+    "issuer_id",
+];
+
+/// A restriction key understood by the verifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestrictionTag<'a> {
+    SchemaId,
+    SchemaIssuerDid,
+    SchemaIssuerId,
+    SchemaName,
+    SchemaVersion,
+    CredDefId,
+    IssuerDid,
+    IssuerId,
+    /// `attr::<name>::value`, matching the raw value of the attribute
+    AttributeValue(&'a str),
+    /// `attr::<name>::marker`, matching credentials which contain the attribute
+    AttributeMarker(&'a str),
+}
+
+impl<'a> RestrictionTag<'a> {
+    /// Parse a restriction key, or return `None` when it is not understood by the verifier
+    pub fn parse(tag: &'a str) -> Option<Self> {
+        Some(match tag {
+            "schema_id" => Self::SchemaId,
+            "schema_issuer_did" => Self::SchemaIssuerDid,
+            "schema_issuer_id" => Self::SchemaIssuerId,
+            "schema_name" => Self::SchemaName,
+            "schema_version" => Self::SchemaVersion,
+            "cred_def_id" => Self::CredDefId,
+            "issuer_did" => Self::IssuerDid,
+            "issuer_id" => Self::IssuerId,
+            _ => {
+                let attr = tag.strip_prefix("attr::")?;
+                let (name, tag) = if let Some(name) = attr.strip_suffix("::value") {
+                    (name, Self::AttributeValue(name))
+                } else {
+                    let name = attr.strip_suffix("::marker")?;
+                    (name, Self::AttributeMarker(name))
+                };
+                if name.is_empty() || name.contains(':') {
+                    return None;
+                }
+                tag
+            }
+        })
+    }
+
+    /// Whether the restriction can only be checked against the schema of the credential
+    pub fn needs_schema(&self) -> bool {
+        matches!(
+            self,
+            Self::SchemaIssuerDid | Self::SchemaIssuerId | Self::SchemaName | Self::SchemaVersion
+        )
+    }
+
+    /// Whether the restriction can only be checked against the credential definition
+    pub fn needs_cred_def(&self) -> bool {
+        matches!(self, Self::IssuerDid | Self::IssuerId)
+    }
+}
+
+/// Check whether a restriction key is understood by the verifier
+pub fn is_restriction_tag(tag: &str) -> bool {
+    RestrictionTag::parse(tag).is_some()
+}
 
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct PresentationRequestPayload {
     pub nonce: Nonce,
     pub name: String,
     pub version: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_referents")]
     pub requested_attributes: HashMap<String, AttributeInfo>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_referents")]
     pub requested_predicates: HashMap<String, PredicateInfo>,
     pub non_revoked: Option<NonRevocedInterval>,
 }
 
+// Deserialize a map keyed by referent, rejecting repeated referents instead of
+// keeping the last one
+fn deserialize_referents<'de, D, V>(deserializer: D) -> Result<HashMap<String, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    struct ReferentsVisitor<V>(std::marker::PhantomData<V>);
+
+    impl<'de, V: Deserialize<'de>> de::Visitor<'de> for ReferentsVisitor<V> {
+        type Value = HashMap<String, V>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of referents")
+        }
+
+        fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::MapAccess<'de>,
+        {
+            let mut referents = HashMap::with_capacity(access.size_hint().unwrap_or(0).min(64));
+            while let Some((referent, value)) = access.next_entry::<String, V>()? {
+                if referents.contains_key(&referent) {
+                    return Err(de::Error::custom(format!(
+                        "duplicate referent: {}",
+                        referent
+                    )));
+                }
+                referents.insert(referent, value);
+            }
+            Ok(referents)
+        }
+    }
+
+    deserializer.deserialize_map(ReferentsVisitor(std::marker::PhantomData))
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PresentationRequest {
     PresentationRequestV1(PresentationRequestPayload),
@@ -58,34 +173,36 @@ impl<'de> Deserialize<'de> for PresentationRequest {
     where
         D: Deserializer<'de>,
     {
+        // The payload along with the version, deserialized without going through a
+        // `Value` so that repeated referents are still seen
         #[derive(Deserialize)]
+        #[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
         struct Helper {
             ver: Option<String>,
+            nonce: Nonce,
+            name: String,
+            version: String,
+            #[serde(default, deserialize_with = "deserialize_referents")]
+            requested_attributes: HashMap<String, AttributeInfo>,
+            #[serde(default, deserialize_with = "deserialize_referents")]
+            requested_predicates: HashMap<String, PredicateInfo>,
+            non_revoked: Option<NonRevocedInterval>,
         }
 
-        let v = Value::deserialize(deserializer)?;
-
-        let helper = Helper::deserialize(&v).map_err(de::Error::custom)?;
+        let helper = Helper::deserialize(deserializer)?;
+        let request = PresentationRequestPayload {
+            nonce: helper.nonce,
+            name: helper.name,
+            version: helper.version,
+            requested_attributes: helper.requested_attributes,
+            requested_predicates: helper.requested_predicates,
+            non_revoked: helper.non_revoked,
+        };
 
-        let req = match helper.ver {
-            Some(version) => match version.as_ref() {
-                "1.0" => {
-                    let request =
-                        PresentationRequestPayload::deserialize(v).map_err(de::Error::custom)?;
-                    PresentationRequest::PresentationRequestV1(request)
-                }
-                "2.0" => {
-                    let request =
-                        PresentationRequestPayload::deserialize(v).map_err(de::Error::custom)?;
-                    PresentationRequest::PresentationRequestV2(request)
-                }
-                _ => return Err(de::Error::unknown_variant(&version, &["2.0"])),
-            },
-            None => {
-                let request =
-                    PresentationRequestPayload::deserialize(v).map_err(de::Error::custom)?;
-                PresentationRequest::PresentationRequestV1(request)
-            }
+        let req = match helper.ver.as_deref() {
+            None | Some("1.0") => PresentationRequest::PresentationRequestV1(request),
+            Some("2.0") => PresentationRequest::PresentationRequestV2(request),
+            Some(version) => return Err(de::Error::unknown_variant(version, &["1.0", "2.0"])),
         };
         Ok(req)
     }
@@ -123,12 +240,26 @@ impl Serialize for PresentationRequest {
 pub type PresentationRequestExtraQuery = HashMap<String, Query>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct NonRevocedInterval {
     pub from: Option<u64>,
     pub to: Option<u64>,
 }
 
+impl NonRevocedInterval {
+    /// Whether a single timestamp can fall within both intervals
+    pub fn overlaps(&self, other: &NonRevocedInterval) -> bool {
+        let from = self.from.max(other.from).unwrap_or(0);
+        match (self.to, other.to) {
+            (Some(a), Some(b)) => from <= a.min(b),
+            (Some(to), None) | (None, Some(to)) => from <= to,
+            (None, None) => true,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct AttributeInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -139,14 +270,91 @@ pub struct AttributeInfo {
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
 pub struct PredicateInfo {
     pub name: String,
     pub p_type: PredicateTypes,
     pub p_value: i32,
+    /// Optional second bound on the same attribute, turning the predicate into a range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_bound: Option<PredicateBound>,
     pub restrictions: Option<Query>,
     pub non_revoked: Option<NonRevocedInterval>,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "serde_strict", serde(deny_unknown_fields))]
+pub struct PredicateBound {
+    pub p_type: PredicateTypes,
+    pub p_value: i32,
+}
+
+impl PredicateInfo {
+    /// Whether any 32-bit attribute value could satisfy the predicate
+    pub fn is_satisfiable(&self) -> bool {
+        self.bounds().all(|(p_type, p_value)| match p_type {
+            PredicateTypes::GT => p_value != i32::MAX,
+            PredicateTypes::LT => p_value != i32::MIN,
+            PredicateTypes::GE | PredicateTypes::LE => true,
+        })
+    }
+
+    /// Whether an attribute value satisfies every bound of the predicate
+    pub fn is_satisfied_by(&self, value: i32) -> bool {
+        self.bounds()
+            .all(|(p_type, p_value)| p_type.is_satisfied_by(value, p_value))
+    }
+
+    /// The operators and values of the predicate, one per CL predicate sub-proof
+    pub fn bounds(&self) -> impl Iterator<Item = (&PredicateTypes, i32)> {
+        std::iter::once((&self.p_type, self.p_value)).chain(
+            self.second_bound
+                .as_ref()
+                .map(|bound| (&bound.p_type, bound.p_value)),
+        )
+    }
+
+    /// Check that a range predicate has one lower and one upper bound, and that
+    /// the lower bound does not exceed the upper bound
+    pub fn validate_range(&self) -> Result<(), ValidationError> {
+        let bound = match self.second_bound.as_ref() {
+            Some(bound) => bound,
+            None => return Ok(()),
+        };
+        let (lower, upper) = match (
+            self.p_type.inclusive_lower(self.p_value),
+            bound.p_type.inclusive_upper(bound.p_value),
+        ) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            _ => match (
+                bound.p_type.inclusive_lower(bound.p_value),
+                self.p_type.inclusive_upper(self.p_value),
+            ) {
+                (Some(lower), Some(upper)) => (lower, upper),
+                _ => {
+                    return Err(invalid!(
+                        InconsistentPredicate,
+                        "Predicate range for {:?} must combine a lower and an upper bound",
+                        self.name
+                    ))
+                }
+            },
+        };
+        if lower > upper {
+            return Err(invalid!(
+                InconsistentPredicate,
+                "Predicate range for {:?} has a lower bound ({}) greater than the upper bound ({})",
+                self.name,
+                lower,
+                upper
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Predicate operators. Unknown operators are rejected when the presentation
+/// request is deserialized.
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub enum PredicateTypes {
     #[serde(rename = ">=")]
@@ -159,6 +367,36 @@ pub enum PredicateTypes {
     LT,
 }
 
+impl PredicateTypes {
+    /// The smallest value satisfying a `>=` or `>` predicate
+    fn inclusive_lower(&self, p_value: i32) -> Option<i64> {
+        match self {
+            PredicateTypes::GE => Some(p_value as i64),
+            PredicateTypes::GT => Some(p_value as i64 + 1),
+            _ => None,
+        }
+    }
+
+    /// Whether `value` compares to `p_value` according to the operator
+    pub fn is_satisfied_by(&self, value: i32, p_value: i32) -> bool {
+        match self {
+            PredicateTypes::GE => value >= p_value,
+            PredicateTypes::GT => value > p_value,
+            PredicateTypes::LE => value <= p_value,
+            PredicateTypes::LT => value < p_value,
+        }
+    }
+
+    /// The largest value satisfying a `<=` or `<` predicate
+    fn inclusive_upper(&self, p_value: i32) -> Option<i64> {
+        match self {
+            PredicateTypes::LE => Some(p_value as i64),
+            PredicateTypes::LT => Some(p_value as i64 - 1),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for PredicateTypes {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -183,16 +421,212 @@ pub struct RequestedPredicateInfo {
     pub predicate_info: PredicateInfo,
 }
 
+/// Builder for a version 2.0 `PresentationRequest`.
+///
+/// Referents are generated as `attr{n}_referent` and `predicate{n}_referent`, and
+/// returned when each requested attribute or predicate is added.
+#[derive(Debug)]
+pub struct PresentationRequestBuilder {
+    name: String,
+    version: String,
+    nonce: Option<Nonce>,
+    requested_attributes: HashMap<String, AttributeInfo>,
+    requested_predicates: HashMap<String, PredicateInfo>,
+    non_revoked: Option<NonRevocedInterval>,
+}
+
+impl PresentationRequestBuilder {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            nonce: None,
+            requested_attributes: HashMap::new(),
+            requested_predicates: HashMap::new(),
+            non_revoked: None,
+        }
+    }
+
+    /// Use the given nonce instead of generating a new one
+    pub fn with_nonce(&mut self, nonce: Nonce) -> &mut Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Request non-revocation proofs within the interval for the whole presentation
+    pub fn with_non_revoked(&mut self, from: Option<u64>, to: Option<u64>) -> &mut Self {
+        self.non_revoked = Some(NonRevocedInterval { from, to });
+        self
+    }
+
+    pub fn add_requested_attribute(
+        &mut self,
+        name: impl Into<String>,
+        restrictions: Option<Query>,
+    ) -> String {
+        self.add_attribute_info(AttributeInfo {
+            name: Some(name.into()),
+            names: None,
+            restrictions,
+            non_revoked: None,
+        })
+    }
+
+    /// Request a group of attributes to be revealed from a single credential
+    pub fn add_requested_attribute_group(
+        &mut self,
+        names: Vec<String>,
+        restrictions: Option<Query>,
+    ) -> String {
+        self.add_attribute_info(AttributeInfo {
+            name: None,
+            names: Some(names),
+            restrictions,
+            non_revoked: None,
+        })
+    }
+
+    pub fn add_predicate(
+        &mut self,
+        name: impl Into<String>,
+        p_type: PredicateTypes,
+        p_value: i32,
+        restrictions: Option<Query>,
+    ) -> String {
+        let referent = format!("predicate{}_referent", self.requested_predicates.len() + 1);
+        self.requested_predicates.insert(
+            referent.clone(),
+            PredicateInfo {
+                name: name.into(),
+                p_type,
+                p_value,
+                second_bound: None,
+                restrictions,
+                non_revoked: None,
+            },
+        );
+        referent
+    }
+
+    /// Override the non-revocation interval for a single requested attribute or predicate
+    pub fn set_local_non_revoked(
+        &mut self,
+        referent: &str,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<&mut Self, ValidationError> {
+        let interval = Some(NonRevocedInterval { from, to });
+        if let Some(info) = self.requested_attributes.get_mut(referent) {
+            info.non_revoked = interval;
+        } else if let Some(info) = self.requested_predicates.get_mut(referent) {
+            info.non_revoked = interval;
+        } else {
+            return Err(invalid!(MissingField, "Unknown referent: {}", referent));
+        }
+        Ok(self)
+    }
+
+    /// Build the presentation request. The builder is left unchanged, so that the
+    /// setters can be chained before `build`, and each request built without a
+    /// nonce from `with_nonce` gets a new one.
+    pub fn build(&self) -> Result<PresentationRequest, ValidationError> {
+        self.check_non_revoked()?;
+
+        let nonce = match self.nonce.as_ref() {
+            Some(nonce) => nonce.try_clone(),
+            None => Nonce::new(),
+        }
+        .map_err(|err| invalid!("Error creating nonce: {}", err))?;
+        let request = PresentationRequest::PresentationRequestV2(PresentationRequestPayload {
+            nonce,
+            name: self.name.clone(),
+            version: self.version.clone(),
+            requested_attributes: self.requested_attributes.clone(),
+            requested_predicates: self.requested_predicates.clone(),
+            non_revoked: self.non_revoked.clone(),
+        });
+        request.validate()?;
+        Ok(request)
+    }
+
+    fn add_attribute_info(&mut self, info: AttributeInfo) -> String {
+        let referent = format!("attr{}_referent", self.requested_attributes.len() + 1);
+        self.requested_attributes.insert(referent.clone(), info);
+        referent
+    }
+
+    // A predicate over an attribute of a requested attribute group, with the same
+    // restrictions, would usually be answered by the same credential and so must
+    // accept a common non-revocation timestamp
+    fn check_non_revoked(&self) -> Result<(), ValidationError> {
+        for (pred_referent, predicate) in self.requested_predicates.iter() {
+            let pred_interval = predicate.non_revoked.as_ref().or(self.non_revoked.as_ref());
+            for (attr_referent, attribute) in self.requested_attributes.iter() {
+                let names = match attribute.names.as_ref() {
+                    Some(names) => names,
+                    None => continue,
+                };
+                if attribute.restrictions != predicate.restrictions
+                    || !names
+                        .iter()
+                        .any(|name| attr_common_view(name) == attr_common_view(&predicate.name))
+                {
+                    continue;
+                }
+                let attr_interval = attribute.non_revoked.as_ref().or(self.non_revoked.as_ref());
+                if let (Some(pred_interval), Some(attr_interval)) = (pred_interval, attr_interval) {
+                    if !pred_interval.overlaps(attr_interval) {
+                        return Err(invalid!(
+                            InconsistentPredicate,
+                            "Predicate {:?} and attribute group {:?} have conflicting non-revoked intervals",
+                            pred_referent,
+                            attr_referent
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Validatable for PresentationRequest {
     fn validate(&self) -> Result<(), ValidationError> {
         let value = self.value();
         let version = self.version();
 
         if value.requested_attributes.is_empty() && value.requested_predicates.is_empty() {
-            return Err(invalid!("Presentation request validation failed: both `requested_attributes` and `requested_predicates` are empty"));
+            return Err(invalid!(MissingField, "Presentation request validation failed: both `requested_attributes` and `requested_predicates` are empty"));
         }
 
-        for (_, requested_attribute) in value.requested_attributes.iter() {
+        for (referent, requested_attribute) in value.requested_attributes.iter() {
+            if let Some(requested_predicate) = value.requested_predicates.get(referent) {
+                return Err(invalid!(
+                    InconsistentPredicate,
+                    "Presentation request validation failed: referent {:?} is used for both a requested attribute and a requested predicate on {:?}",
+                    referent,
+                    requested_predicate.name
+                ));
+            }
+
+            if let Some(names) = requested_attribute.names.as_ref() {
+                if names.is_empty() {
+                    return Err(invalid!(
+                        MissingField,
+                        "Presentation request validation failed: requested attribute {:?} has an empty `names` array",
+                        referent
+                    ));
+                }
+                let mut unique = HashSet::new();
+                if let Some(name) = names.iter().find(|name| !unique.insert(*name)) {
+                    return Err(invalid!(
+                        "Presentation request validation failed: requested attribute {:?} lists {:?} more than once",
+                        referent,
+                        name
+                    ));
+                }
+            }
+
             let has_name = !requested_attribute
                 .name
                 .as_ref()
@@ -205,6 +639,7 @@ impl Validatable for PresentationRequest {
                 .unwrap_or(true);
             if !has_name && !has_names {
                 return Err(invalid!(
+                    MissingField,
                     "Presentation request validation failed: there is empty requested attribute: {:?}",
                     requested_attribute
                 ));
@@ -219,13 +654,25 @@ impl Validatable for PresentationRequest {
             }
         }
 
-        for (_, requested_predicate) in value.requested_predicates.iter() {
+        for (referent, requested_predicate) in value.requested_predicates.iter() {
             if requested_predicate.name.is_empty() {
                 return Err(invalid!(
+                    MissingField,
                     "Presentation request validation failed: there is empty requested attribute: {:?}",
                     requested_predicate
                 ));
             }
+            requested_predicate.validate_range()?;
+            if !requested_predicate.is_satisfiable() {
+                return Err(invalid!(
+                    InconsistentPredicate,
+                    "Presentation request validation failed: predicate {:?} ({} {} {}) can never be satisfied",
+                    referent,
+                    requested_predicate.name,
+                    requested_predicate.p_type,
+                    requested_predicate.p_value
+                ));
+            }
             if let Some(ref restrictions) = requested_predicate.restrictions {
                 _process_operator(restrictions, &version)?;
             }
@@ -279,6 +726,12 @@ fn _check_restriction(
     tag_value: &str,
     version: &PresentationRequestVersion,
 ) -> Result<(), ValidationError> {
+    if !is_restriction_tag(tag_name) {
+        return Err(invalid!(
+            "Presentation request validation failed: unknown restriction {:?}",
+            tag_name
+        ));
+    }
     if *version == PresentationRequestVersion::V1
         && Credential::QUALIFIABLE_TAGS.contains(&tag_name)
         && validation::is_uri_identifier(tag_value)
@@ -293,6 +746,108 @@ fn _check_restriction(
 mod tests {
     use super::*;
 
+    mod unknown_fields {
+        use super::*;
+
+        fn _req_json() -> Value {
+            json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "2.0",
+                "requested_attributes": {
+                    "attr1_referent": {"name": "name", "restrictons": {"cred_def_id": "mock:uri"}}
+                },
+                "requested_predicates": {},
+            })
+        }
+
+        #[cfg(not(feature = "serde_strict"))]
+        #[test]
+        fn presentation_request_ignores_unknown_fields() {
+            serde_json::from_value::<PresentationRequest>(_req_json()).unwrap();
+        }
+
+        #[cfg(feature = "serde_strict")]
+        #[test]
+        fn presentation_request_rejects_unknown_fields() {
+            serde_json::from_value::<PresentationRequest>(_req_json()).unwrap_err();
+
+            let mut req_json = _req_json();
+            req_json["requested_attributes"] = json!({});
+            req_json["ver"] = json!("2.0");
+            serde_json::from_value::<PresentationRequest>(req_json).unwrap();
+        }
+    }
+
+    mod version {
+        use super::*;
+
+        fn _req_json(ver: Option<&str>) -> serde_json::Value {
+            let mut req = json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "1.0",
+                "requested_attributes": {},
+                "requested_predicates": {},
+            });
+            if let Some(ver) = ver {
+                req["ver"] = json!(ver);
+            }
+            req
+        }
+
+        #[test]
+        fn presentation_request_records_version() {
+            for (ver, expected) in [
+                (None, PresentationRequestVersion::V1),
+                (Some("1.0"), PresentationRequestVersion::V1),
+                (Some("2.0"), PresentationRequestVersion::V2),
+            ] {
+                let req = serde_json::from_value::<PresentationRequest>(_req_json(ver)).unwrap();
+                assert_eq!(req.version(), expected);
+            }
+        }
+
+        #[test]
+        fn presentation_request_rejects_unknown_version() {
+            for ver in ["3.0", "2", ""] {
+                serde_json::from_value::<PresentationRequest>(_req_json(Some(ver))).unwrap_err();
+            }
+        }
+    }
+
+    mod duplicate_referents {
+        use super::*;
+
+        fn _req_json(requested: &str) -> String {
+            format!(
+                r#"{{"nonce": "123456", "name": "name", "version": "1.0", {}}}"#,
+                requested
+            )
+        }
+
+        #[test]
+        fn presentation_request_rejects_duplicate_referents() {
+            for requested in [
+                r#""requested_attributes": {"attr1_referent": {"name": "name"}, "attr1_referent": {"name": "age"}}"#,
+                r#""requested_predicates": {"predicate1_referent": {"name": "age", "p_type": ">=", "p_value": 18}, "predicate1_referent": {"name": "age", "p_type": "<", "p_value": 65}}"#,
+            ] {
+                let req_json = _req_json(requested);
+                serde_json::from_str::<PresentationRequest>(&req_json).unwrap_err();
+                serde_json::from_str::<PresentationRequestPayload>(&req_json).unwrap_err();
+            }
+        }
+
+        #[test]
+        fn presentation_request_accepts_distinct_referents() {
+            let req_json = _req_json(
+                r#""requested_attributes": {"attr1_referent": {"name": "name"}, "attr2_referent": {"name": "age"}}"#,
+            );
+            let req = serde_json::from_str::<PresentationRequest>(&req_json).unwrap();
+            assert_eq!(req.value().requested_attributes.len(), 2);
+        }
+    }
+
     mod invalid_nonce {
         use super::*;
 
@@ -330,4 +885,390 @@ mod tests {
             serde_json::from_str::<PresentationRequest>(&req_json).unwrap_err();
         }
     }
+
+    mod builder {
+        use super::*;
+        use crate::error::ValidationErrorKind;
+
+        #[test]
+        fn presentation_request_builder_works() {
+            let mut builder = PresentationRequestBuilder::new("proof", "1.0");
+            let attr = builder.add_requested_attribute(
+                "name",
+                Some(Query::Eq("cred_def_id".to_string(), "mock:uri".to_string())),
+            );
+            let group = builder.add_requested_attribute_group(
+                vec!["name".to_string(), "height".to_string()],
+                None,
+            );
+            let pred = builder.add_predicate("age", PredicateTypes::GE, 18, None);
+            assert_ne!(attr, group);
+
+            let req = builder
+                .with_non_revoked(Some(10), Some(20))
+                .build()
+                .unwrap();
+            // a new nonce is generated for each request
+            assert_ne!(builder.build().unwrap().value().nonce, req.value().nonce);
+            assert_eq!(req.version(), PresentationRequestVersion::V2);
+            let payload = req.value();
+            assert_eq!(
+                payload.requested_attributes[&attr].name.as_deref(),
+                Some("name")
+            );
+            assert_eq!(
+                payload.requested_attributes[&group]
+                    .names
+                    .as_ref()
+                    .unwrap()
+                    .len(),
+                2
+            );
+            assert_eq!(payload.requested_predicates[&pred].p_value, 18);
+            assert_eq!(
+                payload.non_revoked,
+                Some(NonRevocedInterval {
+                    from: Some(10),
+                    to: Some(20)
+                })
+            );
+        }
+
+        #[test]
+        fn presentation_request_builder_runs_validation() {
+            let builder = PresentationRequestBuilder::new("proof", "1.0");
+            assert_eq!(
+                builder.build().unwrap_err().kind(),
+                ValidationErrorKind::MissingField
+            );
+        }
+
+        #[test]
+        fn presentation_request_builder_rejects_conflicting_non_revoked() {
+            let mut builder = PresentationRequestBuilder::new("proof", "1.0");
+            let group = builder
+                .add_requested_attribute_group(vec!["name".to_string(), "age".to_string()], None);
+            let pred = builder.add_predicate("age", PredicateTypes::GE, 18, None);
+            builder
+                .set_local_non_revoked(&group, Some(10), Some(20))
+                .unwrap();
+            builder
+                .set_local_non_revoked(&pred, Some(30), None)
+                .unwrap();
+            assert_eq!(
+                builder.build().unwrap_err().kind(),
+                ValidationErrorKind::InconsistentPredicate
+            );
+        }
+
+        #[test]
+        fn non_revoked_interval_overlaps() {
+            let interval = |from, to| NonRevocedInterval { from, to };
+            assert!(interval(Some(10), Some(20)).overlaps(&interval(Some(20), None)));
+            assert!(interval(None, None).overlaps(&interval(Some(5), Some(6))));
+            assert!(!interval(Some(10), Some(20)).overlaps(&interval(None, Some(9))));
+            assert!(!interval(Some(10), Some(20)).overlaps(&interval(Some(21), Some(30))));
+        }
+    }
+
+    mod validation {
+        use super::*;
+        use crate::error::ValidationErrorKind;
+
+        fn request(attributes: Value, predicates: Value) -> PresentationRequest {
+            serde_json::from_value(json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "2.0",
+                "requested_attributes": attributes,
+                "requested_predicates": predicates,
+            }))
+            .unwrap()
+        }
+
+        #[test]
+        fn presentation_request_valid() {
+            let req = request(
+                json!({
+                    "attr1_referent": {"name": "name"},
+                    "attr2_referent": {"names": ["name", "height"]},
+                }),
+                json!({
+                    "predicate1_referent": {"name": "age", "p_type": ">=", "p_value": 18},
+                }),
+            );
+            req.validate().unwrap();
+        }
+
+        #[test]
+        fn presentation_request_empty() {
+            let req = request(json!({}), json!({}));
+            assert_eq!(
+                req.validate().unwrap_err().kind(),
+                ValidationErrorKind::MissingField
+            );
+        }
+
+        #[test]
+        fn presentation_request_unknown_predicate_operator() {
+            serde_json::from_value::<PresentationRequest>(json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "2.0",
+                "requested_predicates": {
+                    "predicate1_referent": {"name": "age", "p_type": "==", "p_value": 18},
+                },
+            }))
+            .unwrap_err();
+        }
+
+        #[test]
+        fn presentation_request_unsatisfiable_predicate() {
+            let req = request(
+                json!({}),
+                json!({
+                    "predicate1_referent": {"name": "age", "p_type": ">", "p_value": i32::MAX},
+                }),
+            );
+            req.validate().unwrap_err();
+
+            let req = request(
+                json!({}),
+                json!({
+                    "predicate1_referent": {"name": "age", "p_type": "<", "p_value": i32::MIN},
+                }),
+            );
+            req.validate().unwrap_err();
+        }
+
+        #[test]
+        fn presentation_request_range_predicate() {
+            let req = request(
+                json!({}),
+                json!({
+                    "predicate1_referent": {
+                        "name": "age",
+                        "p_type": ">=",
+                        "p_value": 18,
+                        "second_bound": {"p_type": "<", "p_value": 66},
+                    },
+                }),
+            );
+            req.validate().unwrap();
+            let predicate = &req.value().requested_predicates["predicate1_referent"];
+            assert_eq!(
+                predicate.bounds().collect::<Vec<_>>(),
+                vec![(&PredicateTypes::GE, 18), (&PredicateTypes::LT, 66)]
+            );
+
+            // the bounds may be given in either order
+            let req = request(
+                json!({}),
+                json!({
+                    "predicate1_referent": {
+                        "name": "age",
+                        "p_type": "<=",
+                        "p_value": 18,
+                        "second_bound": {"p_type": ">=", "p_value": 18},
+                    },
+                }),
+            );
+            req.validate().unwrap();
+        }
+
+        #[test]
+        fn presentation_request_invalid_range_predicate() {
+            let req = request(
+                json!({}),
+                json!({
+                    "predicate1_referent": {
+                        "name": "age",
+                        "p_type": ">",
+                        "p_value": 65,
+                        "second_bound": {"p_type": "<=", "p_value": 65},
+                    },
+                }),
+            );
+            req.validate().unwrap_err();
+
+            let req = request(
+                json!({}),
+                json!({
+                    "predicate1_referent": {
+                        "name": "age",
+                        "p_type": ">=",
+                        "p_value": 18,
+                        "second_bound": {"p_type": ">=", "p_value": 65},
+                    },
+                }),
+            );
+            req.validate().unwrap_err();
+        }
+
+        #[test]
+        fn presentation_request_shared_referent() {
+            let req = request(
+                json!({
+                    "referent": {"name": "age"},
+                }),
+                json!({
+                    "referent": {"name": "age", "p_type": ">=", "p_value": 18},
+                }),
+            );
+            assert_eq!(
+                req.validate().unwrap_err().kind(),
+                ValidationErrorKind::InconsistentPredicate
+            );
+        }
+
+        #[test]
+        fn presentation_request_empty_names() {
+            let req = request(
+                json!({
+                    "attr1_referent": {"name": "name", "names": []},
+                }),
+                json!({}),
+            );
+            req.validate().unwrap_err();
+        }
+
+        #[test]
+        fn presentation_request_duplicate_names() {
+            let req = request(
+                json!({
+                    "attr1_referent": {"names": ["name", "name"]},
+                }),
+                json!({}),
+            );
+            req.validate().unwrap_err();
+        }
+
+        #[test]
+        fn presentation_request_name_and_names() {
+            let req = request(
+                json!({
+                    "attr1_referent": {"name": "name", "names": ["height"]},
+                }),
+                json!({}),
+            );
+            req.validate().unwrap_err();
+        }
+    }
+
+    mod restrictions {
+        use super::*;
+
+        #[test]
+        fn restriction_tags() {
+            for tag in RESTRICTION_TAGS {
+                assert!(is_restriction_tag(tag));
+            }
+            assert!(is_restriction_tag("attr::name::value"));
+            assert!(is_restriction_tag("attr::name::marker"));
+            assert!(!is_restriction_tag("attr::::value"));
+            assert!(!is_restriction_tag("attr::name::other"));
+            assert!(!is_restriction_tag("attr::a::b::value"));
+            assert!(!is_restriction_tag("cred_def"));
+        }
+
+        #[test]
+        fn parse_restriction_tags() {
+            assert_eq!(
+                RestrictionTag::parse("schema_issuer_did"),
+                Some(RestrictionTag::SchemaIssuerDid)
+            );
+            assert_eq!(
+                RestrictionTag::parse("issuer_id"),
+                Some(RestrictionTag::IssuerId)
+            );
+            assert_eq!(
+                RestrictionTag::parse("attr::name::value"),
+                Some(RestrictionTag::AttributeValue("name"))
+            );
+            assert_eq!(
+                RestrictionTag::parse("attr::name::marker"),
+                Some(RestrictionTag::AttributeMarker("name"))
+            );
+            assert_eq!(RestrictionTag::parse("attr::name"), None);
+            assert_eq!(RestrictionTag::parse("Schema_id"), None);
+
+            assert!(RestrictionTag::SchemaName.needs_schema());
+            assert!(!RestrictionTag::SchemaId.needs_schema());
+            assert!(RestrictionTag::IssuerDid.needs_cred_def());
+            assert!(!RestrictionTag::CredDefId.needs_cred_def());
+        }
+
+        #[test]
+        fn nested_query_round_trip() {
+            let query = Query::Or(vec![
+                Query::And(vec![
+                    Query::Eq("schema_id".to_string(), "mock:schema".to_string()),
+                    Query::Not(Box::new(Query::Eq(
+                        "attr::name::value".to_string(),
+                        "Alex".to_string(),
+                    ))),
+                ]),
+                Query::And(vec![
+                    Query::In(
+                        "cred_def_id".to_string(),
+                        vec!["mock:cred_def_1".to_string(), "mock:cred_def_2".to_string()],
+                    ),
+                    Query::Or(vec![
+                        Query::Eq("issuer_id".to_string(), "mock:issuer".to_string()),
+                        Query::Eq("attr::age::marker".to_string(), "1".to_string()),
+                    ]),
+                ]),
+            ]);
+            let json = serde_json::to_value(&query).unwrap();
+            assert_eq!(
+                json,
+                json!({"$or": [
+                    {"$and": [
+                        {"schema_id": "mock:schema"},
+                        {"$not": {"attr::name::value": "Alex"}},
+                    ]},
+                    {"$and": [
+                        {"cred_def_id": {"$in": ["mock:cred_def_1", "mock:cred_def_2"]}},
+                        {"$or": [
+                            {"issuer_id": "mock:issuer"},
+                            {"attr::age::marker": "1"},
+                        ]},
+                    ]},
+                ]})
+            );
+            assert_eq!(serde_json::from_value::<Query>(json).unwrap(), query);
+
+            let req: PresentationRequest = serde_json::from_value(json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "2.0",
+                "requested_attributes": {
+                    "attr1_referent": {"name": "name", "restrictions": query},
+                },
+            }))
+            .unwrap();
+            req.validate().unwrap();
+            assert_eq!(
+                req.value().requested_attributes["attr1_referent"].restrictions,
+                Some(query)
+            );
+        }
+
+        #[test]
+        fn unknown_restriction_key() {
+            let req: PresentationRequest = serde_json::from_value(json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "2.0",
+                "requested_attributes": {
+                    "attr1_referent": {
+                        "name": "name",
+                        "restrictions": {"$or": [{"cred_def_id": "mock:uri"}, {"cred_def": "mock:uri"}]},
+                    },
+                },
+            }))
+            .unwrap();
+            req.validate().unwrap_err();
+        }
+    }
 }
@@ -3,7 +3,10 @@ use std::collections::HashMap;
 use crate::error::ValidationError;
 use crate::utils::validation::Validatable;
 
-use super::{cred_def::CredentialDefinitionId, rev_reg::RevocationRegistryId, schema::SchemaId};
+use super::{
+    cred_def::CredentialDefinitionId, rev_reg::RevocationRegistryId,
+    rev_reg_def::RevocationRegistryDefinitionId, schema::SchemaId,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Presentation {
@@ -12,6 +15,116 @@ pub struct Presentation {
     pub identifiers: Vec<Identifier>,
 }
 
+impl Presentation {
+    /// The schema, credential definition and revocation registry referenced by
+    /// each sub-proof, available without verifying the proof
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        self.identifiers.clone()
+    }
+
+    /// The revocation registries referenced by the presentation along with the
+    /// timestamp of the revocation status list used, without duplicates
+    pub fn referenced_revocation(&self) -> Vec<(RevocationRegistryDefinitionId, Option<u64>)> {
+        let mut referenced = Vec::new();
+        for identifier in self.identifiers.iter() {
+            if let Some(rev_reg_id) = identifier.rev_reg_id.as_ref() {
+                let entry = (
+                    RevocationRegistryDefinitionId::new_unchecked(rev_reg_id.0.clone()),
+                    identifier.timestamp,
+                );
+                if !referenced.contains(&entry) {
+                    referenced.push(entry);
+                }
+            }
+        }
+        referenced
+    }
+
+    /// The schema, credential definition and revocation registry references which
+    /// still use the legacy identifier format, along with the referents of the
+    /// presentation request they were used for. This only inspects the parsed
+    /// presentation and does not verify the proof.
+    pub fn legacy_identifiers(&self) -> Vec<LegacyIdentifierUse> {
+        legacy_identifier_uses(&self.identifiers, &self.requested_proof)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl Presentation {
+    /// Encode the presentation as compact CBOR, which round-trips
+    /// with the JSON representation. The big integers of the primary proofs
+    /// are stored as bytes, while the group elements of non-revocation proofs
+    /// are kept as text.
+    pub fn to_cbor(&self) -> crate::error::Result<Vec<u8>> {
+        crate::utils::cbor::to_cbor(self)
+    }
+
+    /// Decode the presentation from CBOR produced by `to_cbor`
+    pub fn from_cbor(bytes: &[u8]) -> crate::error::Result<Self> {
+        crate::utils::cbor::from_cbor(bytes)
+    }
+}
+
+/// The kind of object referenced by a legacy identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LegacyObjectType {
+    Schema,
+    CredentialDefinition,
+    RevocationRegistry,
+}
+
+/// A reference to an object using a legacy identifier within a presentation
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyIdentifierUse {
+    /// The index of the sub-proof (and entry of `identifiers`) with the reference
+    pub sub_proof_index: u32,
+    /// The referents of the presentation request satisfied by the sub-proof, sorted
+    pub referents: Vec<String>,
+    pub object_type: LegacyObjectType,
+    pub id: String,
+}
+
+fn legacy_identifier_uses(
+    identifiers: &[Identifier],
+    requested_proof: &RequestedProof,
+) -> Vec<LegacyIdentifierUse> {
+    let mut uses = Vec::new();
+    for (index, identifier) in identifiers.iter().enumerate() {
+        let mut legacy = Vec::new();
+        if identifier.schema_id.is_legacy() {
+            legacy.push((LegacyObjectType::Schema, identifier.schema_id.to_string()));
+        }
+        if identifier.cred_def_id.is_legacy() {
+            legacy.push((
+                LegacyObjectType::CredentialDefinition,
+                identifier.cred_def_id.to_string(),
+            ));
+        }
+        if let Some(rev_reg_id) = identifier.rev_reg_id.as_ref().filter(|id| id.is_legacy()) {
+            legacy.push((LegacyObjectType::RevocationRegistry, rev_reg_id.to_string()));
+        }
+        if legacy.is_empty() {
+            continue;
+        }
+
+        let sub_proof_index = index as u32;
+        let referents = requested_proof.referents_for(sub_proof_index);
+        uses.extend(
+            legacy
+                .into_iter()
+                .map(|(object_type, id)| LegacyIdentifierUse {
+                    sub_proof_index,
+                    referents: referents.clone(),
+                    object_type,
+                    id,
+                }),
+        );
+    }
+    uses
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, Default)]
 pub struct RequestedProof {
     pub revealed_attrs: HashMap<String, RevealedAttributeInfo>,
@@ -26,6 +139,34 @@ pub struct RequestedProof {
     pub predicates: HashMap<String, SubProofReferent>,
 }
 
+impl RequestedProof {
+    /// The referents of the attributes and predicates proven by a sub-proof, sorted
+    fn referents_for(&self, sub_proof_index: u32) -> Vec<String> {
+        let mut referents: Vec<String> = self
+            .revealed_attrs
+            .iter()
+            .filter(|(_, info)| info.sub_proof_index == sub_proof_index)
+            .map(|(referent, _)| referent)
+            .chain(
+                self.revealed_attr_groups
+                    .iter()
+                    .filter(|(_, info)| info.sub_proof_index == sub_proof_index)
+                    .map(|(referent, _)| referent),
+            )
+            .chain(
+                self.unrevealed_attrs
+                    .iter()
+                    .chain(self.predicates.iter())
+                    .filter(|(_, info)| info.sub_proof_index == sub_proof_index)
+                    .map(|(referent, _)| referent),
+            )
+            .cloned()
+            .collect();
+        referents.sort();
+        referents
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct SubProofReferent {
     pub sub_proof_index: u32,
@@ -92,4 +233,86 @@ mod tests {
         let req_proof: RequestedProof = serde_json::from_str(&json).unwrap();
         assert!(req_proof.revealed_attr_groups.is_empty())
     }
+
+    #[test]
+    fn legacy_identifier_uses_report_referents() {
+        let identifiers = vec![
+            Identifier {
+                schema_id: SchemaId::new_unchecked("mock:schema"),
+                cred_def_id: CredentialDefinitionId::new_unchecked("mock:cred_def"),
+                rev_reg_id: None,
+                timestamp: None,
+            },
+            Identifier {
+                schema_id: SchemaId::new_unchecked("NcYxiDXkpYi6ov5FcYDi1e:2:gvt:1.0"),
+                cred_def_id: CredentialDefinitionId::new_unchecked(
+                    "NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag",
+                ),
+                rev_reg_id: Some(RevocationRegistryId::new_unchecked(
+                    "NcYxiDXkpYi6ov5FcYDi1e:4:NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag:CL_ACCUM:default",
+                )),
+                timestamp: Some(1),
+            },
+            Identifier {
+                schema_id: SchemaId::new_unchecked("mock:schema"),
+                cred_def_id: CredentialDefinitionId::new_unchecked(
+                    "NcYxiDXkpYi6ov5FcYDi1e:3:CL:2:tag",
+                ),
+                rev_reg_id: None,
+                timestamp: None,
+            },
+        ];
+        let mut requested_proof = RequestedProof::default();
+        requested_proof.revealed_attrs.insert(
+            "attr1".to_string(),
+            RevealedAttributeInfo {
+                sub_proof_index: 1,
+                raw: "123".to_string(),
+                encoded: "123".to_string(),
+            },
+        );
+        for (referent, sub_proof_index) in [("attr2", 0), ("attr3", 1)] {
+            requested_proof
+                .unrevealed_attrs
+                .insert(referent.to_string(), SubProofReferent { sub_proof_index });
+        }
+        requested_proof
+            .predicates
+            .insert("pred1".to_string(), SubProofReferent { sub_proof_index: 2 });
+        requested_proof
+            .self_attested_attrs
+            .insert("self1".to_string(), "value".to_string());
+
+        let uses = legacy_identifier_uses(&identifiers, &requested_proof);
+        let summary: Vec<_> = uses
+            .iter()
+            .map(|u| (u.sub_proof_index, u.referents.clone(), u.object_type))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    1,
+                    vec!["attr1".to_string(), "attr3".to_string()],
+                    LegacyObjectType::Schema
+                ),
+                (
+                    1,
+                    vec!["attr1".to_string(), "attr3".to_string()],
+                    LegacyObjectType::CredentialDefinition
+                ),
+                (
+                    1,
+                    vec!["attr1".to_string(), "attr3".to_string()],
+                    LegacyObjectType::RevocationRegistry
+                ),
+                (
+                    2,
+                    vec!["pred1".to_string()],
+                    LegacyObjectType::CredentialDefinition
+                ),
+            ]
+        );
+        assert_eq!(uses[3].id, "NcYxiDXkpYi6ov5FcYDi1e:3:CL:2:tag");
+    }
 }
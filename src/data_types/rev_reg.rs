@@ -2,14 +2,19 @@ use bitvec::vec::BitVec;
 use serde::{
     de::{Deserializer, Error as DeError, SeqAccess, Visitor},
     ser::{SerializeSeq, Serializer},
+    Deserialize, Serialize,
 };
 use std::collections::{BTreeSet, HashSet};
+use std::fmt;
 
 use crate::{error, impl_anoncreds_object_identifier};
 
 use super::rev_reg_def::RevocationRegistryDefinitionId;
 
-impl_anoncreds_object_identifier!(RevocationRegistryId);
+impl_anoncreds_object_identifier!(
+    RevocationRegistryId,
+    crate::utils::validation::is_legacy_rev_reg_id
+);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RevocationRegistry {
@@ -27,12 +32,147 @@ impl RevocationRegistry {
     }
 }
 
+/// The accumulator of a revocation registry.
+///
+/// Accumulators are compared, hashed and serialized (as hex) using the canonical
+/// encoding of the accumulator point, so that the same accumulator always matches
+/// regardless of how its coordinates were computed.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Accumulator {
+    bytes: Vec<u8>,
+}
+
+impl Accumulator {
+    pub(crate) fn from_registry(
+        registry: &ursa::cl::RevocationRegistry,
+    ) -> Result<Self, error::Error> {
+        #[derive(Deserialize)]
+        struct Parts {
+            accum: ursa::cl::Tail,
+        }
+
+        let parts: Parts = serde_json::from_value(serde_json::to_value(registry)?)?;
+        Ok(Self {
+            bytes: parts.accum.to_bytes()?,
+        })
+    }
+
+    /// The canonical encoding of the accumulator point
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn from_hex(value: &str) -> Result<Self, error::Error> {
+        if value.len() % 2 != 0 || !value.is_ascii() {
+            return Err(err_msg!("Invalid accumulator encoding"));
+        }
+        let bytes = (0..value.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&value[idx..idx + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| err_msg!("Invalid accumulator encoding"))?;
+        // ensure the value is a valid point, and normalize its encoding
+        let point =
+            ursa::cl::Tail::from_bytes(&bytes).map_err(err_map!("Invalid accumulator encoding"))?;
+        Ok(Self {
+            bytes: point.to_bytes()?,
+        })
+    }
+}
+
+impl fmt::Debug for Accumulator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Accumulator").field(&self.to_hex()).finish()
+    }
+}
+
+impl Serialize for Accumulator {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Accumulator {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Accumulator::from_hex(&value).map_err(D::Error::custom)
+    }
+}
+
+/// Check that `idx` can be assigned to a credential issued in a registry of
+/// `max_cred_num` credentials: it must be greater than 0 and less than `max_cred_num`.
+pub fn check_revocation_index(idx: u32, max_cred_num: u32) -> Result<(), error::Error> {
+    if idx == 0 || idx >= max_cred_num {
+        return Err(error::Error::from_msg(
+            error::ErrorKind::InvalidRevocationIndex {
+                idx,
+                max: max_cred_num,
+            },
+            format!(
+                "Invalid revocation index {}: must be greater than 0 and less than {}",
+                idx, max_cred_num
+            ),
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RevocationRegistryDelta {
     pub value: ursa::cl::RevocationRegistryDelta,
 }
 
+impl RevocationRegistryDelta {
+    /// Check that every index issued or revoked by the delta is within
+    /// `1..=max_cred_num`, and that no index is both issued and revoked.
+    ///
+    /// Deltas fetched from a ledger should be checked before they are applied to
+    /// a witness or an accumulator, as out of range indices cannot be mapped to
+    /// tails.
+    pub fn validate_indices(&self, max_cred_num: u32) -> Result<(), error::Error> {
+        let value = serde_json::to_value(&self.value)?;
+        let indices = |name: &str| -> Result<BTreeSet<u32>, error::Error> {
+            match value.get(name) {
+                Some(indices) => Ok(serde_json::from_value(indices.clone())?),
+                None => Ok(BTreeSet::new()),
+            }
+        };
+        let issued = indices("issued")?;
+        let revoked = indices("revoked")?;
+
+        if let Some(idx) = issued
+            .iter()
+            .chain(revoked.iter())
+            .find(|&&idx| idx == 0 || idx > max_cred_num)
+        {
+            return Err(err_msg!(
+                InvalidUserRevocId,
+                "Revocation registry delta index {} is out of range: must be between 1 and {}",
+                idx,
+                max_cred_num
+            ));
+        }
+        if let Some(idx) = issued.intersection(&revoked).next() {
+            return Err(err_msg!(
+                "Revocation registry delta index {} is both issued and revoked",
+                idx
+            ));
+        }
+        Ok(())
+    }
+}
+
 impl Validatable for RevocationRegistryDelta {}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -48,6 +188,26 @@ pub struct RevocationStatusList {
     timestamp: Option<u64>,
 }
 
+/// The changes between two states of a revocation status list
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationStatusDelta {
+    /// Indices which were revoked in the previous state and are not revoked in the current one
+    pub issued: BTreeSet<u32>,
+    /// Indices which are newly revoked in the current state
+    pub revoked: BTreeSet<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_timestamp: Option<u64>,
+}
+
+impl RevocationStatusDelta {
+    pub fn is_empty(&self) -> bool {
+        self.issued.is_empty() && self.revoked.is_empty()
+    }
+}
+
 impl From<&RevocationStatusList> for Option<ursa::cl::RevocationRegistry> {
     fn from(rev_status_list: &RevocationStatusList) -> Option<ursa::cl::RevocationRegistry> {
         rev_status_list.registry.clone()
@@ -76,6 +236,59 @@ impl RevocationStatusList {
         &self.revocation_list
     }
 
+    /// The number of entries of the status list
+    pub fn len(&self) -> usize {
+        self.revocation_list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revocation_list.is_empty()
+    }
+
+    /// Whether the entry `idx` of the status list is revoked (or not yet issued),
+    /// or `None` if it is out of range
+    pub fn is_revoked(&self, idx: u32) -> Option<bool> {
+        self.revocation_list.get(idx as usize).map(|bit| *bit)
+    }
+
+    /// Mark the revocation index `idx` as revoked, or as issued when `revoked` is
+    /// false. The index must be one which can be assigned to a credential, see
+    /// `check_revocation_index`.
+    ///
+    /// Only the bitmap is changed: the accumulator is not updated, so the list no
+    /// longer matches its registry. Use `issuer::update_revocation_status_list` to
+    /// produce a status list to publish.
+    pub fn set_revoked(&mut self, idx: u32, revoked: bool) -> Result<(), error::Error> {
+        check_revocation_index(idx, self.revocation_list.len() as u32)?;
+        self.set_bit(idx, revoked)
+    }
+
+    // Only changes the bitmap: the accumulator must be updated by the caller
+    fn set_bit(&mut self, idx: u32, revoked: bool) -> Result<(), error::Error> {
+        let mut bit = self.revocation_list.get_mut(idx as usize).ok_or_else(|| {
+            error::Error::from_msg(
+                crate::ErrorKind::Unexpected,
+                "Update Revocation List Index Out of Range",
+            )
+        })?;
+        *bit = revoked;
+        Ok(())
+    }
+
+    /// The current accumulator of the registry, if the status list includes one.
+    ///
+    /// The accumulator is not stored with the list but extracted on each call: ursa
+    /// only exposes it through the serialized form of the registry, and the
+    /// conversion fails when the registry does not hold a valid point. Storing it
+    /// would mean validating the registry wherever one is set, making
+    /// `set_registry` fallible and rejecting such status lists when they are parsed.
+    pub fn accumulator(&self) -> Result<Option<Accumulator>, error::Error> {
+        self.registry
+            .as_ref()
+            .map(Accumulator::from_registry)
+            .transpose()
+    }
+
     pub fn set_registry(&mut self, registry: ursa::cl::RevocationRegistry) {
         self.registry = Some(registry)
     }
@@ -103,26 +316,14 @@ impl RevocationStatusList {
             // issued credentials are assigned `false`
             // i.e. NOT revoked
             for i in issued {
-                let mut bit = self.revocation_list.get_mut(i as usize).ok_or_else(|| {
-                    error::Error::from_msg(
-                        crate::ErrorKind::Unexpected,
-                        "Update Revocation List Index Out of Range",
-                    )
-                })?;
-                *bit = false;
+                self.set_bit(i, false)?;
             }
         }
         if let Some(revoked) = revoked {
             // revoked credentials are assigned `true`
             // i.e. IS revoked
             for i in revoked {
-                let mut bit = self.revocation_list.get_mut(i as usize).ok_or_else(|| {
-                    error::Error::from_msg(
-                        crate::ErrorKind::Unexpected,
-                        "Update Revocation List Index Out of Range",
-                    )
-                })?;
-                *bit = true;
+                self.set_bit(i, true)?;
             }
         }
         // only update if input is Some
@@ -132,6 +333,84 @@ impl RevocationStatusList {
         Ok(())
     }
 
+    /// Compute the indices which have been issued or revoked since `previous`
+    pub fn diff(
+        &self,
+        previous: &RevocationStatusList,
+    ) -> Result<RevocationStatusDelta, error::Error> {
+        if self.rev_reg_def_id != previous.rev_reg_def_id {
+            return Err(err_msg!(
+                "Revocation status lists reference different revocation registry definitions: {:?} and {:?}",
+                previous.rev_reg_def_id,
+                self.rev_reg_def_id
+            ));
+        }
+        if let (Some(from), Some(to)) = (previous.timestamp, self.timestamp) {
+            if from > to {
+                return Err(err_msg!(
+                    "Previous revocation status list has a later timestamp ({}) than the current one ({})",
+                    from,
+                    to
+                ));
+            }
+        }
+        if self.revocation_list.len() != previous.revocation_list.len() {
+            return Err(err_msg!(
+                "Revocation status lists have different sizes: {} and {}",
+                previous.revocation_list.len(),
+                self.revocation_list.len()
+            ));
+        }
+
+        let mut delta = RevocationStatusDelta {
+            from_timestamp: previous.timestamp,
+            to_timestamp: self.timestamp,
+            ..Default::default()
+        };
+        for (idx, (prev, cur)) in previous
+            .revocation_list
+            .iter()
+            .zip(self.revocation_list.iter())
+            .enumerate()
+        {
+            match (*prev, *cur) {
+                (true, false) => {
+                    delta.issued.insert(idx as u32);
+                }
+                (false, true) => {
+                    delta.revoked.insert(idx as u32);
+                }
+                _ => (),
+            }
+        }
+        Ok(delta)
+    }
+
+    /// Build the revocation registry delta from `previous` to this status list,
+    /// including indices which were recovered after being revoked
+    pub fn registry_delta(
+        &self,
+        previous: &RevocationStatusList,
+    ) -> Result<RevocationRegistryDelta, error::Error> {
+        let delta = self.diff(previous)?;
+        let (from, to) = match (previous.registry.as_ref(), self.registry.as_ref()) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                return Err(err_msg!(
+                    "Revocation status lists must include the accumulator to build a delta"
+                ))
+            }
+        };
+        Ok(RevocationRegistryDelta {
+            value: ursa::cl::RevocationRegistryDelta::from_parts(
+                Some(from),
+                to,
+                &delta.issued.into_iter().collect::<HashSet<_>>(),
+                &delta.revoked.into_iter().collect::<HashSet<_>>(),
+            ),
+        })
+    }
+
     pub fn new(
         rev_reg_def_id: Option<&str>,
         revocation_list: bitvec::vec::BitVec,
@@ -147,6 +426,34 @@ impl RevocationStatusList {
             timestamp,
         })
     }
+
+    /// Create a status list of `max_cred_num` entries from the revoked indices only,
+    /// all other entries being issued. The result is identical to a list created
+    /// with `new` from the full bitmap.
+    pub fn from_sparse(
+        rev_reg_def_id: Option<&str>,
+        max_cred_num: u32,
+        revoked: &[u32],
+        registry: Option<ursa::cl::RevocationRegistry>,
+        timestamp: Option<u64>,
+    ) -> Result<Self, error::Error> {
+        let mut revocation_list = bitvec::bitvec![0; max_cred_num as usize];
+        for &idx in revoked {
+            let mut bit = revocation_list.get_mut(idx as usize).ok_or_else(|| {
+                err_msg!(
+                    InvalidUserRevocId,
+                    "Revoked index {} is out of range: the status list has {} entries",
+                    idx,
+                    max_cred_num
+                )
+            })?;
+            if *bit {
+                return Err(err_msg!("Revoked index {} is listed more than once", idx));
+            }
+            *bit = true;
+        }
+        Self::new(rev_reg_def_id, revocation_list, registry, timestamp)
+    }
 }
 
 pub mod serde_revocation_list {
@@ -241,5 +548,187 @@ mod tests {
             .unwrap();
         assert_eq!(list.get(0usize).unwrap(), false);
         assert_eq!(list.timestamp().unwrap(), 1245);
+        assert_eq!(list.is_revoked(0), Some(false));
+        assert_eq!(list.is_revoked(1), Some(true));
+        assert_eq!(list.is_revoked(4), None);
+        assert!(list
+            .update(None, None, Some(BTreeSet::from([4])), None)
+            .is_err());
+    }
+
+    #[test]
+    fn rev_status_list_accumulator() {
+        let list = serde_json::from_str::<RevocationStatusList>(REVOCATION_LIST).unwrap();
+        let accum = list.accumulator().unwrap().unwrap();
+
+        let ser = serde_json::to_string(&accum).unwrap();
+        assert_eq!(ser.len(), ursa::cl::Tail::BYTES_REPR_SIZE * 2 + 2);
+        let des = serde_json::from_str::<Accumulator>(&ser).unwrap();
+        assert_eq!(des, accum);
+        assert_eq!(des.to_hex(), accum.to_hex());
+
+        // the same point computed differently has the same accumulator
+        let point = ursa::cl::Tail::from_bytes(accum.as_bytes()).unwrap();
+        let other = ursa::cl::Tail::new().unwrap();
+        let same = point.add(&other).unwrap().sub(&other).unwrap();
+        assert_eq!(same.to_bytes().unwrap(), accum.as_bytes());
+
+        let mut without_registry = list.clone();
+        without_registry.registry = None;
+        assert!(without_registry.accumulator().unwrap().is_none());
+
+        assert!(serde_json::from_str::<Accumulator>(r#""00ff""#).is_err());
+        assert!(serde_json::from_str::<Accumulator>(r#""zz""#).is_err());
+    }
+
+    #[test]
+    fn rev_status_list_from_sparse() {
+        let dense = serde_json::from_str::<RevocationStatusList>(REVOCATION_LIST).unwrap();
+        let mut dense_list = bitvec![0; 10];
+        dense_list.set(3, true);
+        dense_list.set(7, true);
+        let dense =
+            RevocationStatusList::new(Some("reg"), dense_list, dense.registry.clone(), Some(1234))
+                .unwrap();
+        let sparse = RevocationStatusList::from_sparse(
+            Some("reg"),
+            10,
+            &[7, 3],
+            dense.registry.clone(),
+            Some(1234),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_vec(&sparse).unwrap(),
+            serde_json::to_vec(&dense).unwrap()
+        );
+
+        let empty = RevocationStatusList::from_sparse(None, 10, &[], None, None).unwrap();
+        assert_eq!(empty.len(), 10);
+        assert!((0..10).all(|idx| empty.is_revoked(idx) == Some(false)));
+
+        assert_kind!(
+            InvalidUserRevocId,
+            RevocationStatusList::from_sparse(None, 10, &[10], None, None)
+        );
+        assert_kind!(
+            Input,
+            RevocationStatusList::from_sparse(None, 10, &[3, 3], None, None)
+        );
+    }
+
+    #[test]
+    fn large_rev_status_list_is_bit_packed() {
+        const SIZE: usize = 1_000_000;
+        let mut json = String::with_capacity(SIZE * 2 + 64);
+        json.push_str(r#"{"revRegDefId": "reg", "revocationList": ["#);
+        for idx in 0..SIZE {
+            if idx > 0 {
+                json.push(',');
+            }
+            json.push(if idx % 3 == 0 { '1' } else { '0' });
+        }
+        json.push_str("]}");
+
+        let list = serde_json::from_str::<RevocationStatusList>(&json).unwrap();
+        assert_eq!(list.len(), SIZE);
+        assert_eq!(list.is_revoked(0), Some(true));
+        assert_eq!(list.is_revoked(1), Some(false));
+        assert_eq!(list.is_revoked(SIZE as u32 - 1), Some(true));
+
+        // one bit per entry, instead of one byte or more for a list of flags
+        let bytes = std::mem::size_of_val(list.state().as_raw_slice());
+        assert!(bytes <= SIZE / 8 + std::mem::size_of::<usize>());
+
+        let ser = serde_json::to_value(&list).unwrap();
+        assert_eq!(ser["revocationList"].as_array().unwrap().len(), SIZE);
+        assert_eq!(ser["revocationList"][3], 1);
+    }
+
+    #[test]
+    fn diff_rev_status_lists_works() {
+        let previous = serde_json::from_str::<RevocationStatusList>(REVOCATION_LIST).unwrap();
+        let mut list = previous.clone();
+        list.update(None, Some(BTreeSet::from([0u32, 2])), None, Some(1245))
+            .unwrap();
+
+        let delta = list.diff(&previous).unwrap();
+        assert_eq!(delta.issued, BTreeSet::from([0u32, 2]));
+        assert!(delta.revoked.is_empty());
+        assert_eq!(delta.from_timestamp, Some(1234));
+        assert_eq!(delta.to_timestamp, Some(1245));
+
+        let reverse = previous.diff(&list);
+        assert!(reverse.is_err());
+
+        let mut later = list.clone();
+        later
+            .update(None, None, Some(BTreeSet::from([2u32])), Some(1300))
+            .unwrap();
+        let delta = later.diff(&list).unwrap();
+        assert!(delta.issued.is_empty());
+        assert_eq!(delta.revoked, BTreeSet::from([2u32]));
+
+        assert!(list.diff(&list).unwrap().is_empty());
+    }
+
+    #[test]
+    fn diff_rev_status_lists_rejects_different_registries() {
+        let previous = serde_json::from_str::<RevocationStatusList>(REVOCATION_LIST).unwrap();
+        let list = RevocationStatusList::new(
+            Some("other"),
+            previous.state_owned(),
+            previous.registry.clone(),
+            Some(1245),
+        )
+        .unwrap();
+        assert!(list.diff(&previous).is_err());
+    }
+
+    #[test]
+    fn delta_indices_are_bounded() {
+        let list = serde_json::from_str::<RevocationStatusList>(REVOCATION_LIST).unwrap();
+        let registry = Option::<ursa::cl::RevocationRegistry>::from(&list).unwrap();
+        let delta = |issued: &[u32], revoked: &[u32]| RevocationRegistryDelta {
+            value: ursa::cl::RevocationRegistryDelta::from_parts(
+                None,
+                &registry,
+                &issued.iter().copied().collect::<HashSet<u32>>(),
+                &revoked.iter().copied().collect::<HashSet<u32>>(),
+            ),
+        };
+
+        delta(&[1, 4], &[2]).validate_indices(4).unwrap();
+        assert_kind!(InvalidUserRevocId, delta(&[0], &[]).validate_indices(4));
+        assert_kind!(InvalidUserRevocId, delta(&[], &[5]).validate_indices(4));
+        assert_kind!(Input, delta(&[2], &[2]).validate_indices(4));
+    }
+
+    #[test]
+    fn set_revoked_checks_the_index() {
+        let mut list = serde_json::from_str::<RevocationStatusList>(REVOCATION_LIST).unwrap();
+        list.set_revoked(2, false).unwrap();
+        assert_eq!(list.is_revoked(2), Some(false));
+        list.set_revoked(2, true).unwrap();
+        assert_eq!(list.is_revoked(2), Some(true));
+        for idx in [0, 4] {
+            assert!(matches!(
+                list.set_revoked(idx, false).unwrap_err().kind(),
+                error::ErrorKind::InvalidRevocationIndex { max: 4, .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn revocation_index_is_checked() {
+        check_revocation_index(1, 4).unwrap();
+        check_revocation_index(3, 4).unwrap();
+        for idx in [0, 4, 5] {
+            let err = check_revocation_index(idx, 4).unwrap_err();
+            assert!(matches!(
+                err.kind(),
+                error::ErrorKind::InvalidRevocationIndex { idx: i, max: 4 } if i == idx
+            ));
+        }
     }
 }
@@ -1,12 +1,20 @@
 use std::str::FromStr;
 
-use crate::{error::ConversionError, impl_anoncreds_object_identifier};
+use crate::services::tails::{self, TailsReader};
+use crate::utils::json::to_canonical_json;
+use crate::{
+    error::{ConversionError, Error},
+    impl_anoncreds_object_identifier,
+};
 
 use super::{cred_def::CredentialDefinitionId, issuer_id::IssuerId};
 
 pub const CL_ACCUM: &str = "CL_ACCUM";
 
-impl_anoncreds_object_identifier!(RevocationRegistryDefinitionId);
+impl_anoncreds_object_identifier!(
+    RevocationRegistryDefinitionId,
+    crate::utils::validation::is_legacy_rev_reg_id
+);
 
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -50,12 +58,30 @@ pub struct RevocationRegistryDefinition {
     pub value: RevocationRegistryDefinitionValue,
 }
 
+impl RevocationRegistryDefinition {
+    /// Serialize the revocation registry definition to canonical JSON, for
+    /// example to derive a content-addressed identifier
+    pub fn to_canonical_json(&self) -> Result<Vec<u8>, Error> {
+        to_canonical_json(self)
+    }
+
+    /// Check that the tails data matches the `tails_hash` of the definition,
+    /// failing with `ErrorKind::TailsHashMismatch` otherwise
+    pub fn verify_tails(&self, tails_reader: &TailsReader) -> Result<(), Error> {
+        tails::verify_tails(self, tails_reader)
+    }
+}
+
 impl Validatable for RevocationRegistryDefinition {
     fn validate(&self) -> Result<(), ValidationError> {
         self.cred_def_id.validate()
     }
 }
 
+/// The issuer's private key for a revocation registry.
+///
+/// The key is owned by ursa, and clearing its memory on drop is left to ursa: this
+/// crate does not zeroize it.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RevocationRegistryDefinitionPrivate {
     pub value: ursa::cl::RevocationKeyPrivate,
@@ -1,13 +1,18 @@
 use crate::impl_anoncreds_object_identifier;
+use crate::invalid;
+use crate::services::helpers::{attr_common_view, attr_duplicate_key};
+use crate::utils::json::to_canonical_json;
+use crate::Error;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
 
 use super::issuer_id::IssuerId;
 
+/// The maximum number of attributes in a schema
 pub const MAX_ATTRIBUTES_COUNT: usize = 125;
 
-impl_anoncreds_object_identifier!(SchemaId);
+impl_anoncreds_object_identifier!(SchemaId, crate::utils::validation::is_legacy_schema_id);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,9 +23,19 @@ pub struct Schema {
     pub issuer_id: IssuerId,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Default)]
 pub struct AttributeNames(pub HashSet<String>);
 
+// Attribute names are serialized in sorted order, so that the JSON of a schema
+// does not depend on the iteration order of the set
+impl serde::Serialize for AttributeNames {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut names = self.0.iter().collect::<Vec<_>>();
+        names.sort_unstable();
+        serde::Serialize::serialize(&names, serializer)
+    }
+}
+
 impl From<&[&str]> for AttributeNames {
     fn from(attrs: &[&str]) -> Self {
         let mut attrset = HashSet::new();
@@ -49,6 +64,14 @@ impl From<AttributeNames> for HashSet<String> {
     }
 }
 
+impl Schema {
+    /// Serialize the schema to canonical JSON, for example to derive a
+    /// content-addressed identifier
+    pub fn to_canonical_json(&self) -> Result<Vec<u8>, Error> {
+        to_canonical_json(self)
+    }
+}
+
 impl Validatable for Schema {
     fn validate(&self) -> Result<(), ValidationError> {
         self.issuer_id.validate()?;
@@ -57,10 +80,18 @@ impl Validatable for Schema {
     }
 }
 
+/// Attribute names must not be empty after normalization, and must not be duplicates
+/// of each other after normalization and Unicode case folding (see
+/// `attr_duplicate_key`), so names which only differ in case or surrounding
+/// whitespace are rejected. Only the space character is removed from within a name:
+/// `"first\tname"` and `"firstname"` are distinct.
 impl Validatable for AttributeNames {
     fn validate(&self) -> Result<(), ValidationError> {
         if self.0.is_empty() {
-            return Err("Empty list of Schema attributes has been passed".into());
+            return Err(invalid!(
+                MissingField,
+                "Empty list of Schema attributes has been passed"
+            ));
         }
 
         if self.0.len() > MAX_ATTRIBUTES_COUNT {
@@ -71,6 +102,25 @@ impl Validatable for AttributeNames {
             )
             .into());
         }
+
+        let mut normalized = HashMap::new();
+        for name in self.0.iter() {
+            if attr_common_view(name).is_empty() {
+                return Err(invalid!(
+                    MissingField,
+                    "Schema attribute names cannot be empty: {:?}",
+                    name
+                ));
+            }
+            if let Some(other) = normalized.insert(attr_duplicate_key(name), name) {
+                return Err(invalid!(
+                    SchemaMismatch,
+                    "Schema attributes {:?} and {:?} are duplicates after normalization",
+                    other,
+                    name
+                ));
+            }
+        }
         Ok(())
     }
 }
@@ -93,6 +143,35 @@ mod test_schema_validation {
         assert_eq!(schema.version, "1.0");
     }
 
+    #[test]
+    fn test_schema_canonical_json() {
+        let schema: Schema = serde_json::from_value(json!({
+            "name": "gvt",
+            "version": "1.0",
+            "attrNames": ["ccc", "aaa", "bbb"],
+            "issuerId": "mock:uri"
+        }))
+        .unwrap();
+        let other: Schema = serde_json::from_value(json!({
+            "issuerId": "mock:uri",
+            "attrNames": ["bbb", "ccc", "aaa"],
+            "version": "1.0",
+            "name": "gvt"
+        }))
+        .unwrap();
+
+        let bytes = schema.to_canonical_json().unwrap();
+        assert_eq!(
+            bytes,
+            br#"{"attrNames":["aaa","bbb","ccc"],"issuerId":"mock:uri","name":"gvt","version":"1.0"}"#
+                .to_vec()
+        );
+        assert_eq!(other.to_canonical_json().unwrap(), bytes);
+
+        let parsed: Schema = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.to_canonical_json().unwrap(), bytes);
+    }
+
     #[test]
     fn test_schema_invalid_missing_properties() {
         let schema_json = json!({
@@ -128,4 +207,54 @@ mod test_schema_validation {
         let schema: Schema = serde_json::from_value(schema_json).unwrap();
         assert!(schema.validate().is_err());
     }
+
+    #[test]
+    fn test_schema_attr_names_collisions() {
+        for attrs in [
+            &["First Name", "first name "][..],
+            &["name", "NAME"],
+            &["age", " age"],
+            &["\u{c4}ge", "\u{e4}GE"],
+            &["stra\u{df}e", "STRASSE"],
+            &["Stra\u{df}e ", "strasse"],
+        ] {
+            let err = AttributeNames::from(attrs)
+                .validate()
+                .expect_err("Attribute names should collide");
+            assert_eq!(
+                err.kind(),
+                crate::error::ValidationErrorKind::SchemaMismatch,
+                "{:?} should collide",
+                attrs
+            );
+        }
+
+        AttributeNames::from(&["First Name", "Last Name", "age"][..])
+            .validate()
+            .unwrap();
+        // only the space character is removed from within a name
+        AttributeNames::from(&["first\tname", "firstname"][..])
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_schema_attr_names_empty_name() {
+        for attrs in [&["name", ""][..], &["name", "   "]] {
+            let err = AttributeNames::from(attrs).validate().unwrap_err();
+            assert_eq!(err.kind(), crate::error::ValidationErrorKind::MissingField);
+        }
+    }
+
+    #[test]
+    fn test_schema_attr_names_max_count() {
+        let names: Vec<String> = (0..MAX_ATTRIBUTES_COUNT)
+            .map(|i| format!("attr{i}"))
+            .collect();
+        AttributeNames::from(names.clone()).validate().unwrap();
+
+        let mut names = names;
+        names.push("one_too_many".to_owned());
+        assert!(AttributeNames::from(names).validate().is_err());
+    }
 }
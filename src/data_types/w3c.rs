@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    cred_def::{CredentialDefinition, CredentialDefinitionId},
+    credential::{AttributeValues, Credential, CredentialValues},
+    issuer_id::IssuerId,
+    rev_reg::RevocationRegistryId,
+    schema::SchemaId,
+};
+use crate::error::{Error, ValidationError};
+use crate::invalid;
+use crate::services::helpers::attr_common_view;
+use crate::utils::{base58, validation::Validatable};
+
+pub const W3C_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+pub const ANONCREDS_CONTEXT: &str =
+    "https://raw.githubusercontent.com/hyperledger/anoncreds-spec/main/data/anoncreds-w3c-context.json";
+pub const W3C_CREDENTIAL_TYPE: &str = "VerifiableCredential";
+pub const ANONCREDS_CREDENTIAL_TYPE: &str = "AnonCredsCredential";
+pub const ANONCREDS_DEFINITION_TYPE: &str = "AnonCredsDefinition";
+pub const DATA_INTEGRITY_PROOF_TYPE: &str = "DataIntegrityProof";
+pub const ANONCREDS_CRYPTOSUITE: &str = "anoncreds-2023";
+pub const ASSERTION_METHOD_PURPOSE: &str = "assertionMethod";
+
+/// Multibase prefix for base58btc encoded values
+const MULTIBASE_BASE58_BTC: char = 'z';
+
+/// Credential in the W3C Verifiable Credentials Data Model representation
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cCredential {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub issuer: IssuerId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuance_date: Option<String>,
+    pub credential_schema: W3cCredentialSchema,
+    /// Raw attribute values, keyed by attribute name
+    pub credential_subject: HashMap<String, String>,
+    pub proof: DataIntegrityProof,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cCredentialSchema {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub definition: CredentialDefinitionId,
+    pub schema: SchemaId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revocation_registry: Option<RevocationRegistryId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub cryptosuite: String,
+    pub proof_purpose: String,
+    pub verification_method: CredentialDefinitionId,
+    /// Multibase (base58btc) encoding of the CL signature, its correctness proof,
+    /// the revocation witness when present, and the encoded attribute values.
+    ///
+    /// The encoded value is a JSON document private to this crate, which does not
+    /// follow the proof value encoding of the `anoncreds-2023` cryptosuite and can
+    /// only be read back by `W3cCredential::to_legacy`.
+    pub proof_value: String,
+}
+
+/// Contents of `DataIntegrityProof::proof_value`, serialized as JSON. The layout is
+/// internal to this crate and may change.
+#[derive(Deserialize, Serialize)]
+struct CredentialSignatureProof {
+    signature: ursa::cl::CredentialSignature,
+    signature_correctness_proof: ursa::cl::SignatureCorrectnessProof,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rev_reg: Option<ursa::cl::RevocationRegistry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    witness: Option<ursa::cl::Witness>,
+    encoded: HashMap<String, String>,
+}
+
+impl Credential {
+    /// Convert the credential to the AnonCreds W3C representation.
+    ///
+    /// The credential attributes must match the attributes of the credential definition,
+    /// and must remain distinct once normalized as in the credential definition.
+    ///
+    /// The proof value is encoded in a format private to this crate, see
+    /// `DataIntegrityProof::proof_value`, so the result is only meant to be converted
+    /// back with `W3cCredential::to_legacy`.
+    pub fn to_w3c(&self, cred_def: &CredentialDefinition) -> Result<W3cCredential, Error> {
+        if self.schema_id != cred_def.schema_id {
+            return Err(err_msg!(
+                "Credential schema {} does not match the credential definition schema {}",
+                self.schema_id,
+                cred_def.schema_id
+            ));
+        }
+        let cred_def_attrs = cred_def.attribute_names().map_err(err_map!(
+            Unexpected,
+            "Error reading attributes from credential definition"
+        ))?;
+        // names which only differ in case or spacing would be signed as the same
+        // attribute, and could not be told apart in the credential subject
+        let mut cred_attrs = HashSet::with_capacity(self.values.0.len());
+        for attr in self.values.0.keys() {
+            if !cred_attrs.insert(attr_common_view(attr)) {
+                return Err(err_msg!(
+                    "Credential attribute {:?} is a duplicate of another attribute",
+                    attr
+                ));
+            }
+        }
+        if cred_attrs != cred_def_attrs {
+            return Err(err_msg!(
+                "Credential attributes {:?} do not match the credential definition attributes {:?}",
+                cred_attrs,
+                cred_def_attrs
+            ));
+        }
+
+        let (credential_subject, encoded): (HashMap<_, _>, HashMap<_, _>) = self
+            .values
+            .0
+            .iter()
+            .map(|(attr, values)| {
+                (
+                    (attr.clone(), values.raw.clone()),
+                    (attr.clone(), values.encoded.clone()),
+                )
+            })
+            .unzip();
+        let proof = CredentialSignatureProof {
+            signature: self.signature.try_clone()?,
+            signature_correctness_proof: self.signature_correctness_proof.try_clone()?,
+            rev_reg: self.rev_reg.clone(),
+            witness: self.witness.clone(),
+            encoded,
+        };
+        let proof_value = serde_json::to_vec(&proof).map_err(err_map!(
+            Unexpected,
+            "Error serializing credential signature"
+        ))?;
+
+        Ok(W3cCredential {
+            context: vec![W3C_CONTEXT.to_owned(), ANONCREDS_CONTEXT.to_owned()],
+            type_: vec![
+                W3C_CREDENTIAL_TYPE.to_owned(),
+                ANONCREDS_CREDENTIAL_TYPE.to_owned(),
+            ],
+            issuer: cred_def.issuer_id.clone(),
+            issuance_date: None,
+            credential_schema: W3cCredentialSchema {
+                type_: ANONCREDS_DEFINITION_TYPE.to_owned(),
+                definition: self.cred_def_id.clone(),
+                schema: self.schema_id.clone(),
+                revocation_registry: self.rev_reg_id.clone(),
+            },
+            credential_subject,
+            proof: DataIntegrityProof {
+                type_: DATA_INTEGRITY_PROOF_TYPE.to_owned(),
+                cryptosuite: ANONCREDS_CRYPTOSUITE.to_owned(),
+                proof_purpose: ASSERTION_METHOD_PURPOSE.to_owned(),
+                verification_method: self.cred_def_id.clone(),
+                proof_value: format!("{}{}", MULTIBASE_BASE58_BTC, base58::encode(proof_value)),
+            },
+        })
+    }
+}
+
+impl W3cCredential {
+    /// Convert the credential back to the AnonCreds representation
+    pub fn to_legacy(&self) -> Result<Credential, Error> {
+        self.validate()?;
+
+        let proof_value = self
+            .proof
+            .proof_value
+            .strip_prefix(MULTIBASE_BASE58_BTC)
+            .ok_or_else(|| err_msg!("Unsupported multibase encoding for the proof value"))?;
+        let proof_value =
+            base58::decode(proof_value).map_err(err_map!("Error decoding the proof value"))?;
+        let proof: CredentialSignatureProof = serde_json::from_slice(&proof_value)
+            .map_err(err_map!("Error deserializing the proof value"))?;
+
+        let credential_attrs = self.credential_subject.keys().collect::<HashSet<_>>();
+        if credential_attrs != proof.encoded.keys().collect::<HashSet<_>>() {
+            return Err(err_msg!(
+                "Credential subject attributes do not match the signed attributes"
+            ));
+        }
+        let values = self
+            .credential_subject
+            .iter()
+            .map(|(attr, raw)| {
+                (
+                    attr.clone(),
+                    AttributeValues {
+                        raw: raw.clone(),
+                        encoded: proof.encoded[attr].clone(),
+                    },
+                )
+            })
+            .collect();
+
+        Ok(Credential {
+            schema_id: self.credential_schema.schema.clone(),
+            cred_def_id: self.credential_schema.definition.clone(),
+            rev_reg_id: self.credential_schema.revocation_registry.clone(),
+            values: CredentialValues(values),
+            signature: proof.signature,
+            signature_correctness_proof: proof.signature_correctness_proof,
+            rev_reg: proof.rev_reg,
+            witness: proof.witness,
+        })
+    }
+}
+
+impl Validatable for W3cCredential {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if !self.context.iter().any(|c| c == W3C_CONTEXT) {
+            return Err(invalid!(
+                "W3C credential validation failed: missing context {}",
+                W3C_CONTEXT
+            ));
+        }
+        if !self.type_.iter().any(|t| t == W3C_CREDENTIAL_TYPE)
+            || !self.type_.iter().any(|t| t == ANONCREDS_CREDENTIAL_TYPE)
+        {
+            return Err(invalid!(
+                "W3C credential validation failed: unexpected credential type {:?}",
+                self.type_
+            ));
+        }
+        if self.credential_schema.type_ != ANONCREDS_DEFINITION_TYPE {
+            return Err(invalid!(
+                "W3C credential validation failed: unexpected credential schema type {}",
+                self.credential_schema.type_
+            ));
+        }
+        if self.proof.type_ != DATA_INTEGRITY_PROOF_TYPE
+            || self.proof.cryptosuite != ANONCREDS_CRYPTOSUITE
+        {
+            return Err(invalid!(
+                "W3C credential validation failed: unsupported proof {} / {}",
+                self.proof.type_,
+                self.proof.cryptosuite
+            ));
+        }
+        if self.proof.verification_method != self.credential_schema.definition {
+            return Err(invalid!(
+                "W3C credential validation failed: proof verification method does not match the credential definition"
+            ));
+        }
+        if self.credential_subject.is_empty() {
+            return Err(invalid!(
+                MissingField,
+                "W3C credential validation failed: `credentialSubject` is empty"
+            ));
+        }
+        self.issuer.validate()?;
+        self.credential_schema.schema.validate()?;
+        self.credential_schema.definition.validate()?;
+        self.credential_schema
+            .revocation_registry
+            .as_ref()
+            .map(Validatable::validate)
+            .transpose()?;
+        Ok(())
+    }
+}
@@ -18,8 +18,17 @@ pub enum ErrorKind {
     // Credential/proof errors
     CredentialRevoked,
     InvalidUserRevocId,
+    /// A revocation index outside of `1..max` for a registry of `max` credentials
+    InvalidRevocationIndex {
+        idx: u32,
+        max: u32,
+    },
     ProofRejected,
     RevocationRegistryFull,
+    TooManyAttributes,
+    PresentationTooLarge,
+    /// The tails data does not match the `tails_hash` of the revocation registry
+    TailsHashMismatch,
 }
 
 impl ErrorKind {
@@ -31,8 +40,12 @@ impl ErrorKind {
             Self::Unexpected => "Unexpected error",
             Self::CredentialRevoked => "Credential revoked",
             Self::InvalidUserRevocId => "Invalid revocation accumulator index",
+            Self::InvalidRevocationIndex { .. } => "Invalid revocation index",
             Self::ProofRejected => "Proof rejected",
             Self::RevocationRegistryFull => "Revocation registry full",
+            Self::TooManyAttributes => "Too many attributes",
+            Self::PresentationTooLarge => "Presentation too large",
+            Self::TailsHashMismatch => "Tails hash mismatch",
         }
     }
 }
@@ -342,11 +355,150 @@ define_error!(
     "Error type for eventualities that shouldn't normally occur"
 );
 
-define_error!(
-    ValidationError,
-    "Validation error",
-    "Error type for failures of `Validatable::validate`"
-);
+/// Categories of validation failures, allowing callers to handle them without
+/// inspecting the error message
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ValidationErrorKind {
+    /// An identifier is not a valid URI or legacy identifier
+    IdentifierFormat,
+    /// A required value is missing or empty
+    MissingField,
+    /// A requested predicate is malformed or can never be satisfied
+    InconsistentPredicate,
+    /// Attribute names are duplicated, or attributes do not match the related schema or
+    /// credential definition
+    SchemaMismatch,
+    /// Any other validation failure
+    #[default]
+    Generic,
+}
+
+impl ValidationErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::IdentifierFormat => "Invalid identifier format",
+            Self::MissingField => "Missing field",
+            Self::InconsistentPredicate => "Inconsistent predicate",
+            Self::SchemaMismatch => "Schema mismatch",
+            Self::Generic => "Validation error",
+        }
+    }
+}
+
+impl Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error type for failures of `Validatable::validate`
+#[derive(Debug, Error)]
+pub struct ValidationError {
+    pub kind: ValidationErrorKind,
+    pub context: Option<String>,
+    pub source: Option<DynError>,
+}
+
+impl ValidationError {
+    pub fn from_msg<T: Into<String>>(msg: T) -> Self {
+        Self::from(msg.into())
+    }
+
+    pub fn from_kind_msg<T: Into<String>>(kind: ValidationErrorKind, msg: T) -> Self {
+        Self::from(msg.into()).with_kind(kind)
+    }
+
+    pub fn from_err<E>(err: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Self {
+            kind: ValidationErrorKind::default(),
+            context: None,
+            source: Some(Box::new(err) as DynError),
+        }
+    }
+
+    pub fn from_msg_err<M, E>(msg: M, err: E) -> Self
+    where
+        M: Into<String>,
+        E: StdError + Send + Sync + 'static,
+    {
+        Self {
+            kind: ValidationErrorKind::default(),
+            context: Some(msg.into()),
+            source: Some(Box::new(err) as DynError),
+        }
+    }
+
+    pub fn kind(&self) -> ValidationErrorKind {
+        self.kind
+    }
+
+    pub fn with_kind(mut self, kind: ValidationErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+}
+
+impl From<ValidationErrorKind> for ValidationError {
+    fn from(kind: ValidationErrorKind) -> Self {
+        Self {
+            kind,
+            context: None,
+            source: None,
+        }
+    }
+}
+
+impl From<&str> for ValidationError {
+    fn from(context: &str) -> Self {
+        Self::from(Some(context.to_owned()))
+    }
+}
+
+impl From<String> for ValidationError {
+    fn from(context: String) -> Self {
+        Self::from(Some(context))
+    }
+}
+
+impl From<Option<String>> for ValidationError {
+    fn from(context: Option<String>) -> Self {
+        Self {
+            kind: ValidationErrorKind::default(),
+            context,
+            source: None,
+        }
+    }
+}
+
+impl<M, E> From<(M, E)> for ValidationError
+where
+    M: Into<String>,
+    E: StdError + Send + Sync + 'static,
+{
+    fn from((context, err): (M, E)) -> Self {
+        Self::from_msg_err(context, err)
+    }
+}
+
+impl From<ValidationError> for String {
+    fn from(s: ValidationError) -> Self {
+        s.to_string()
+    }
+}
+
+// The kind is available through `kind()` and is not part of the message
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Validation error")?;
+        match self.context {
+            Some(ref context) => write!(f, ": {}", context),
+            None => Ok(()),
+        }
+    }
+}
 
 impl From<serde_json::error::Error> for ConversionError {
     fn from(err: serde_json::error::Error) -> Self {
@@ -378,6 +530,7 @@ impl From<ValidationError> for ConversionError {
 impl From<ConversionError> for ValidationError {
     fn from(err: ConversionError) -> Self {
         Self {
+            kind: ValidationErrorKind::default(),
             context: err.context,
             source: err.source,
         }
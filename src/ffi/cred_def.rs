@@ -48,9 +48,7 @@ pub extern "C" fn anoncreds_create_credential_definition(
             issuer_id,
             tag,
             signature_type,
-            CredentialDefinitionConfig {
-                support_revocation: support_revocation != 0,
-            },
+            CredentialDefinitionConfig::new(support_revocation != 0),
         )?;
         let cred_def = ObjectHandle::create(cred_def)?;
         let cred_def_pvt = ObjectHandle::create(cred_def_pvt)?;
@@ -20,7 +20,7 @@ pub extern "C" fn anoncreds_create_credential_offer(
             .as_opt_str()
             .ok_or_else(|| err_msg!("Missing cred def ID"))?;
         let cred_offer =
-            create_credential_offer(schema_id, cred_def_id, key_proof.load()?.cast_ref()?)?;
+            create_credential_offer(schema_id, cred_def_id, key_proof.load()?.cast_ref()?, None)?;
         let cred_offer = ObjectHandle::create(cred_offer)?;
         unsafe { *cred_offer_p = cred_offer };
         Ok(())
@@ -36,6 +36,7 @@ pub extern "C" fn anoncreds_create_credential_request(
             master_secret.load()?.cast_ref()?,
             master_secret_id,
             cred_offer.load()?.cast_ref()?,
+            None,
         )?;
         let cred_req = ObjectHandle::create(cred_req)?;
         let cred_req_metadata = ObjectHandle::create(cred_req_metadata)?;
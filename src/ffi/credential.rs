@@ -39,6 +39,7 @@ impl RevocationConfig {
             reg_def_private: self.reg_def_private.cast_ref()?,
             registry_idx: self.reg_idx,
             tails_reader: TailsFileReader::new_tails_reader(self.tails_path.as_str()),
+            verify_tails: false,
         })
     }
 }
@@ -130,6 +131,8 @@ pub extern "C" fn anoncreds_create_credential(
                 .as_ref()
                 .map(RevocationConfig::as_ref_config)
                 .transpose()?,
+            None,
+            None,
         )?;
         let cred = ObjectHandle::create(cred)?;
         unsafe {
@@ -22,6 +22,9 @@ pub enum ErrorCode {
     InvalidUserRevocId = 6,
     ProofRejected = 7,
     RevocationRegistryFull = 8,
+    TooManyAttributes = 9,
+    PresentationTooLarge = 10,
+    TailsHashMismatch = 11,
 }
 
 impl From<ErrorKind> for ErrorCode {
@@ -32,9 +35,14 @@ impl From<ErrorKind> for ErrorCode {
             ErrorKind::InvalidState => ErrorCode::InvalidState,
             ErrorKind::Unexpected => ErrorCode::Unexpected,
             ErrorKind::CredentialRevoked => ErrorCode::CredentialRevoked,
-            ErrorKind::InvalidUserRevocId => ErrorCode::InvalidUserRevocId,
+            ErrorKind::InvalidUserRevocId | ErrorKind::InvalidRevocationIndex { .. } => {
+                ErrorCode::InvalidUserRevocId
+            }
             ErrorKind::ProofRejected => ErrorCode::ProofRejected,
             ErrorKind::RevocationRegistryFull => ErrorCode::RevocationRegistryFull,
+            ErrorKind::TooManyAttributes => ErrorCode::TooManyAttributes,
+            ErrorKind::PresentationTooLarge => ErrorCode::PresentationTooLarge,
+            ErrorKind::TailsHashMismatch => ErrorCode::TailsHashMismatch,
         }
     }
 }
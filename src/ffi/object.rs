@@ -14,6 +14,7 @@ use serde::Serialize;
 use super::error::{catch_error, ErrorCode};
 use crate::error::Result;
 use crate::new_handle_type;
+use crate::utils::zeroizing::ZeroizingBuffer;
 
 pub(crate) static FFI_OBJECTS: Lazy<Mutex<BTreeMap<ObjectHandle, AnonCredsObject>>> =
     Lazy::new(|| Mutex::new(BTreeMap::new()));
@@ -113,7 +114,11 @@ where
     T: Serialize,
 {
     fn to_json(&self) -> Result<Vec<u8>> {
-        serde_json::to_vec(self).map_err(err_map!("Error serializing object"))
+        // The JSON of secret objects must not be left behind as the output grows. The
+        // result is cleared by `anoncreds_buffer_free`.
+        let mut buf = ZeroizingBuffer::default();
+        serde_json::to_writer(&mut buf, self).map_err(err_map!("Error serializing object"))?;
+        Ok(buf.into_vec())
     }
 }
 
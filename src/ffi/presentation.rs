@@ -273,6 +273,8 @@ pub extern "C" fn anoncreds_verify_presentation(
             &cred_defs,
             rev_reg_defs,
             rev_status_list,
+            None,
+            None,
         )?;
         unsafe { *result_p = verify as i8 };
         Ok(())
@@ -83,6 +83,7 @@ pub extern "C" fn anoncreds_update_revocation_status_list(
             timestamp,
             issued,
             revoked,
+            None,
             rev_reg_def.load()?.cast_ref()?,
             rev_current_list.load()?.cast_ref()?,
         )?;
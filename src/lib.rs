@@ -18,7 +18,9 @@ pub use ursa;
 mod error;
 #[doc(hidden)]
 pub use self::error::Result;
-pub use self::error::{Error, ErrorKind};
+pub use self::error::{Error, ErrorKind, ValidationError, ValidationErrorKind};
+
+pub mod rng;
 
 mod services;
 pub use services::*;
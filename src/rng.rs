@@ -0,0 +1,22 @@
+//! Random number generation for the values created by this crate.
+//!
+//! The nonces of credential offers and credential requests are sampled from the
+//! generator passed to `issuer::create_credential_offer` and
+//! `prover::create_credential_request`, or from the operating system's generator
+//! when none is given. `Nonce::from_rng` accepts a generator for other nonces.
+//!
+//! The secret values of the CL signature scheme are not covered: the key material of
+//! `issuer::create_credential_definition`, the signature randomness of
+//! `issuer::create_credential` and the blinding factors of
+//! `prover::create_credential_request` are sampled by ursa from the OpenSSL random
+//! number generator, which has to be configured in OpenSSL.
+//!
+//! The placeholder prover DID used by `issuer::create_credential` for requests without
+//! one is not secret, and is taken from the thread-local generator of `rand`.
+
+use rand::RngCore;
+
+/// A cryptographically secure random number generator, usable as a trait object
+pub trait CryptoRng: RngCore + rand::CryptoRng {}
+
+impl<R: RngCore + rand::CryptoRng + ?Sized> CryptoRng for R {}
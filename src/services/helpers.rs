@@ -8,6 +8,7 @@ use crate::data_types::{
 use crate::utils::hash::SHA256;
 
 use crate::error::Result;
+use crate::rng::CryptoRng;
 use crate::ursa::{
     bn::BigNumber,
     cl::{
@@ -16,8 +17,21 @@ use crate::ursa::{
     },
 };
 
+/// Normalize an attribute name, as used for schema attribute comparison and for the
+/// attribute names of the credential values.
+///
+/// Whitespace is trimmed from both ends, the space character is removed from within
+/// the name and the result is lowercased with `str::to_lowercase`. These are the
+/// attribute names of the CL signature, so this must not change.
 pub fn attr_common_view(attr: &str) -> String {
-    attr.replace(' ', "").to_lowercase()
+    attr.trim().replace(' ', "").to_lowercase()
+}
+
+/// Key used to detect attribute names which are duplicates of each other: the name
+/// normalized with `attr_common_view`, then with full Unicode case folding, so that
+/// names such as `"straße"` and `"STRASSE"` are also duplicates.
+pub fn attr_duplicate_key(attr: &str) -> String {
+    caseless::default_case_fold_str(&attr_common_view(attr))
 }
 
 pub fn build_credential_schema(attrs: &HashSet<String>) -> Result<CredentialSchema> {
@@ -70,21 +84,36 @@ pub fn build_credential_values(
     Ok(res)
 }
 
+/// Encode a raw attribute value with the standard encoding, as used by
+/// `MakeCredentialValues::add_raw`.
+///
+/// Values which parse as a 32-bit signed integer are encoded as that integer, so
+/// they can be used in predicates. Any other value is encoded as the decimal
+/// representation of its big-endian SHA-256 digest. Issuers may choose other
+/// encodings with `MakeCredentialValues::add_encoded` or `add_str`, so the raw value
+/// of a credential does not always determine its encoded value.
 pub fn encode_credential_attribute(raw_value: &str) -> Result<String> {
     if let Ok(val) = raw_value.parse::<i32>() {
         Ok(val.to_string())
     } else {
-        let digest = SHA256::digest(raw_value.as_bytes());
-        #[cfg(target_endian = "big")]
-        let digest = {
-            let mut d = digest;
-            d.reverse();
-            d
-        };
-        Ok(BigNumber::from_bytes(&digest)?.to_dec()?)
+        encode_string_attribute(raw_value)
     }
 }
 
+/// Encode a raw attribute value as the decimal representation of its big-endian
+/// SHA-256 digest, even when it parses as an integer. This is the encoding of
+/// `MakeCredentialValues::add_str`.
+pub fn encode_string_attribute(raw_value: &str) -> Result<String> {
+    let digest = SHA256::digest(raw_value.as_bytes());
+    #[cfg(target_endian = "big")]
+    let digest = {
+        let mut d = digest;
+        d.reverse();
+        d
+    };
+    Ok(BigNumber::from_bytes(&digest)?.to_dec()?)
+}
+
 pub fn build_sub_proof_request(
     attrs_for_credential: &[AttributeInfo],
     predicates_for_credential: &[PredicateInfo],
@@ -118,13 +147,13 @@ pub fn build_sub_proof_request(
     }
 
     for predicate in predicates_for_credential {
-        let p_type = format!("{}", predicate.p_type);
-
-        sub_proof_request_builder.add_predicate(
-            &attr_common_view(&predicate.name),
-            &p_type,
-            predicate.p_value,
-        )?;
+        for (p_type, p_value) in predicate.bounds() {
+            sub_proof_request_builder.add_predicate(
+                &attr_common_view(&predicate.name),
+                &p_type.to_string(),
+                p_value,
+            )?;
+        }
     }
 
     let res = sub_proof_request_builder.finalize()?;
@@ -153,8 +182,13 @@ pub fn get_non_revoc_interval(
     interval
 }
 
-pub fn new_nonce() -> Result<Nonce> {
-    Nonce::new().map_err(err_map!(Unexpected))
+/// Generate a nonce from `rng`, or from the operating system's generator
+pub fn new_nonce(rng: Option<&mut dyn CryptoRng>) -> Result<Nonce> {
+    match rng {
+        Some(rng) => Nonce::from_rng(rng),
+        None => Nonce::new(),
+    }
+    .map_err(err_map!(Unexpected, "Error creating nonce"))
 }
 
 #[cfg(test)]
@@ -168,6 +202,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn attr_common_view_normalizes() {
+        assert_eq!(attr_common_view("First Name"), "firstname");
+        assert_eq!(attr_common_view(" first name \t"), "firstname");
+        assert_eq!(attr_common_view("\u{c4}GE"), "\u{e4}ge");
+    }
+
+    #[test]
+    fn attr_duplicate_key_folds_case() {
+        assert_eq!(attr_duplicate_key(" First Name"), "firstname");
+        assert_eq!(attr_duplicate_key("stra\u{df}e"), "strasse");
+        assert_eq!(attr_duplicate_key("STRASSE"), "strasse");
+        // the CL attribute name is unchanged
+        assert_eq!(attr_common_view("stra\u{df}e"), "stra\u{df}e");
+    }
+
+    #[test]
+    fn new_nonce_from_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let nonce = new_nonce(Some(&mut StdRng::seed_from_u64(1))).unwrap();
+        assert_eq!(
+            nonce,
+            Nonce::from_rng(&mut StdRng::seed_from_u64(1)).unwrap()
+        );
+        assert_ne!(nonce, new_nonce(None).unwrap());
+    }
+
     #[test]
     fn get_non_revoc_interval_for_global() {
         let res = get_non_revoc_interval(&Some(_interval()), &None).unwrap();
@@ -1,7 +1,8 @@
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::iter::FromIterator;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::types::*;
 
@@ -12,15 +13,18 @@ use crate::data_types::rev_reg_def::RevocationRegistryDefinitionId;
 use crate::data_types::schema::SchemaId;
 use crate::data_types::{
     cred_def::{CredentialDefinition, CredentialDefinitionData},
-    nonce::Nonce,
+    nonce::NonceSeen,
+    rev_reg::check_revocation_index,
     rev_reg_def::{RevocationRegistryDefinitionValue, RevocationRegistryDefinitionValuePublicKeys},
     schema::Schema,
 };
 use crate::error::{Error, ErrorKind, Result, ValidationError};
+use crate::invalid;
+use crate::rng::CryptoRng;
 use crate::services::helpers::*;
 use crate::ursa::cl::{
-    issuer::Issuer as CryptoIssuer, RevocationRegistryDelta as CryptoRevocationRegistryDelta,
-    Witness,
+    issuer::Issuer as CryptoIssuer, CredentialPublicKey,
+    RevocationRegistryDelta as CryptoRevocationRegistryDelta, Witness,
 };
 use crate::utils::validation::Validatable;
 use bitvec::bitvec;
@@ -58,6 +62,14 @@ where
     Ok(schema)
 }
 
+/// Create a credential definition and its private key for a schema.
+///
+/// Key generation is slow, so asynchronous callers should run this on a blocking
+/// thread (for example with `tokio::task::spawn_blocking`). All of the arguments can
+/// be moved into the closure, and the returned objects are `Send + Sync + 'static`.
+///
+/// The key material is sampled by the CL implementation from the OpenSSL random
+/// number generator.
 pub fn create_credential_definition<SI, II>(
     schema_id: SI,
     schema: &Schema,
@@ -82,6 +94,17 @@ where
     let issuer_id = issuer_id.try_into()?;
     let schema_id = schema_id.try_into()?;
 
+    // the schema attributes and the implicit master secret attribute
+    let attr_count = schema.attr_names.0.len() + 1;
+    if attr_count > config.max_attributes() {
+        return Err(err_msg!(
+            TooManyAttributes,
+            "Credential definition would have {} attributes, the maximum is {}",
+            attr_count,
+            config.max_attributes()
+        ));
+    }
+
     let credential_schema = build_credential_schema(&schema.attr_names.0)?;
     let non_credential_schema = build_non_credential_schema()?;
 
@@ -230,21 +253,53 @@ pub fn update_revocation_status_list_timestamp_only(
 }
 /// Update Revocation Status List
 /// - if `timestamp` is `None`: the timestamp is not updated
+/// - `issued` and `revoked` indices which are already in that state are ignored
+/// - `recovered` indices must currently be revoked, and are moved back to issued to
+///   reverse an erroneous revocation. An index cannot be both revoked and recovered.
 pub fn update_revocation_status_list(
     timestamp: Option<u64>,
     issued: Option<BTreeSet<u32>>,
     revoked: Option<BTreeSet<u32>>,
+    recovered: Option<BTreeSet<u32>>,
     rev_reg_def: &RevocationRegistryDefinition,
     current_list: &RevocationStatusList,
 ) -> Result<RevocationStatusList> {
+    for &idx in recovered.iter().flatten() {
+        if revoked.as_ref().map_or(false, |r| r.contains(&idx)) {
+            return Err(err_msg!(
+                "Revocation index {} cannot be both revoked and recovered",
+                idx
+            ));
+        }
+        match current_list.is_revoked(idx) {
+            Some(true) => (),
+            Some(false) => {
+                return Err(err_msg!(
+                    "Revocation index {} is not revoked and cannot be recovered",
+                    idx
+                ))
+            }
+            None => {
+                return Err(err_msg!(
+                    InvalidUserRevocId,
+                    "Revocation status list does not have the index {}",
+                    idx
+                ))
+            }
+        }
+    }
+
     let mut new_list = current_list.clone();
-    let issued = issued.map(|i_list| {
-        BTreeSet::from_iter(
-            i_list
+    let issued = match (issued, recovered) {
+        (None, None) => None,
+        (issued, recovered) => Some(BTreeSet::from_iter(
+            issued
                 .into_iter()
+                .chain(recovered)
+                .flatten()
                 .filter(|&i| current_list.get(i as usize).unwrap_or(false)),
-        )
-    });
+        )),
+    };
 
     let revoked = revoked.map(|r_list| {
         BTreeSet::from_iter(
@@ -276,16 +331,21 @@ pub fn update_revocation_status_list(
     Ok(new_list)
 }
 
+/// Create a credential offer for a credential definition.
+///
+/// The offer nonce is sampled from `rng` when it is provided, and from the operating
+/// system's generator otherwise.
 pub fn create_credential_offer(
     schema_id: impl TryInto<SchemaId, Error = ValidationError>,
     cred_def_id: impl TryInto<CredentialDefinitionId, Error = ValidationError>,
     correctness_proof: &CredentialKeyCorrectnessProof,
+    rng: Option<&mut dyn CryptoRng>,
 ) -> Result<CredentialOffer> {
     let schema_id = schema_id.try_into()?;
     let cred_def_id = cred_def_id.try_into()?;
     trace!("create_credential_offer >>> cred_def_id: {:?}", cred_def_id);
 
-    let nonce = Nonce::new().map_err(err_map!(Unexpected, "Error creating nonce"))?;
+    let nonce = new_nonce(rng)?;
 
     let key_correctness_proof = correctness_proof
         .try_clone()
@@ -296,12 +356,87 @@ pub fn create_credential_offer(
         key_correctness_proof: key_correctness_proof.value,
         nonce,
         method_name: None,
+        created: Some(now_timestamp()?),
     };
 
     trace!("create_credential_offer <<< result: {:?}", credential_offer);
     Ok(credential_offer)
 }
 
+// The request must reference the credential definition of the offer, and the offer
+// must be for the schema of the credential definition. The proof of the blinded master
+// secret is verified by ursa in `sign_credential`, before the signature is computed.
+fn check_credential_request(
+    cred_request: &CredentialRequest,
+    cred_offer: &CredentialOffer,
+    cred_def: &CredentialDefinition,
+    offer_ttl: Option<u64>,
+) -> Result<()> {
+    trace!(
+        "check_credential_request >>> cred_request: {:?}, cred_offer: {:?}, cred_def: {:?}",
+        cred_request,
+        cred_offer,
+        cred_def
+    );
+
+    if cred_request.cred_def_id != cred_offer.cred_def_id {
+        return Err(err_msg!(
+            "Credential request is for credential definition {}, but the offer is for {}",
+            cred_request.cred_def_id,
+            cred_offer.cred_def_id
+        ));
+    }
+    if cred_offer.schema_id != cred_def.schema_id {
+        return Err(err_msg!(
+            "Credential offer is for schema {}, but the credential definition is for {}",
+            cred_offer.schema_id,
+            cred_def.schema_id
+        ));
+    }
+    if let Some(ttl) = offer_ttl {
+        if cred_offer.created.is_none() {
+            return Err(err_msg!(
+                "Credential offer has no creation time, so its age cannot be checked"
+            ));
+        }
+        if cred_offer.is_expired(now_timestamp()?, ttl) {
+            return Err(err_msg!("Credential offer has expired"));
+        }
+    }
+
+    trace!("check_credential_request <<<");
+
+    Ok(())
+}
+
+/// Sign a credential for the holder of `cred_request`.
+///
+/// Signing is CPU bound and should be run on a blocking thread by asynchronous
+/// callers. Every borrowed argument, including the references held by a
+/// `CredentialRevocationConfig`, is `Send + Sync`, and the `TailsReader` is `Send`:
+/// move owned copies of the inputs into the closure and borrow them from there.
+/// The returned `Credential` is `Send + Sync + 'static`.
+///
+/// The signature randomness is sampled by the CL implementation from the OpenSSL
+/// random number generator.
+///
+/// The request must be for the credential definition of the offer. The proof of the
+/// blinded master secret is verified before the credential is signed, so a request
+/// which does not answer the offer is rejected without computing a signature.
+///
+/// When `offer_ttl` is provided, requests for an offer created more than that many
+/// seconds ago are rejected, as are offers without a creation time. The creation time
+/// is not signed, so `cred_offer` must be the issuer's own copy of the offer rather
+/// than one returned by the holder.
+///
+/// When `nonces` is provided, a request for an already used offer is rejected as a
+/// replay before the credential is signed, and the nonce of the credential offer is
+/// consumed once the credential has been created. A request which fails to produce a
+/// credential leaves the offer usable.
+///
+/// The revocation index must be greater than 0 and less than the `max_cred_num` of the
+/// registry. Whether the index was already assigned to another credential is not
+/// checked, see `create_credentials`.
 pub fn create_credential(
     cred_def: &CredentialDefinition,
     cred_def_private: &CredentialDefinitionPrivate,
@@ -311,16 +446,222 @@ pub fn create_credential(
     rev_reg_id: Option<RevocationRegistryId>,
     rev_status_list: Option<&RevocationStatusList>,
     revocation_config: Option<CredentialRevocationConfig>,
+    offer_ttl: Option<u64>,
+    nonces: Option<&mut dyn NonceSeen>,
 ) -> Result<Credential> {
     trace!("create_credential >>> cred_def: {:?}, cred_def_private: {:?}, cred_offer.nonce: {:?}, cred_request: {:?},\
             cred_values: {:?}, revocation_config: {:?}",
             cred_def, secret!(&cred_def_private), &cred_offer.nonce, &cred_request, secret!(&cred_values), revocation_config,
             );
 
+    check_credential_request(cred_request, cred_offer, cred_def, offer_ttl)?;
+    check_credential_values(&cred_def.attribute_names()?, &cred_values)?;
+    check_offer_unused(cred_offer, nonces.as_deref())?;
+
+    let cred_public_key = cred_def.get_public_key().map_err(err_map!(
+        Unexpected,
+        "Error fetching public key from credential definition"
+    ))?;
+    let credential = _create_credential(
+        &cred_public_key,
+        cred_def_private,
+        cred_offer,
+        cred_request,
+        cred_values,
+        rev_reg_id,
+        rev_status_list,
+        revocation_config,
+    )?;
+    if let Some(nonces) = nonces {
+        if !nonces.consume(&cred_offer.nonce) {
+            return Err(offer_replay_error());
+        }
+    }
+
+    trace!(
+        "create_credential <<< credential {:?}",
+        secret!(&credential),
+    );
+
+    Ok(credential)
+}
+
+/// Issue a batch of credentials for the same credential definition.
+///
+/// Each entry contains the credential offer, the holder's credential request, the
+/// attribute values and an optional revocation configuration. The revocation indices
+/// must be greater than 0, less than the `max_cred_num` of the registry, unique within
+/// the batch and present in `rev_status_list`, otherwise the corresponding entry fails.
+/// An index already assigned by an earlier call cannot be detected: under issuance by
+/// default the status list does not record which indices have been handed out, so the
+/// issuer must track assigned indices itself. Failures are reported per entry so a
+/// single bad request does not abort the rest of the batch, and the outer error is only
+/// returned when the shared credential definition cannot be used.
+///
+/// The credential public key is reconstructed once for the whole batch instead of once
+/// per credential. The CL signature (and witness, when revocation is supported) is still
+/// computed for every holder, so the saving is limited to the per-call setup, which is
+/// only significant for credential definitions with many attributes or large batches.
+///
+/// The `offer_ttl` and `nonces` are applied to each offer as in `create_credential`:
+/// an already used offer is rejected before signing, and the nonce of an offer is
+/// consumed once its credential has been created. An offer repeated within the batch
+/// only produces a credential for its first entry, and is signed only once.
+pub fn create_credentials(
+    cred_def: &CredentialDefinition,
+    cred_def_private: &CredentialDefinitionPrivate,
+    rev_reg_id: Option<RevocationRegistryId>,
+    rev_status_list: Option<&RevocationStatusList>,
+    requests: Vec<(
+        &CredentialOffer,
+        &CredentialRequest,
+        CredentialValues,
+        Option<CredentialRevocationConfig>,
+    )>,
+    offer_ttl: Option<u64>,
+    mut nonces: Option<&mut dyn NonceSeen>,
+) -> Result<Vec<Result<Credential>>> {
+    trace!(
+        "create_credentials >>> cred_def: {:?}, cred_def_private: {:?}, rev_reg_id: {:?}, requests: {}",
+        cred_def,
+        secret!(&cred_def_private),
+        rev_reg_id,
+        requests.len()
+    );
+
     let cred_public_key = cred_def.get_public_key().map_err(err_map!(
         Unexpected,
         "Error fetching public key from credential definition"
     ))?;
+
+    let attr_names = cred_def.attribute_names()?;
+    let mut used_indices = HashSet::new();
+    let credentials = requests
+        .into_iter()
+        .map(
+            |(cred_offer, cred_request, cred_values, revocation_config)| {
+                check_credential_request(cred_request, cred_offer, cred_def, offer_ttl)?;
+                check_credential_values(&attr_names, &cred_values)?;
+                check_offer_unused(cred_offer, nonces.as_deref())?;
+                if let Some(revocation_config) = revocation_config.as_ref() {
+                    let idx = revocation_config.registry_idx;
+                    let rev_status_list = rev_status_list.ok_or_else(|| {
+                        err_msg!("A revocation status list is required for revocable credentials")
+                    })?;
+                    check_revocation_index(idx, revocation_config.reg_def.value.max_cred_num)?;
+                    if rev_status_list.get(idx as usize).is_none() {
+                        return Err(err_msg!(
+                            InvalidUserRevocId,
+                            "Revocation status list does not have the index {}",
+                            idx
+                        ));
+                    }
+                    if !used_indices.insert(idx) {
+                        return Err(err_msg!(
+                            InvalidUserRevocId,
+                            "Revocation index {} is used more than once in the batch",
+                            idx
+                        ));
+                    }
+                }
+                let credential = _create_credential(
+                    &cred_public_key,
+                    cred_def_private,
+                    cred_offer,
+                    cred_request,
+                    cred_values,
+                    rev_reg_id.clone(),
+                    rev_status_list,
+                    revocation_config,
+                )?;
+                if let Some(nonces) = nonces.as_deref_mut() {
+                    if !nonces.consume(&cred_offer.nonce) {
+                        return Err(offer_replay_error());
+                    }
+                }
+                Ok(credential)
+            },
+        )
+        .collect::<Vec<_>>();
+
+    trace!(
+        "create_credentials <<< credentials: {:?}",
+        secret!(&credentials)
+    );
+
+    Ok(credentials)
+}
+
+// Reject a replayed offer before the credential is signed. The nonce is only consumed
+// once the credential has been created, so a failed request leaves the offer usable.
+fn check_offer_unused(cred_offer: &CredentialOffer, nonces: Option<&dyn NonceSeen>) -> Result<()> {
+    match nonces {
+        Some(nonces) if nonces.seen(&cred_offer.nonce) => Err(offer_replay_error()),
+        _ => Ok(()),
+    }
+}
+
+fn offer_replay_error() -> Error {
+    err_msg!("Credential offer nonce has already been used")
+}
+
+// Reject credential values which do not match the attributes of the credential
+// definition before signing, as the CL signature error would not name them
+fn check_credential_values(
+    attr_names: &HashSet<String>,
+    cred_values: &CredentialValues,
+) -> Result<()> {
+    let mut value_names: HashMap<String, &String> = HashMap::new();
+    for name in cred_values.0.keys() {
+        if let Some(other) = value_names.insert(attr_common_view(name), name) {
+            return Err(invalid!(
+                SchemaMismatch,
+                "Credential values {:?} and {:?} are duplicates after normalization",
+                other,
+                name
+            )
+            .into());
+        }
+    }
+    let mut missing: Vec<&String> = attr_names
+        .iter()
+        .filter(|name| !value_names.contains_key(*name))
+        .collect();
+    let mut extra: Vec<&String> = value_names
+        .keys()
+        .filter(|name| !attr_names.contains(*name))
+        .collect();
+    if missing.is_empty() && extra.is_empty() {
+        return Ok(());
+    }
+    missing.sort();
+    extra.sort();
+    Err(invalid!(
+        SchemaMismatch,
+        "Credential values do not match the credential definition attributes: missing {:?}, extra {:?}",
+        missing,
+        extra
+    )
+    .into())
+}
+
+fn now_timestamp() -> Result<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .map_err(err_map!(Unexpected, "Error getting the current time"))
+}
+
+fn _create_credential(
+    cred_public_key: &CredentialPublicKey,
+    cred_def_private: &CredentialDefinitionPrivate,
+    cred_offer: &CredentialOffer,
+    cred_request: &CredentialRequest,
+    cred_values: CredentialValues,
+    rev_reg_id: Option<RevocationRegistryId>,
+    rev_status_list: Option<&RevocationStatusList>,
+    revocation_config: Option<CredentialRevocationConfig>,
+) -> Result<Credential> {
     let credential_values = build_credential_values(&cred_values.0, None)?;
     let rand_str = String::from_utf8(thread_rng().sample_iter(&Alphanumeric).take(22).collect())
         .map_err(|_| err_msg!("Unable to instantiate random string for prover did"))?;
@@ -329,6 +670,17 @@ pub fn create_credential(
     let (credential_signature, signature_correctness_proof, rev_reg, witness) =
         match (revocation_config, rev_status_list) {
             (Some(revocation_config), Some(rev_status_list)) => {
+                // Reject revocation indices outside of the registry before they reach
+                // the accumulator computations
+                check_revocation_index(
+                    revocation_config.registry_idx,
+                    revocation_config.reg_def.value.max_cred_num,
+                )?;
+                if revocation_config.verify_tails {
+                    revocation_config
+                        .reg_def
+                        .verify_tails(&revocation_config.tails_reader)?;
+                }
                 let rev_reg_def = &revocation_config.reg_def.value;
                 let rev_reg: Option<ursa::cl::RevocationRegistry> = rev_status_list.into();
                 let mut rev_reg = rev_reg.ok_or_else(|| {
@@ -342,11 +694,11 @@ pub fn create_credential(
                     .get(revocation_config.registry_idx as usize)
                     .ok_or_else(|| {
                         err_msg!(
+                            InvalidUserRevocId,
                             "Revocation status list does not have the index {}",
                             revocation_config.registry_idx
                         )
                     })?;
-
                 // This will be a temporary solution for the `issuance_on_demand` vs
                 // `issuance_by_default` state. Right now, we pass in the revcation status list and
                 // we check in this list whether the provided idx (revocation_config.registry_idx)
@@ -369,7 +721,7 @@ pub fn create_credential(
                         cred_offer.nonce.as_native(),
                         cred_request.nonce.as_native(),
                         &credential_values,
-                        &cred_public_key,
+                        cred_public_key,
                         &cred_def_private.value,
                         revocation_config.registry_idx,
                         rev_reg_def.max_cred_num,
@@ -413,7 +765,7 @@ pub fn create_credential(
                     cred_offer.nonce.as_native(),
                     cred_request.nonce.as_native(),
                     &credential_values,
-                    &cred_public_key,
+                    cred_public_key,
                     &cred_def_private.value,
                 )?;
                 (signature, correctness_proof, None, None)
@@ -431,11 +783,6 @@ pub fn create_credential(
         witness,
     };
 
-    trace!(
-        "create_credential <<< credential {:?}",
-        secret!(&credential),
-    );
-
     Ok(credential)
 }
 
@@ -509,5 +856,207 @@ mod tests {
             encode_credential_attribute("\x02").unwrap(),
             "99398763056634537812744552006896172984671876672520535998211840060697129507206"
         );
+
+        // negative and signed integers use the integer encoding
+        assert_eq!(encode_credential_attribute("-1").unwrap(), "-1");
+        assert_eq!(encode_credential_attribute("-0").unwrap(), "0");
+        assert_eq!(encode_credential_attribute("+5").unwrap(), "5");
+        assert_eq!(
+            encode_credential_attribute(" 1").unwrap(),
+            "101845468265237731434727701032909192901075578134913494203521508446598787009991"
+        );
+
+        // unicode
+        assert_eq!(
+            encode_credential_attribute("Zoë").unwrap(),
+            "89842672770476262810088087560007210096187736160362453030956468350699791970407"
+        );
+        assert_eq!(
+            encode_credential_attribute("日本語").unwrap(),
+            "54024958127689052707598281546242328173741089318791746144105291844749148807333"
+        );
+        assert_eq!(
+            encode_credential_attribute("😀").unwrap(),
+            "108675630968267008227778572451170725577785950928504733736152867604697062040281"
+        );
+    }
+
+    #[test]
+    fn test_create_credential_definition_attribute_limit() {
+        let schema = create_schema("test", "1.0", "mock:uri", ["a", "b", "c"][..].into()).unwrap();
+
+        // the implicit master secret attribute is counted
+        let create = |max_attributes| {
+            create_credential_definition(
+                "mock:uri",
+                &schema,
+                "mock:uri",
+                "tag",
+                SignatureType::CL,
+                CredentialDefinitionConfig::new(false).with_max_attributes(max_attributes),
+            )
+        };
+        assert_kind!(TooManyAttributes, create(3));
+        create(4).unwrap();
+
+        assert_eq!(
+            CredentialDefinitionConfig::default().max_attributes(),
+            MAX_ATTRIBUTES_COUNT + 1
+        );
+    }
+
+    #[test]
+    fn test_create_credential_definition_at_the_default_limit() {
+        let names: Vec<String> = (0..MAX_ATTRIBUTES_COUNT)
+            .map(|i| format!("attr{i}"))
+            .collect();
+        let schema = create_schema("test", "1.0", "mock:uri", names.into()).unwrap();
+        create_credential_definition(
+            "mock:uri",
+            &schema,
+            "mock:uri",
+            "tag",
+            SignatureType::CL,
+            CredentialDefinitionConfig::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_check_credential_values() {
+        let attr_names = HashSet::from(["name".to_string(), "age".to_string()]);
+        let make_values = |names: &[&str]| {
+            let mut values = MakeCredentialValues::default();
+            for name in names {
+                values.add_raw(*name, "1").unwrap();
+            }
+            CredentialValues::from(values)
+        };
+
+        check_credential_values(&attr_names, &make_values(&["name", "age"])).unwrap();
+        // names are normalized like schema attribute names
+        check_credential_values(&attr_names, &make_values(&["Name", " age"])).unwrap();
+
+        let err = check_credential_values(&attr_names, &make_values(&["name"])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Input);
+        assert!(err.to_string().contains(r#"missing ["age"], extra []"#));
+
+        let err = check_credential_values(&attr_names, &make_values(&["name", "age", "sex"]))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Input);
+        assert!(err.to_string().contains(r#"missing [], extra ["sex"]"#));
+
+        // names which only differ after normalization cannot both be given
+        let err = check_credential_values(&attr_names, &make_values(&["name", "Name", "age"]))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Input);
+        assert!(err.to_string().contains("duplicates after normalization"));
+    }
+
+    #[test]
+    fn test_create_credential_rejects_mismatched_values() {
+        let schema = create_schema("test", "1.0", "mock:uri", ["a", "b"][..].into()).unwrap();
+        let (cred_def, cred_def_private, key_correctness_proof) = create_credential_definition(
+            "mock:uri",
+            &schema,
+            "mock:uri",
+            "tag",
+            SignatureType::CL,
+            CredentialDefinitionConfig::new(false),
+        )
+        .unwrap();
+        let cred_offer =
+            create_credential_offer("mock:uri", "mock:cred_def", &key_correctness_proof, None)
+                .unwrap();
+        let master_secret = crate::prover::create_master_secret().unwrap();
+        let (cred_request, _) = crate::prover::create_credential_request(
+            None,
+            &cred_def,
+            &master_secret,
+            "default",
+            &cred_offer,
+            None,
+        )
+        .unwrap();
+
+        for (names, message) in [
+            (&["a"][..], r#"missing ["b"], extra []"#),
+            (&["a", "b", "c"][..], r#"missing [], extra ["c"]"#),
+        ] {
+            let mut values = MakeCredentialValues::default();
+            for name in names {
+                values.add_raw(*name, "1").unwrap();
+            }
+            let err = create_credential(
+                &cred_def,
+                &cred_def_private,
+                &cred_offer,
+                &cred_request,
+                values.into(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Input);
+            assert!(err.to_string().contains(message));
+        }
+    }
+
+    #[test]
+    fn test_check_credential_request() {
+        let schema = create_schema("test", "1.0", "mock:uri", ["a", "b", "c"][..].into()).unwrap();
+        let (cred_def, _, key_correctness_proof) = create_credential_definition(
+            "mock:uri",
+            &schema,
+            "mock:uri",
+            "tag",
+            SignatureType::CL,
+            CredentialDefinitionConfig::new(false),
+        )
+        .unwrap();
+        let cred_offer =
+            create_credential_offer("mock:uri", "mock:cred_def", &key_correctness_proof, None)
+                .unwrap();
+        let master_secret = crate::prover::create_master_secret().unwrap();
+        let (cred_request, _) = crate::prover::create_credential_request(
+            None,
+            &cred_def,
+            &master_secret,
+            "default",
+            &cred_offer,
+            None,
+        )
+        .unwrap();
+
+        check_credential_request(&cred_request, &cred_offer, &cred_def, None).unwrap();
+        check_credential_request(&cred_request, &cred_offer, &cred_def, Some(60)).unwrap();
+
+        let other_offer =
+            create_credential_offer("mock:uri", "mock:other", &key_correctness_proof, None)
+                .unwrap();
+        assert_kind!(
+            Input,
+            check_credential_request(&cred_request, &other_offer, &cred_def, None)
+        );
+
+        // expired offer, only rejected when a TTL is given
+        let mut cred_offer = cred_offer;
+        cred_offer.created = Some(cred_offer.created.unwrap() - 120);
+        check_credential_request(&cred_request, &cred_offer, &cred_def, None).unwrap();
+        assert_kind!(
+            Input,
+            check_credential_request(&cred_request, &cred_offer, &cred_def, Some(60))
+        );
+
+        // offer without a timestamp, only accepted without a TTL
+        cred_offer.created = None;
+        check_credential_request(&cred_request, &cred_offer, &cred_def, None).unwrap();
+        assert_kind!(
+            Input,
+            check_credential_request(&cred_request, &cred_offer, &cred_def, Some(60))
+        );
     }
 }
@@ -1,4 +1,4 @@
-mod helpers;
+pub(crate) mod helpers;
 
 pub mod issuer;
 pub mod prover;
@@ -7,5 +7,5 @@ pub mod types;
 pub mod verifier;
 
 pub mod utils {
-    pub use super::helpers::encode_credential_attribute;
+    pub use super::helpers::{encode_credential_attribute, encode_string_attribute};
 }
@@ -9,7 +9,10 @@ use super::types::*;
 use crate::data_types::{
     cred_def::{CredentialDefinition, CredentialDefinitionId},
     credential::AttributeValues,
-    pres_request::{PresentationRequestPayload, RequestedAttributeInfo, RequestedPredicateInfo},
+    pres_request::{
+        NonRevocedInterval, PresentationRequestPayload, PresentationRequestVersion,
+        RequestedAttributeInfo, RequestedPredicateInfo, RestrictionTag,
+    },
     presentation::{
         AttributeValue, Identifier, RequestedProof, RevealedAttributeGroupInfo,
         RevealedAttributeInfo, SubProofReferent,
@@ -18,6 +21,7 @@ use crate::data_types::{
     schema::{Schema, SchemaId},
 };
 use crate::error::{Error, Result};
+use crate::rng::CryptoRng;
 use crate::services::helpers::*;
 use crate::ursa::cl::{
     issuer::Issuer as CryptoIssuer, prover::Prover as CryptoProver,
@@ -27,18 +31,27 @@ use crate::ursa::cl::{
 };
 use crate::utils::validation::Validatable;
 
-use super::tails::TailsFileReader;
+#[cfg(feature = "parallel")]
+use super::tails::PreloadedTailsAccessor;
+use super::tails::{CachedTailsAccessor, TailsFileReader, TailsReader};
+use super::verifier::{process_identifier_filter, process_query, Filter};
 
 pub fn create_master_secret() -> Result<MasterSecret> {
     MasterSecret::new().map_err(err_map!(Unexpected))
 }
 
+/// Create a credential request for a credential offer.
+///
+/// The blinding factors are sampled by the CL implementation from the OpenSSL random
+/// number generator. The request nonce is sampled from `rng` when it is provided, and
+/// from the operating system's generator otherwise.
 pub fn create_credential_request(
     prover_did: Option<&str>,
     cred_def: &CredentialDefinition,
     master_secret: &MasterSecret,
     master_secret_id: &str,
     credential_offer: &CredentialOffer,
+    rng: Option<&mut dyn CryptoRng>,
 ) -> Result<(CredentialRequest, CredentialRequestMetadata)> {
     trace!(
         "create_credential_request >>> cred_def: {:?}, master_secret: {:?}, credential_offer: {:?}",
@@ -64,7 +77,7 @@ pub fn create_credential_request(
     credential_values_builder.add_value_hidden("master_secret", &master_secret.value.value()?)?;
     let cred_values = credential_values_builder.finalize()?;
 
-    let nonce = new_nonce()?;
+    let nonce = new_nonce(rng)?;
     let nonce_copy = nonce.try_clone().map_err(err_map!(Unexpected))?;
 
     let (blinded_ms, master_secret_blinding_data, blinded_ms_correctness_proof) =
@@ -133,6 +146,12 @@ pub fn process_credential(
     Ok(())
 }
 
+/// Create a presentation answering `pres_req` from the selected credentials.
+///
+/// Proof generation is CPU bound and should be run on a blocking thread by
+/// asynchronous callers. The `PresentCredentials` borrows its credentials, so build it
+/// inside the closure from owned credentials and revocation states, which are all
+/// `Send + Sync + 'static`, as are the other inputs and the returned `Presentation`.
 pub fn create_presentation(
     pres_req: &PresentationRequest,
     credentials: PresentCredentials,
@@ -158,8 +177,11 @@ pub fn create_presentation(
     credentials.validate()?;
 
     let pres_req_val = pres_req.value();
+    check_presented_referents(pres_req_val, &credentials, self_attested.as_ref())?;
     let mut proof_builder = CryptoProver::new_proof_builder()?;
-    proof_builder.add_common_attribute("master_secret")?;
+    if check_master_secrets(&credentials, master_secret)? {
+        proof_builder.add_common_attribute("master_secret")?;
+    }
 
     let mut requested_proof = RequestedProof {
         self_attested_attrs: self_attested.unwrap_or_default(),
@@ -170,11 +192,12 @@ pub fn create_presentation(
     let non_credential_schema = build_non_credential_schema()?;
 
     let mut identifiers: Vec<Identifier> = Vec::with_capacity(credentials.len());
-    for present in credentials.0 {
+    for present in credentials.creds {
         if present.is_empty() {
             continue;
         }
         let credential = present.cred;
+        let master_secret = present.master_secret.unwrap_or(master_secret);
 
         let schema = *schemas
             .get(&credential.schema_id)
@@ -201,6 +224,8 @@ pub fn create_presentation(
             present.requested_predicates,
             pres_req_val,
         )?;
+        check_credential_attributes(&credential.values.0, &req_attrs)?;
+        check_credential_predicates(&credential.values.0, &req_predicates)?;
         let sub_proof_request = build_sub_proof_request(&req_attrs, &req_predicates)?;
 
         proof_builder.add_sub_proof_request(
@@ -256,6 +281,38 @@ pub fn create_presentation(
     Ok(full_proof)
 }
 
+// Check whether all the presented credentials use the same master secret, in which
+// case the presentation proves that they are linked. Credentials with different
+// master secrets are only accepted when the holder allowed unlinked credentials.
+fn check_master_secrets(
+    credentials: &PresentCredentials,
+    default_master_secret: &MasterSecret,
+) -> Result<bool> {
+    let mut first = None;
+    for present in credentials.creds.iter().filter(|c| !c.is_empty()) {
+        let value = present
+            .master_secret
+            .unwrap_or(default_master_secret)
+            .value
+            .value()?;
+        match first {
+            None => first = Some((value, present.cred)),
+            Some((ref first_value, first_cred)) if *first_value != value => {
+                if credentials.allow_unlinked {
+                    return Ok(false);
+                }
+                return Err(err_msg!(
+                    "Credentials for {} and {} are bound to different master secrets and cannot be proven together",
+                    first_cred.cred_def_id,
+                    present.cred.cred_def_id
+                ));
+            }
+            _ => (),
+        }
+    }
+    Ok(true)
+}
+
 pub fn create_or_update_revocation_state_with_witness(
     witness: Witness,
     revocation_status_list: &RevocationStatusList,
@@ -282,6 +339,31 @@ pub fn create_or_update_revocation_state(
     rev_reg_idx: u32,
     rev_state: Option<&CredentialRevocationState>, // for witness update
     old_rev_status_list: Option<&RevocationStatusList>, // for witness update
+) -> Result<CredentialRevocationState> {
+    let tails_reader = TailsFileReader::new_tails_reader(tails_path);
+    create_or_update_revocation_state_with_tails_reader(
+        &tails_reader,
+        revoc_reg_def,
+        rev_status_list,
+        rev_reg_idx,
+        rev_state,
+        old_rev_status_list,
+        false,
+    )
+}
+
+// Same as `create_or_update_revocation_state`, reading the tails from any source,
+// for example a `TailsRemoteReader` when the tails file is not available locally.
+// With `verify_tails`, the tails data is first checked against the `tails_hash`
+// of the revocation registry definition
+pub fn create_or_update_revocation_state_with_tails_reader(
+    tails_reader: &TailsReader,
+    revoc_reg_def: &RevocationRegistryDefinition,
+    rev_status_list: &RevocationStatusList,
+    rev_reg_idx: u32,
+    rev_state: Option<&CredentialRevocationState>, // for witness update
+    old_rev_status_list: Option<&RevocationStatusList>, // for witness update
+    verify_tails: bool,
 ) -> Result<CredentialRevocationState> {
     trace!(
         "create_or_update_revocation_state >>> revoc_reg_def: {:?}, \
@@ -293,6 +375,10 @@ pub fn create_or_update_revocation_state(
         old_rev_status_list,
     );
 
+    if verify_tails {
+        revoc_reg_def.verify_tails(tails_reader)?;
+    }
+
     let rev_reg: Option<ursa::cl::RevocationRegistry> = rev_status_list.into();
     let rev_reg = rev_reg.ok_or_else(|| {
         err_msg!("revocation registry is required to create or update the revocation state")
@@ -302,60 +388,47 @@ pub fn create_or_update_revocation_state(
         err_msg!("Timestamp is required to create or update the revocation state")
     })?;
 
-    let mut issued = HashSet::<u32>::new();
-    let mut revoked = HashSet::<u32>::new();
-    let tails_reader = TailsFileReader::new_tails_reader(tails_path);
-    let witness = if let (Some(source_rev_state), Some(source_rev_list)) =
-        (rev_state, old_rev_status_list)
-    {
-        _create_index_deltas(
-            rev_status_list
-                .state_owned()
-                .bitxor(source_rev_list.state()),
-            rev_status_list.state(),
-            &mut issued,
-            &mut revoked,
-        );
+    let witness =
+        if let (Some(source_rev_state), Some(source_rev_list)) = (rev_state, old_rev_status_list) {
+            let mut issued = HashSet::<u32>::new();
+            let mut revoked = HashSet::<u32>::new();
+            _create_index_deltas(
+                rev_status_list
+                    .state_owned()
+                    .bitxor(source_rev_list.state()),
+                rev_status_list.state(),
+                &mut issued,
+                &mut revoked,
+            );
 
-        let source_rev_reg: Option<ursa::cl::RevocationRegistry> = source_rev_list.into();
+            let source_rev_reg: Option<ursa::cl::RevocationRegistry> = source_rev_list.into();
 
-        let rev_reg_delta = RevocationRegistryDelta::from_parts(
-            source_rev_reg.as_ref(),
-            &rev_reg,
-            &issued,
-            &revoked,
-        );
+            let rev_reg_delta = RevocationRegistryDelta::from_parts(
+                source_rev_reg.as_ref(),
+                &rev_reg,
+                &issued,
+                &revoked,
+            );
 
-        let mut witness = source_rev_state.witness.clone();
-        witness.update(
-            rev_reg_idx,
-            revoc_reg_def.value.max_cred_num,
-            &rev_reg_delta,
-            &tails_reader,
-        )?;
-        witness
-    } else {
-        let list_size = usize::try_from(revoc_reg_def.value.max_cred_num)
-            .map_err(|e| Error::from_msg(crate::ErrorKind::InvalidState, e.to_string()))?;
-        // Issuance by default
-        let bit: usize = 0;
-        let list = bitvec![bit; list_size];
-        _create_index_deltas(
-            rev_status_list.state_owned().bitxor(list),
-            rev_status_list.state(),
-            &mut issued,
-            &mut revoked,
-        );
-        let rev_reg_delta = RevocationRegistryDelta::from_parts(None, &rev_reg, &issued, &revoked);
-        Witness::new(
-            rev_reg_idx,
-            revoc_reg_def.value.max_cred_num,
-            // issuance by default
-            true,
-            &rev_reg_delta,
-            &tails_reader,
-        )?
-    };
+            let mut witness = source_rev_state.witness.clone();
+            witness.update(
+                rev_reg_idx,
+                revoc_reg_def.value.max_cred_num,
+                &rev_reg_delta,
+                tails_reader,
+            )?;
+            witness
+        } else {
+            let rev_reg_delta = _create_initial_delta(revoc_reg_def, rev_status_list, &rev_reg)?;
+            Witness::new(
+                rev_reg_idx,
+                revoc_reg_def.value.max_cred_num,
+                // issuance by default
+                true,
+                &rev_reg_delta,
+                tails_reader,
+            )?
+        };
 
     Ok(CredentialRevocationState {
         witness,
@@ -364,6 +437,167 @@ pub fn create_or_update_revocation_state(
     })
 }
 
+/// Create the revocation states of several credentials issued from the same
+/// revocation registry, as of `rev_status_list`.
+///
+/// The states are identical to those created one at a time by
+/// `create_or_update_revocation_state`. The registry delta is computed once and the
+/// tails read from `tails_reader` are cached, so that a tail used by several
+/// witnesses is only read once: a witness reads `max_cred_num - 1` tails, and the
+/// witnesses of all the indices of a registry share at most `2 * max_cred_num`
+/// distinct tails. The cache is limited to 16 MiB of tails, beyond which tails are
+/// read again for every witness.
+///
+/// Each witness is still accumulated on its own, so the computation grows with
+/// `rev_reg_idxs.len() * max_cred_num` as it does when the states are created one
+/// at a time. Only the tails reads are saved.
+///
+/// With the `parallel` feature every tail of the registry is read up front, in
+/// chunks, and held in memory while the witnesses are accumulated on the rayon
+/// thread pool.
+pub fn create_revocation_states(
+    revoc_reg_def: &RevocationRegistryDefinition,
+    rev_status_list: &RevocationStatusList,
+    rev_reg_idxs: &[u32],
+    tails_reader: &TailsReader,
+) -> Result<HashMap<u32, CredentialRevocationState>> {
+    trace!(
+        "create_revocation_states >>> revoc_reg_def: {:?}, rev_status_list: {:?}, rev_reg_idxs: {:?}",
+        revoc_reg_def,
+        rev_status_list,
+        rev_reg_idxs,
+    );
+
+    let rev_reg: Option<ursa::cl::RevocationRegistry> = rev_status_list.into();
+    let rev_reg = rev_reg.ok_or_else(|| {
+        err_msg!("revocation registry is required to create the revocation states")
+    })?;
+
+    let timestamp = rev_status_list
+        .timestamp()
+        .ok_or_else(|| err_msg!("Timestamp is required to create the revocation states"))?;
+
+    let rev_reg_delta = _create_initial_delta(revoc_reg_def, rev_status_list, &rev_reg)?;
+
+    let mut rev_reg_idxs = rev_reg_idxs.to_vec();
+    rev_reg_idxs.sort_unstable();
+    rev_reg_idxs.dedup();
+    #[cfg(feature = "parallel")]
+    let witnesses = _create_witnesses_parallel(
+        &rev_reg_idxs,
+        revoc_reg_def.value.max_cred_num,
+        &rev_reg_delta,
+        tails_reader,
+    )?;
+    #[cfg(not(feature = "parallel"))]
+    let witnesses = _create_witnesses(
+        &rev_reg_idxs,
+        revoc_reg_def.value.max_cred_num,
+        &rev_reg_delta,
+        tails_reader,
+    )?;
+
+    let rev_states = rev_reg_idxs
+        .into_iter()
+        .zip(witnesses)
+        .map(|(rev_reg_idx, witness)| {
+            (
+                rev_reg_idx,
+                CredentialRevocationState {
+                    witness,
+                    rev_reg: rev_reg.clone(),
+                    timestamp,
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    trace!("create_revocation_states <<< rev_states: {:?}", rev_states);
+
+    Ok(rev_states)
+}
+
+// Witnesses of the distinct indices `rev_reg_idxs`, in the same order
+fn _create_witnesses(
+    rev_reg_idxs: &[u32],
+    max_cred_num: u32,
+    rev_reg_delta: &RevocationRegistryDelta,
+    tails_reader: &TailsReader,
+) -> Result<Vec<Witness>> {
+    let tails_accessor = CachedTailsAccessor::new(tails_reader);
+    rev_reg_idxs
+        .iter()
+        .map(|&rev_reg_idx| {
+            // issuance by default
+            Witness::new(
+                rev_reg_idx,
+                max_cred_num,
+                true,
+                rev_reg_delta,
+                &tails_accessor,
+            )
+            .map_err(Error::from)
+        })
+        .collect()
+}
+
+// Witnesses of the distinct indices `rev_reg_idxs`, in the same order. Each
+// witness is accumulated on a single thread, so the result does not depend on
+// the number of threads. A single witness only reads half of the tails, so it is
+// not worth preloading them.
+#[cfg(feature = "parallel")]
+fn _create_witnesses_parallel(
+    rev_reg_idxs: &[u32],
+    max_cred_num: u32,
+    rev_reg_delta: &RevocationRegistryDelta,
+    tails_reader: &TailsReader,
+) -> Result<Vec<Witness>> {
+    use rayon::prelude::*;
+
+    if rev_reg_idxs.len() < 2 {
+        return _create_witnesses(rev_reg_idxs, max_cred_num, rev_reg_delta, tails_reader);
+    }
+    let tails_accessor = PreloadedTailsAccessor::new(tails_reader, max_cred_num)?;
+    rev_reg_idxs
+        .par_iter()
+        .map(|&rev_reg_idx| {
+            // issuance by default
+            Witness::new(
+                rev_reg_idx,
+                max_cred_num,
+                true,
+                rev_reg_delta,
+                &tails_accessor,
+            )
+            .map_err(Error::from)
+        })
+        .collect()
+}
+
+// Delta from an empty registry with issuance by default to `rev_status_list`
+fn _create_initial_delta(
+    revoc_reg_def: &RevocationRegistryDefinition,
+    rev_status_list: &RevocationStatusList,
+    rev_reg: &CryptoRevocationRegistry,
+) -> Result<RevocationRegistryDelta> {
+    let list_size = usize::try_from(revoc_reg_def.value.max_cred_num)
+        .map_err(|e| Error::from_msg(crate::ErrorKind::InvalidState, e.to_string()))?;
+    // Issuance by default
+    let bit: usize = 0;
+    let list = bitvec![bit; list_size];
+    let mut issued = HashSet::<u32>::new();
+    let mut revoked = HashSet::<u32>::new();
+    _create_index_deltas(
+        rev_status_list.state_owned().bitxor(list),
+        rev_status_list.state(),
+        &mut issued,
+        &mut revoked,
+    );
+    Ok(RevocationRegistryDelta::from_parts(
+        None, rev_reg, &issued, &revoked,
+    ))
+}
+
 fn _create_index_deltas(
     delta: bitvec::vec::BitVec,
     list: &bitvec::vec::BitVec,
@@ -381,6 +615,53 @@ fn _create_index_deltas(
     }
 }
 
+// Reject referents which are not part of the presentation request, or which are
+// answered both from a credential and as a self-attested attribute, before any
+// proof is built
+fn check_presented_referents(
+    pres_req: &PresentationRequestPayload,
+    credentials: &PresentCredentials,
+    self_attested: Option<&HashMap<String, String>>,
+) -> Result<()> {
+    let mut presented_attrs = HashSet::new();
+    for present in credentials.creds.iter() {
+        for (referent, _revealed) in present.requested_attributes.iter() {
+            if !pres_req.requested_attributes.contains_key(referent) {
+                return Err(err_msg!(
+                    "Attribute referent \"{}\" is not in the presentation request",
+                    referent
+                ));
+            }
+            presented_attrs.insert(referent.as_str());
+        }
+        for referent in present.requested_predicates.iter() {
+            if !pres_req.requested_predicates.contains_key(referent) {
+                return Err(err_msg!(
+                    "Predicate referent \"{}\" is not in the presentation request",
+                    referent
+                ));
+            }
+        }
+    }
+
+    for referent in self_attested.into_iter().flat_map(HashMap::keys) {
+        if !pres_req.requested_attributes.contains_key(referent) {
+            return Err(err_msg!(
+                "Self-attested attribute referent \"{}\" is not in the presentation request",
+                referent
+            ));
+        }
+        if presented_attrs.contains(referent.as_str()) {
+            return Err(err_msg!(
+                "Self-attested attribute referent \"{}\" is also presented from a credential",
+                referent
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn prepare_credential_for_proving(
     requested_attributes: HashSet<(String, bool)>,
     requested_predicates: HashSet<String>,
@@ -463,6 +744,85 @@ fn get_credential_values_for_attribute(
     res
 }
 
+// All the attributes of a `names` group must be proven from the same credential
+fn check_credential_attributes(
+    credential_attrs: &HashMap<String, AttributeValues>,
+    req_attrs: &[RequestedAttributeInfo],
+) -> Result<()> {
+    for RequestedAttributeInfo {
+        attr_referent,
+        attr_info,
+        ..
+    } in req_attrs
+    {
+        if let Some(name) = attr_info.name.as_ref() {
+            if get_credential_values_for_attribute(credential_attrs, name).is_none() {
+                return Err(err_msg!(
+                    "Credential value not found for attribute {:?} of referent {:?}",
+                    name,
+                    attr_referent
+                ));
+            }
+        } else if let Some(names) = attr_info.names.as_ref() {
+            if let Some(name) = names
+                .iter()
+                .find(|name| get_credential_values_for_attribute(credential_attrs, name).is_none())
+            {
+                return Err(err_msg!(
+                    "Attribute group {:?} must be satisfied by a single credential, which does not contain the attribute {:?}",
+                    attr_referent,
+                    name
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_credential_predicates(
+    credential_attrs: &HashMap<String, AttributeValues>,
+    req_predicates: &[RequestedPredicateInfo],
+) -> Result<()> {
+    for RequestedPredicateInfo {
+        predicate_referent,
+        predicate_info,
+    } in req_predicates
+    {
+        let attr_values =
+            get_credential_values_for_attribute(credential_attrs, &predicate_info.name)
+                .ok_or_else(|| {
+                    err_msg!(
+                        "Credential value not found for attribute {:?}",
+                        predicate_info.name
+                    )
+                })?;
+        let value = attr_values.encoded.parse::<i32>().map_err(|_| {
+            err_msg!(
+                "Credential value {:?} for predicate {:?} on attribute {:?} is not a 32-bit integer",
+                attr_values.raw,
+                predicate_referent,
+                predicate_info.name
+            )
+        })?;
+        if let Some((p_type, p_value)) = predicate_info
+            .bounds()
+            .find(|(p_type, p_value)| !p_type.is_satisfied_by(value, *p_value))
+        {
+            return Err(err_msg!(
+                "Credential value {:?} for attribute {:?} does not satisfy predicate {:?}: {} {}",
+                attr_values.raw,
+                predicate_info.name,
+                predicate_referent,
+                p_type,
+                p_value
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn update_requested_proof(
     req_attrs_for_credential: Vec<RequestedAttributeInfo>,
     req_predicates_for_credential: Vec<RequestedPredicateInfo>,
@@ -559,13 +919,13 @@ fn build_sub_proof_request(
     }
 
     for predicate in req_predicates_for_credential {
-        let p_type = format!("{}", predicate.predicate_info.p_type);
-
-        sub_proof_request_builder.add_predicate(
-            &attr_common_view(&predicate.predicate_info.name),
-            &p_type,
-            predicate.predicate_info.p_value,
-        )?;
+        for (p_type, p_value) in predicate.predicate_info.bounds() {
+            sub_proof_request_builder.add_predicate(
+                &attr_common_view(&predicate.predicate_info.name),
+                &p_type.to_string(),
+                p_value,
+            )?;
+        }
     }
 
     let sub_proof_request = sub_proof_request_builder.finalize()?;
@@ -578,6 +938,181 @@ fn build_sub_proof_request(
     Ok(sub_proof_request)
 }
 
+/// Resolve the revocation status list to prove non-revocation against for a
+/// non-revocation interval of a presentation request.
+///
+/// The list current at the end of the interval (or at `now` when it is open ended)
+/// is fetched from the resolver, and must have been published within the interval.
+/// The start of the interval is checked like the verifier does for a request of
+/// `version` verified with `params`: it is not checked for version 1.0 requests when
+/// `params` enable `legacy_v1_intervals`.
+///
+/// `create_presentation` does not take a resolver, as it only consumes revocation
+/// states: creating them from the resolved list also needs the tails file and the
+/// previous state of each credential. Pass the resolved list to
+/// `create_or_update_revocation_state` and present the resulting states.
+pub fn resolve_revocation_status_list(
+    resolver: &dyn RevocationStatusListResolver,
+    rev_reg_def_id: &RevocationRegistryDefinitionId,
+    interval: &NonRevocedInterval,
+    version: &PresentationRequestVersion,
+    params: Option<&VerifyParams>,
+    now: u64,
+) -> Result<RevocationStatusList> {
+    let params = params.copied().unwrap_or_default();
+    let enforce_from = super::verifier::enforces_interval_from(version, &params);
+    let requested = interval.to.unwrap_or(now);
+    let list = resolver.status_list_at(rev_reg_def_id, requested)?;
+
+    if let Some(id) = list.id() {
+        if &id != rev_reg_def_id {
+            return Err(err_msg!(
+                InvalidState,
+                "Resolved revocation status list is for {}, expected {}",
+                id,
+                rev_reg_def_id
+            ));
+        }
+    }
+    let timestamp = list
+        .timestamp()
+        .ok_or_else(|| err_msg!("Resolved revocation status list has no timestamp"))?;
+    let before_from = enforce_from && interval.from.map_or(false, |from| timestamp < from);
+    if timestamp > requested || before_from {
+        return Err(err_msg!(
+            "No revocation status list of {} was published within the non-revocation interval {:?} (resolved list timestamp: {})",
+            rev_reg_def_id,
+            interval,
+            timestamp
+        ));
+    }
+
+    Ok(list)
+}
+
+/// Find the held credentials which can be used for each referent of a presentation
+/// request, without performing any cryptographic operation.
+///
+/// A credential matches an attribute referent when it has all the requested attribute
+/// names and satisfies the restrictions, and it matches a predicate referent when it
+/// also has an integer value for the attribute within the predicate bounds.
+/// Non-revocation intervals are not considered. Restrictions on the schema name,
+/// version or issuer, or on the issuer of the credential definition, only match
+/// credentials whose schema and credential definition are provided. Restrictions
+/// are checked as by the verifier, so that `issuer_did` and `schema_issuer_did`
+/// only match legacy issuer identifiers.
+pub fn match_credentials<'c>(
+    pres_req: &PresentationRequest,
+    credentials: &'c [CredentialInfo],
+    schemas: &HashMap<&SchemaId, &Schema>,
+    cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
+) -> CredentialMatches<'c> {
+    let pres_req = pres_req.value();
+    let mut matches = CredentialMatches::default();
+
+    for (referent, info) in pres_req.requested_attributes.iter() {
+        let names = match (info.name.as_ref(), info.names.as_ref()) {
+            (Some(name), _) => vec![name.as_str()],
+            (None, Some(names)) => names.iter().map(String::as_str).collect(),
+            (None, None) => vec![],
+        };
+        let found = credentials
+            .iter()
+            .filter(|cred| {
+                !names.is_empty()
+                    && names
+                        .iter()
+                        .all(|name| credential_attribute(cred, name).is_some())
+                    && matches_restrictions(cred, info.restrictions.as_ref(), schemas, cred_defs)
+            })
+            .collect();
+        matches.attributes.insert(referent.clone(), found);
+    }
+
+    for (referent, info) in pres_req.requested_predicates.iter() {
+        let found = credentials
+            .iter()
+            .filter(|cred| {
+                credential_attribute(cred, &info.name)
+                    .and_then(|raw| raw.parse::<i32>().ok())
+                    .map_or(false, |value| info.is_satisfied_by(value))
+                    && matches_restrictions(cred, info.restrictions.as_ref(), schemas, cred_defs)
+            })
+            .collect();
+        matches.predicates.insert(referent.clone(), found);
+    }
+
+    matches
+}
+
+fn credential_attribute<'c>(cred: &'c CredentialInfo, name: &str) -> Option<&'c str> {
+    let name = attr_common_view(name);
+    cred.attrs
+        .iter()
+        .find(|(attr, _)| attr_common_view(attr) == name)
+        .map(|(_, raw)| raw.as_str())
+}
+
+fn matches_restrictions(
+    cred: &CredentialInfo,
+    restrictions: Option<&Query>,
+    schemas: &HashMap<&SchemaId, &Schema>,
+    cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
+) -> bool {
+    let query = match restrictions {
+        // empty restrictions do not restrict anything, as in the verifier
+        None => return true,
+        Some(Query::And(queries)) | Some(Query::Or(queries)) if queries.is_empty() => return true,
+        Some(query) => query,
+    };
+    let schema = schemas.get(&cred.schema_id).copied();
+    let cred_def = cred_defs.get(&cred.cred_def_id).copied();
+    // the verifier rejects unknown tags, and a tag which cannot be checked without the
+    // schema or credential definition must not match under `$neq` or `$not` either
+    let checkable = query.get_name().into_iter().all(|tag| {
+        RestrictionTag::parse(tag).map_or(false, |tag| {
+            (schema.is_some() || !tag.needs_schema())
+                && (cred_def.is_some() || !tag.needs_cred_def())
+        })
+    });
+    if !checkable {
+        return false;
+    }
+    let filter = Filter::new(&cred.schema_id, schema, &cred.cred_def_id, cred_def);
+    process_query(query, &|tag, value| {
+        credential_tag_matches(cred, tag, value, &filter)
+    })
+    .is_ok()
+}
+
+fn credential_tag_matches(
+    cred: &CredentialInfo,
+    tag: &str,
+    tag_value: &str,
+    filter: &Filter,
+) -> Result<()> {
+    if let Some(res) = process_identifier_filter(tag, tag_value, filter) {
+        return res;
+    }
+    // unlike the verifier, the prover knows the value of every attribute
+    let found = match RestrictionTag::parse(tag) {
+        Some(RestrictionTag::AttributeValue(name)) => credential_attribute(cred, name),
+        Some(RestrictionTag::AttributeMarker(name)) => {
+            credential_attribute(cred, name).map(|_| "1")
+        }
+        _ => None,
+    };
+    if found == Some(tag_value) {
+        Ok(())
+    } else {
+        Err(err_msg!(
+            ProofRejected,
+            "\"{}\" does not match the credential",
+            tag
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -630,6 +1165,7 @@ mod tests {
                 name: "age".to_string(),
                 p_type: PredicateTypes::GE,
                 p_value: 8,
+                second_bound: None,
                 restrictions: None,
                 non_revoked: None,
             }
@@ -637,7 +1173,7 @@ mod tests {
 
         fn _proof_req() -> PresentationRequestPayload {
             PresentationRequestPayload {
-                nonce: new_nonce().unwrap(),
+                nonce: new_nonce(None).unwrap(),
                 name: "Job-Application".to_string(),
                 version: "0.1".to_string(),
                 requested_attributes: hashmap!(
@@ -776,6 +1312,71 @@ mod tests {
         }
     }
 
+    mod check_credential_predicates {
+        use crate::data_types::pres_request::{PredicateBound, PredicateInfo};
+
+        use super::*;
+
+        fn _req_predicate(p_type: PredicateTypes, p_value: i32) -> RequestedPredicateInfo {
+            RequestedPredicateInfo {
+                predicate_referent: "predicate_referent".to_string(),
+                predicate_info: PredicateInfo {
+                    name: "age".to_string(),
+                    p_type,
+                    p_value,
+                    second_bound: None,
+                    restrictions: None,
+                    non_revoked: None,
+                },
+            }
+        }
+
+        fn _cred_values(raw: &str, encoded: &str) -> HashMap<String, AttributeValues> {
+            hashmap!("age".to_string() => AttributeValues {
+                raw: raw.to_string(),
+                encoded: encoded.to_string(),
+            })
+        }
+
+        #[test]
+        fn check_credential_predicates_works() {
+            let res = check_credential_predicates(
+                &_cred_values("28", "28"),
+                &[_req_predicate(PredicateTypes::GE, 18)],
+            );
+            assert!(res.is_ok());
+        }
+
+        #[test]
+        fn check_credential_predicates_works_for_unsatisfied_predicate() {
+            let res = check_credential_predicates(
+                &_cred_values("16", "16"),
+                &[_req_predicate(PredicateTypes::GE, 18)],
+            );
+            assert_kind!(Input, res);
+        }
+
+        #[test]
+        fn check_credential_predicates_works_for_unsatisfied_second_bound() {
+            let mut predicate = _req_predicate(PredicateTypes::GE, 18);
+            predicate.predicate_info.second_bound = Some(PredicateBound {
+                p_type: PredicateTypes::LT,
+                p_value: 25,
+            });
+            let res = check_credential_predicates(&_cred_values("28", "28"), &[predicate]);
+            assert_kind!(Input, res);
+        }
+
+        #[test]
+        fn check_credential_predicates_works_for_non_integer_value() {
+            let res = check_credential_predicates(
+                &_cred_values("Alex", "1139481716457488690172217916278103335"),
+                &[_req_predicate(PredicateTypes::GE, 18)],
+            );
+            assert_kind!(Input, res);
+        }
+    }
+
     mod using_prover_did_with_new_and_legacy_identifiers {
         use crate::issuer::{create_credential_definition, create_credential_offer, create_schema};
 
@@ -787,9 +1388,10 @@ mod tests {
 
         const PROVER_DID: &str = "NcYxiDXkpYi6ov5FcYDi1e";
 
-        const LEGACY_SCHEMA_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e";
+        const LEGACY_SCHEMA_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e:2:test:1.0";
         const LEGACY_ISSUER_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e";
-        const LEGACY_CRED_DEF_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e";
+        const LEGACY_CRED_DEF_ID: &str =
+            "NcYxiDXkpYi6ov5FcYDi1e:3:CL:NcYxiDXkpYi6ov5FcYDi1e:2:test:1.0:tag";
 
         fn _master_secret() -> MasterSecret {
             MasterSecret::new().expect("Error creating prover master secret")
@@ -807,16 +1409,14 @@ mod tests {
                 ISSUER_ID,
                 "tag",
                 SignatureType::CL,
-                CredentialDefinitionConfig {
-                    support_revocation: false,
-                },
+                CredentialDefinitionConfig::new(false),
             )
             .unwrap();
             (cred_def, key_correctness_proof)
         }
 
         fn _cred_offer(key_correctness_proof: CredentialKeyCorrectnessProof) -> CredentialOffer {
-            create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &key_correctness_proof).unwrap()
+            create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &key_correctness_proof, None).unwrap()
         }
 
         fn _legacy_schema() -> Schema {
@@ -831,9 +1431,7 @@ mod tests {
                 LEGACY_ISSUER_ID,
                 "tag",
                 SignatureType::CL,
-                CredentialDefinitionConfig {
-                    support_revocation: false,
-                },
+                CredentialDefinitionConfig::new(false),
             )
             .unwrap();
             (cred_def, key_correctness_proof)
@@ -842,8 +1440,13 @@ mod tests {
         fn _legacy_cred_offer(
             key_correctness_proof: CredentialKeyCorrectnessProof,
         ) -> CredentialOffer {
-            create_credential_offer(LEGACY_SCHEMA_ID, LEGACY_CRED_DEF_ID, &key_correctness_proof)
-                .unwrap()
+            create_credential_offer(
+                LEGACY_SCHEMA_ID,
+                LEGACY_CRED_DEF_ID,
+                &key_correctness_proof,
+                None,
+            )
+            .unwrap()
         }
 
         #[test]
@@ -851,8 +1454,14 @@ mod tests {
             let (cred_def, key_correctness_proof) = _cred_def_and_key_correctness_proof();
             let master_secret = _master_secret();
             let cred_offer = _cred_offer(key_correctness_proof);
-            let resp =
-                create_credential_request(None, &cred_def, &master_secret, "default", &cred_offer);
+            let resp = create_credential_request(
+                None,
+                &cred_def,
+                &master_secret,
+                "default",
+                &cred_offer,
+                None,
+            );
             assert!(resp.is_ok())
         }
 
@@ -867,6 +1476,7 @@ mod tests {
                 &master_secret,
                 "default",
                 &cred_offer,
+                None,
             );
             assert!(resp.is_ok())
         }
@@ -876,8 +1486,14 @@ mod tests {
             let (cred_def, key_correctness_proof) = _legacy_cred_def_and_key_correctness_proof();
             let master_secret = _master_secret();
             let cred_offer = _legacy_cred_offer(key_correctness_proof);
-            let resp =
-                create_credential_request(None, &cred_def, &master_secret, "default", &cred_offer);
+            let resp = create_credential_request(
+                None,
+                &cred_def,
+                &master_secret,
+                "default",
+                &cred_offer,
+                None,
+            );
             assert!(resp.is_ok())
         }
 
@@ -892,6 +1508,7 @@ mod tests {
                 &master_secret,
                 "default",
                 &cred_offer,
+                None,
             );
             assert!(resp.is_err())
         }
@@ -907,8 +1524,267 @@ mod tests {
                 &master_secret,
                 "default",
                 &cred_offer,
+                None,
             );
             assert!(resp.is_err())
         }
     }
+
+    mod match_credentials {
+        use super::*;
+
+        fn _cred_info(referent: &str, schema_id: &str, attrs: &[(&str, &str)]) -> CredentialInfo {
+            CredentialInfo {
+                referent: referent.to_string(),
+                attrs: attrs
+                    .iter()
+                    .map(|(name, raw)| (name.to_string(), raw.to_string()))
+                    .collect(),
+                schema_id: SchemaId::new_unchecked(schema_id),
+                cred_def_id: CredentialDefinitionId::new_unchecked("mock:cred_def"),
+                rev_reg_id: None,
+                cred_rev_id: None,
+            }
+        }
+
+        fn _referents(found: &[&CredentialInfo]) -> Vec<String> {
+            let mut referents: Vec<String> =
+                found.iter().map(|cred| cred.referent.clone()).collect();
+            referents.sort();
+            referents
+        }
+
+        #[test]
+        fn match_credentials_filters_by_request() {
+            let creds = [
+                _cred_info(
+                    "alex",
+                    "mock:gvt",
+                    &[("name", "Alex"), ("age", "28"), ("height", "175")],
+                ),
+                _cred_info("sam", "mock:gvt", &[("name", "Sam"), ("age", "16")]),
+                _cred_info("other", "mock:xyz", &[("Name", "Alex"), ("age", "old")]),
+            ];
+            let schema = crate::issuer::create_schema(
+                "gvt",
+                "1.0",
+                "mock:issuer",
+                ["name", "age"][..].into(),
+            )
+            .unwrap();
+            let schema_id = SchemaId::new_unchecked("mock:gvt");
+            let schemas = hashmap!(&schema_id => &schema);
+
+            let pres_req: PresentationRequest = serde_json::from_value(json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "1.0",
+                "ver": "2.0",
+                "requested_attributes": {
+                    "any_name": {"name": "name"},
+                    "group": {"names": ["name", "height"]},
+                    "gvt_name": {"name": "name", "restrictions": {"schema_name": "gvt"}},
+                    "not_alex": {
+                        "name": "age",
+                        "restrictions": {"$not": {"attr::name::value": "Alex"}}
+                    },
+                    "marker": {
+                        "name": "age",
+                        "restrictions": [{"attr::height::marker": "1"}, {"schema_id": "mock:xyz"}]
+                    },
+                    "issuer": {"name": "name", "restrictions": {"issuer_id": "mock:issuer"}}
+                },
+                "requested_predicates": {
+                    "adult": {"name": "age", "p_type": ">=", "p_value": 18},
+                    "young": {
+                        "name": "age",
+                        "p_type": "<",
+                        "p_value": 30,
+                        "restrictions": {"schema_id": "mock:gvt"}
+                    }
+                }
+            }))
+            .unwrap();
+
+            let matches = match_credentials(&pres_req, &creds, &schemas, &HashMap::new());
+            let attrs = |referent: &str| _referents(&matches.attributes[referent]);
+            let preds = |referent: &str| _referents(&matches.predicates[referent]);
+
+            assert_eq!(attrs("any_name"), ["alex", "other", "sam"]);
+            assert_eq!(attrs("group"), ["alex"]);
+            assert_eq!(attrs("gvt_name"), ["alex", "sam"]);
+            assert_eq!(attrs("not_alex"), ["sam"]);
+            assert_eq!(attrs("marker"), ["alex", "other"]);
+            // the credential definition is required to match on its issuer
+            assert!(attrs("issuer").is_empty());
+            assert_eq!(preds("adult"), ["alex"]);
+            assert_eq!(preds("young"), ["alex", "sam"]);
+        }
+
+        #[test]
+        fn match_credentials_only_matches_legacy_issuer_dids() {
+            const LEGACY_ISSUER: &str = "NcYxiDXkpYi6ov5FcYDi1e";
+
+            let creds = [
+                _cred_info("legacy", "mock:legacy", &[("name", "Alex")]),
+                _cred_info("uri", "mock:uri", &[("name", "Alex")]),
+            ];
+            let legacy_schema =
+                crate::issuer::create_schema("gvt", "1.0", LEGACY_ISSUER, ["name"][..].into())
+                    .unwrap();
+            let uri_schema =
+                crate::issuer::create_schema("gvt", "1.0", "mock:issuer", ["name"][..].into())
+                    .unwrap();
+            let legacy_id = SchemaId::new_unchecked("mock:legacy");
+            let uri_id = SchemaId::new_unchecked("mock:uri");
+            let schemas = hashmap!(&legacy_id => &legacy_schema, &uri_id => &uri_schema);
+
+            let pres_req: PresentationRequest = serde_json::from_value(json!({
+                "nonce": "123456",
+                "name": "name",
+                "version": "1.0",
+                "requested_attributes": {
+                    "legacy": {
+                        "name": "name",
+                        "restrictions": {"schema_issuer_did": LEGACY_ISSUER}
+                    },
+                    "uri": {
+                        "name": "name",
+                        "restrictions": {"schema_issuer_did": "mock:issuer"}
+                    },
+                    "new": {
+                        "name": "name",
+                        "restrictions": {"schema_issuer_id": "mock:issuer"}
+                    }
+                }
+            }))
+            .unwrap();
+
+            let matches = match_credentials(&pres_req, &creds, &schemas, &HashMap::new());
+            let attrs = |referent: &str| _referents(&matches.attributes[referent]);
+
+            assert_eq!(attrs("legacy"), ["legacy"]);
+            // the verifier would reject the legacy tag for a URI issuer
+            assert!(attrs("uri").is_empty());
+            assert_eq!(attrs("new"), ["uri"]);
+        }
+    }
+
+    mod resolve_revocation_status_list {
+        use std::collections::BTreeMap;
+
+        use super::*;
+
+        struct Resolver(BTreeMap<u64, RevocationStatusList>);
+
+        impl RevocationStatusListResolver for Resolver {
+            fn status_list_at(
+                &self,
+                _rev_reg_def_id: &RevocationRegistryDefinitionId,
+                timestamp: u64,
+            ) -> Result<RevocationStatusList> {
+                self.0
+                    .range(..=timestamp)
+                    .next_back()
+                    .map(|(_, list)| list.clone())
+                    .ok_or_else(|| err_msg!("No status list at {}", timestamp))
+            }
+        }
+
+        fn _resolver(id: &str) -> Resolver {
+            Resolver(
+                [10, 20, 30]
+                    .into_iter()
+                    .map(|t| {
+                        (
+                            t,
+                            RevocationStatusList::new(Some(id), bitvec![0; 4], None, Some(t))
+                                .unwrap(),
+                        )
+                    })
+                    .collect(),
+            )
+        }
+
+        fn _resolve_for(
+            resolver: &Resolver,
+            from: Option<u64>,
+            to: Option<u64>,
+            version: PresentationRequestVersion,
+            params: Option<&VerifyParams>,
+        ) -> Result<u64> {
+            let id = RevocationRegistryDefinitionId::new_unchecked("mock:reg");
+            resolve_revocation_status_list(
+                resolver,
+                &id,
+                &NonRevocedInterval { from, to },
+                &version,
+                params,
+                25,
+            )
+            .map(|list| list.timestamp().unwrap())
+        }
+
+        fn _resolve(resolver: &Resolver, from: Option<u64>, to: Option<u64>) -> Result<u64> {
+            _resolve_for(resolver, from, to, PresentationRequestVersion::V2, None)
+        }
+
+        #[test]
+        fn resolves_latest_list_within_interval() {
+            let resolver = _resolver("mock:reg");
+            assert_eq!(_resolve(&resolver, None, Some(29)).unwrap(), 20);
+            assert_eq!(_resolve(&resolver, Some(15), Some(30)).unwrap(), 30);
+            assert_eq!(_resolve(&resolver, Some(20), Some(20)).unwrap(), 20);
+            // open ended intervals resolve the list current now
+            assert_eq!(_resolve(&resolver, Some(5), None).unwrap(), 20);
+        }
+
+        #[test]
+        fn rejects_lists_outside_interval() {
+            let resolver = _resolver("mock:reg");
+            // the latest list before the end of the interval precedes it
+            assert_kind!(Input, _resolve(&resolver, Some(21), Some(29)));
+            assert!(_resolve(&resolver, None, Some(5)).is_err());
+
+            let other = _resolver("mock:other");
+            assert_kind!(InvalidState, _resolve(&other, None, Some(29)));
+        }
+
+        #[test]
+        fn legacy_intervals_only_check_the_end_of_v1_intervals() {
+            let resolver = _resolver("mock:reg");
+            let legacy = VerifyParams::default().with_legacy_v1_intervals(true);
+            assert_eq!(
+                _resolve_for(
+                    &resolver,
+                    Some(21),
+                    Some(29),
+                    PresentationRequestVersion::V1,
+                    Some(&legacy)
+                )
+                .unwrap(),
+                20
+            );
+            assert_kind!(
+                Input,
+                _resolve_for(
+                    &resolver,
+                    Some(21),
+                    Some(29),
+                    PresentationRequestVersion::V2,
+                    Some(&legacy)
+                )
+            );
+            assert_kind!(
+                Input,
+                _resolve_for(
+                    &resolver,
+                    Some(21),
+                    Some(29),
+                    PresentationRequestVersion::V1,
+                    None
+                )
+            );
+        }
+    }
 }
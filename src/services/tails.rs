@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::PathBuf;
@@ -7,6 +8,7 @@ use crate::utils::base58;
 use sha2::{Digest, Sha256};
 use tempfile;
 
+use crate::data_types::rev_reg_def::RevocationRegistryDefinition;
 use crate::error::Result;
 use crate::ursa::{
     cl::{RevocationTailsAccessor, RevocationTailsGenerator, Tail},
@@ -14,6 +16,7 @@ use crate::ursa::{
 };
 
 const TAILS_BLOB_TAG_SZ: u8 = 2;
+const TAILS_BLOB_VERSION: [u8; 2] = [0u8, 2u8];
 const TAIL_SIZE: usize = Tail::BYTES_REPR_SIZE;
 
 #[derive(Debug)]
@@ -22,11 +25,17 @@ pub struct TailsReader {
 }
 
 impl TailsReader {
-    pub(crate) fn new<TR: TailsReaderImpl + 'static>(inner: TR) -> Self {
+    /// Wrap a custom source of tails data, for use with the issuer and prover services
+    pub fn new<TR: TailsReaderImpl + 'static>(inner: TR) -> Self {
         Self {
             inner: Box::new(RefCell::new(inner)),
         }
     }
+
+    /// The SHA-256 hash of the tails data
+    pub fn hash(&self) -> Result<Vec<u8>> {
+        self.inner.borrow_mut().hash()
+    }
 }
 
 pub trait TailsReaderImpl: std::fmt::Debug + Send {
@@ -34,16 +43,9 @@ pub trait TailsReaderImpl: std::fmt::Debug + Send {
     fn read(&mut self, size: usize, offset: usize) -> Result<Vec<u8>>;
 }
 
-impl RevocationTailsAccessor for TailsReader {
-    fn access_tail(
-        &self,
-        tail_id: u32,
-        accessor: &mut dyn FnMut(&Tail),
-    ) -> std::result::Result<(), UrsaCryptoError> {
-        trace!("access_tail >>> tail_id: {:?}", tail_id);
-
-        let tail_bytes = self
-            .inner
+impl TailsReader {
+    fn read_tail_bytes(&self, tail_id: u32) -> std::result::Result<Vec<u8>, UrsaCryptoError> {
+        self.inner
             .borrow_mut()
             .read(
                 TAIL_SIZE,
@@ -54,8 +56,19 @@ impl RevocationTailsAccessor for TailsReader {
                     UrsaCryptoErrorKind::InvalidState,
                     "Can't read tail bytes from file",
                 )
-            })?; // FIXME: IO error should be returned
+            }) // FIXME: IO error should be returned
+    }
+}
+
+impl RevocationTailsAccessor for TailsReader {
+    fn access_tail(
+        &self,
+        tail_id: u32,
+        accessor: &mut dyn FnMut(&Tail),
+    ) -> std::result::Result<(), UrsaCryptoError> {
+        trace!("access_tail >>> tail_id: {:?}", tail_id);
 
+        let tail_bytes = self.read_tail_bytes(tail_id)?;
         let tail = Tail::from_bytes(tail_bytes.as_slice())?;
         accessor(&tail);
 
@@ -64,6 +77,134 @@ impl RevocationTailsAccessor for TailsReader {
     }
 }
 
+// Check that the tails data matches the `tails_hash` of a revocation registry
+// definition, see `RevocationRegistryDefinition::verify_tails`
+pub(crate) fn verify_tails(
+    rev_reg_def: &RevocationRegistryDefinition,
+    tails_reader: &TailsReader,
+) -> Result<()> {
+    let tails_hash = &rev_reg_def.value.tails_hash;
+    let expected =
+        base58::decode(tails_hash).map_err(err_map!("Invalid tails hash: {}", tails_hash))?;
+    let hash = tails_reader.hash()?;
+    if hash != expected {
+        return Err(err_msg!(
+            TailsHashMismatch,
+            "Tails file hash mismatch for {}: expected {}, found {}",
+            rev_reg_def.value.tails_location,
+            tails_hash,
+            base58::encode(&hash)
+        ));
+    }
+    Ok(())
+}
+
+/// Maximum number of bytes of tails kept by a `CachedTailsAccessor`
+const CACHED_TAILS_SIZE: usize = 16 * 1024 * 1024;
+
+/// Tails accessor keeping the tails read from the underlying reader, so that the
+/// witnesses of several revocation indices only read each tail once.
+///
+/// At most `CACHED_TAILS_SIZE` bytes of tails are kept. Once the cache is full the
+/// tails already held are kept and any other tail is read on every access: each
+/// witness scans its tails in order, so evicting the oldest tails would evict every
+/// tail before the next witness uses it.
+#[derive(Debug)]
+pub(crate) struct CachedTailsAccessor<'r> {
+    reader: &'r TailsReader,
+    tails: RefCell<HashMap<u32, Vec<u8>>>,
+    capacity: usize,
+}
+
+impl<'r> CachedTailsAccessor<'r> {
+    pub fn new(reader: &'r TailsReader) -> Self {
+        Self {
+            reader,
+            tails: RefCell::new(HashMap::new()),
+            capacity: CACHED_TAILS_SIZE / TAIL_SIZE,
+        }
+    }
+}
+
+impl RevocationTailsAccessor for CachedTailsAccessor<'_> {
+    fn access_tail(
+        &self,
+        tail_id: u32,
+        accessor: &mut dyn FnMut(&Tail),
+    ) -> std::result::Result<(), UrsaCryptoError> {
+        let tail = {
+            let mut tails = self.tails.borrow_mut();
+            match tails.get(&tail_id) {
+                Some(tail_bytes) => Tail::from_bytes(tail_bytes)?,
+                None => {
+                    let tail_bytes = self.reader.read_tail_bytes(tail_id)?;
+                    let tail = Tail::from_bytes(&tail_bytes)?;
+                    if tails.len() < self.capacity {
+                        tails.insert(tail_id, tail_bytes);
+                    }
+                    tail
+                }
+            }
+        };
+        accessor(&tail);
+        Ok(())
+    }
+}
+
+/// Number of tails requested at once by a `PreloadedTailsAccessor`
+#[cfg(feature = "parallel")]
+const PRELOAD_CHUNK_TAILS: usize = 4096;
+
+/// Tails accessor holding every tail of a registry in memory, read from the
+/// underlying reader in chunks of `PRELOAD_CHUNK_TAILS` tails. Unlike
+/// `CachedTailsAccessor` it can be shared between threads.
+///
+/// The whole registry is held in memory, which is `TAIL_SIZE` bytes for each of the
+/// `2 * max_cred_num + 1` tails: about 25 MiB for a registry of 100000 credentials.
+#[cfg(feature = "parallel")]
+#[derive(Debug)]
+pub(crate) struct PreloadedTailsAccessor {
+    tails: Vec<u8>,
+}
+
+#[cfg(feature = "parallel")]
+impl PreloadedTailsAccessor {
+    /// Read the `2 * max_cred_num + 1` tails generated for a registry
+    pub fn new(reader: &TailsReader, max_cred_num: u32) -> Result<Self> {
+        let count = 2 * max_cred_num as usize + 1;
+        let mut tails = Vec::with_capacity(TAIL_SIZE * count);
+        let mut inner = reader.inner.borrow_mut();
+        for start in (0..count).step_by(PRELOAD_CHUNK_TAILS) {
+            let chunk = PRELOAD_CHUNK_TAILS.min(count - start);
+            tails.extend(inner.read(
+                TAIL_SIZE * chunk,
+                TAIL_SIZE * start + TAILS_BLOB_TAG_SZ as usize,
+            )?);
+        }
+        Ok(Self { tails })
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl RevocationTailsAccessor for PreloadedTailsAccessor {
+    fn access_tail(
+        &self,
+        tail_id: u32,
+        accessor: &mut dyn FnMut(&Tail),
+    ) -> std::result::Result<(), UrsaCryptoError> {
+        let start = TAIL_SIZE * tail_id as usize;
+        let tail_bytes = self.tails.get(start..start + TAIL_SIZE).ok_or_else(|| {
+            UrsaCryptoError::from_msg(
+                UrsaCryptoErrorKind::InvalidState,
+                format!("Tail index out of range: {}", tail_id),
+            )
+        })?;
+        let tail = Tail::from_bytes(tail_bytes)?;
+        accessor(&tail);
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct TailsFileReader {
     path: String,
@@ -80,6 +221,28 @@ impl TailsFileReader {
         })
     }
 
+    /// Open a tails file for streaming access. The file is opened immediately and
+    /// its header checked, so that a missing or unsupported file is reported here
+    /// rather than on first use. As with `new_tails_reader`, the file is not loaded
+    /// as a whole: each read seeks to and reads only the requested range.
+    pub fn open_streaming(path: &str) -> Result<TailsReader> {
+        let mut reader = Self {
+            path: path.to_owned(),
+            file: None,
+            hash: None,
+        };
+        reader.open()?;
+        let version = reader.read(TAILS_BLOB_TAG_SZ as usize, 0)?;
+        if version != TAILS_BLOB_VERSION {
+            return Err(err_msg!(
+                "Unsupported tails file version {:?}: {}",
+                version,
+                path
+            ));
+        }
+        Ok(TailsReader::new(reader))
+    }
+
     pub fn open(&mut self) -> Result<()> {
         if self.file.is_some() {
             Ok(())
@@ -129,6 +292,202 @@ impl TailsReaderImpl for TailsFileReader {
     }
 }
 
+/// Size of the ranges requested when hashing a remote tails file
+const REMOTE_HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Callback used to fetch a byte range of a remote tails file, given its location,
+/// the offset and the number of bytes requested. Fewer bytes may only be returned
+/// at the end of the file.
+pub type TailsFetchFn = dyn FnMut(&str, u64, usize) -> Result<Vec<u8>> + Send;
+
+/// Reader for tails files which are not available locally, such as those published
+/// over HTTP. Tail points are fetched on demand through a caller-provided callback,
+/// so no networking dependency is required and the tails file is never stored in full.
+///
+/// The file is fetched in fixed size chunks, and each read only fetches the chunks it
+/// covers. The last chunk read is kept, so reading the tail points in sequence fetches
+/// each chunk once.
+///
+/// The tails file format only records the hash of the whole file, so checking the
+/// data against the `tails_hash` of a revocation registry definition requires
+/// fetching the whole file once. This is opt-in: it happens when the reader is
+/// created with `new_verified_tails_reader`, or when its hash is requested, as with
+/// the `verify_tails` options of the issuer and prover services. The hash of each
+/// chunk is recorded during that pass, and every later read checks the chunks it
+/// fetches against those hashes, so the data returned is the verified data even when
+/// the remote source changes. Reads made before the file is hashed are not checked.
+pub struct TailsRemoteReader {
+    location: String,
+    fetch: Box<TailsFetchFn>,
+    hash: Option<Vec<u8>>,
+    chunk_hashes: Vec<Vec<u8>>,
+    chunk: Option<(u64, Vec<u8>)>,
+}
+
+impl TailsRemoteReader {
+    /// Create a tails reader for the tails file at `location`, which is normally the
+    /// `tails_location` of the revocation registry definition.
+    ///
+    /// Nothing is fetched until the reader is used, and only the chunks covering the
+    /// tail points read are fetched. The data is not checked against the tails hash
+    /// unless the hash of the reader is requested, which fetches the whole file.
+    pub fn new_tails_reader<F>(location: &str, fetch: F) -> TailsReader
+    where
+        F: FnMut(&str, u64, usize) -> Result<Vec<u8>> + Send + 'static,
+    {
+        TailsReader::new(Self::new(location, fetch))
+    }
+
+    /// Create a tails reader for the tails file at `location`, checking it against
+    /// `tails_hash` (the `tails_hash` of the revocation registry definition) first.
+    ///
+    /// The whole remote file is fetched once, in fixed size ranges, to compute its hash
+    /// before the reader is returned. Later reads fetch their chunks again and check
+    /// them against the hashes recorded during that pass.
+    pub fn new_verified_tails_reader<F>(
+        location: &str,
+        tails_hash: &str,
+        fetch: F,
+    ) -> Result<TailsReader>
+    where
+        F: FnMut(&str, u64, usize) -> Result<Vec<u8>> + Send + 'static,
+    {
+        let expected =
+            base58::decode(tails_hash).map_err(err_map!("Invalid tails hash: {}", tails_hash))?;
+        let mut reader = Self::new(location, fetch);
+        let hash = reader.hash()?;
+        if hash != expected {
+            return Err(err_msg!(
+                TailsHashMismatch,
+                "Tails file hash mismatch for {}: expected {}, found {}",
+                location,
+                tails_hash,
+                base58::encode(&hash)
+            ));
+        }
+        Ok(TailsReader::new(reader))
+    }
+
+    fn new<F>(location: &str, fetch: F) -> Self
+    where
+        F: FnMut(&str, u64, usize) -> Result<Vec<u8>> + Send + 'static,
+    {
+        Self {
+            location: location.to_owned(),
+            fetch: Box::new(fetch),
+            hash: None,
+            chunk_hashes: Vec::new(),
+            chunk: None,
+        }
+    }
+
+    fn fetch_chunk(&mut self, index: u64) -> Result<Vec<u8>> {
+        let chunk = (self.fetch)(
+            &self.location,
+            index * REMOTE_HASH_CHUNK_SIZE as u64,
+            REMOTE_HASH_CHUNK_SIZE,
+        )?;
+        if chunk.len() > REMOTE_HASH_CHUNK_SIZE {
+            return Err(err_msg!(
+                IOError,
+                "Tails fetch returned more bytes than requested"
+            ));
+        }
+        Ok(chunk)
+    }
+
+    fn fetch_hash(&mut self) -> Result<Vec<u8>> {
+        let mut hasher = Sha256::default();
+        let mut chunk_hashes = Vec::new();
+        loop {
+            let chunk = self.fetch_chunk(chunk_hashes.len() as u64)?;
+            hasher.update(&chunk);
+            chunk_hashes.push(Sha256::digest(&chunk).to_vec());
+            if chunk.len() < REMOTE_HASH_CHUNK_SIZE {
+                break;
+            }
+        }
+        // the chunk kept from an earlier read has not been checked
+        self.chunk = None;
+        self.chunk_hashes = chunk_hashes;
+        Ok(hasher.finalize().to_vec())
+    }
+
+    // Fetch a chunk of the file. Once the file has been hashed, the chunk is checked
+    // against the hash recorded for it.
+    fn read_chunk(&mut self, index: u64) -> Result<&[u8]> {
+        if !matches!(&self.chunk, Some((cached, _)) if *cached == index) {
+            let chunk = self.fetch_chunk(index)?;
+            if self.hash.is_some() {
+                let expected = self.chunk_hashes.get(index as usize).ok_or_else(|| {
+                    err_msg!(
+                        IOError,
+                        "Tails data requested past the end of {}",
+                        self.location
+                    )
+                })?;
+                if Sha256::digest(&chunk).as_slice() != expected.as_slice() {
+                    return Err(err_msg!(
+                        TailsHashMismatch,
+                        "Tails data for {} does not match the verified tails file",
+                        self.location
+                    ));
+                }
+            }
+            self.chunk = Some((index, chunk));
+        }
+        Ok(self
+            .chunk
+            .as_ref()
+            .map(|(_, chunk)| chunk.as_slice())
+            .unwrap())
+    }
+}
+
+impl std::fmt::Debug for TailsRemoteReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TailsRemoteReader")
+            .field("location", &self.location)
+            .field("hash", &self.hash.as_ref().map(base58::encode))
+            .finish()
+    }
+}
+
+impl TailsReaderImpl for TailsRemoteReader {
+    fn hash(&mut self) -> Result<Vec<u8>> {
+        if self.hash.is_none() {
+            let hash = self.fetch_hash()?;
+            self.hash = Some(hash);
+        }
+        Ok(self.hash.clone().unwrap())
+    }
+
+    fn read(&mut self, size: usize, offset: usize) -> Result<Vec<u8>> {
+        let (offset, end) = (offset as u64, offset as u64 + size as u64);
+        let chunk_size = REMOTE_HASH_CHUNK_SIZE as u64;
+        let mut buf = Vec::with_capacity(size);
+        let mut pos = offset;
+        while pos < end {
+            let index = pos / chunk_size;
+            let start = index * chunk_size;
+            let chunk = self.read_chunk(index)?;
+            let to = (end - start).min(chunk.len() as u64);
+            if to <= pos - start {
+                return Err(err_msg!(
+                    IOError,
+                    "Expected {} bytes of tails data at offset {}, the file has {} bytes",
+                    size,
+                    offset,
+                    start + chunk.len() as u64
+                ));
+            }
+            buf.extend_from_slice(&chunk[(pos - start) as usize..to as usize]);
+            pos = start + to;
+        }
+        Ok(buf)
+    }
+}
+
 pub trait TailsWriter: std::fmt::Debug {
     fn write(&mut self, generator: &mut RevocationTailsGenerator) -> Result<(String, String)>;
 }
@@ -153,7 +512,7 @@ impl TailsWriter for TailsFileWriter {
         let mut tempf = tempfile::NamedTempFile::new_in(self.root_path.clone())?;
         let file = tempf.as_file_mut();
         let mut hasher = Sha256::default();
-        let version = &[0u8, 2u8];
+        let version = &TAILS_BLOB_VERSION;
         file.write_all(version)?;
         hasher.update(version);
         while let Some(tail) = generator.try_next()? {
@@ -178,3 +537,139 @@ impl TailsWriter for TailsFileWriter {
         Ok((path, hash))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_streaming_checks_header() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&TAILS_BLOB_VERSION).unwrap();
+        let path = file.path().to_string_lossy().into_owned();
+        TailsFileReader::open_streaming(&path).unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&[0u8, 1u8]).unwrap();
+        let path = file.path().to_string_lossy().into_owned();
+        assert_kind!(Input, TailsFileReader::open_streaming(&path));
+    }
+
+    fn fetch_from(data: Vec<u8>) -> impl FnMut(&str, u64, usize) -> Result<Vec<u8>> + Send {
+        move |_location, offset, size| {
+            let start = (offset as usize).min(data.len());
+            let end = (start + size).min(data.len());
+            Ok(data[start..end].to_vec())
+        }
+    }
+
+    #[test]
+    fn remote_reader_checks_hash() {
+        let mut data = TAILS_BLOB_VERSION.to_vec();
+        data.extend((0..REMOTE_HASH_CHUNK_SIZE + 10).map(|i| i as u8));
+        let hash = base58::encode(Sha256::digest(&data));
+
+        let reader =
+            TailsRemoteReader::new_verified_tails_reader("remote", &hash, fetch_from(data.clone()))
+                .unwrap();
+        assert_eq!(
+            reader.inner.borrow_mut().hash().unwrap(),
+            Sha256::digest(&data).to_vec()
+        );
+        assert_eq!(
+            reader.inner.borrow_mut().read(4, 2).unwrap(),
+            data[2..6].to_vec()
+        );
+        assert_kind!(IOError, reader.inner.borrow_mut().read(4, data.len() - 2));
+
+        data[10] ^= 1;
+        assert_kind!(
+            TailsHashMismatch,
+            TailsRemoteReader::new_verified_tails_reader("remote", &hash, fetch_from(data))
+        );
+    }
+
+    #[test]
+    fn remote_reader_verifies_ranges() {
+        use std::sync::{Arc, Mutex};
+
+        let mut data = TAILS_BLOB_VERSION.to_vec();
+        data.extend((0..3 * REMOTE_HASH_CHUNK_SIZE).map(|i| (i / 7) as u8));
+        let hash = base58::encode(Sha256::digest(&data));
+        let source = Arc::new(Mutex::new(data.clone()));
+        let fetch = {
+            let source = source.clone();
+            move |_location: &str, offset: u64, size: usize| {
+                let data = source.lock().unwrap();
+                let start = (offset as usize).min(data.len());
+                let end = (start + size).min(data.len());
+                Ok(data[start..end].to_vec())
+            }
+        };
+        let reader = TailsRemoteReader::new_verified_tails_reader("remote", &hash, fetch).unwrap();
+
+        // a range spanning two chunks
+        let offset = REMOTE_HASH_CHUNK_SIZE - 3;
+        assert_eq!(
+            reader.inner.borrow_mut().read(TAIL_SIZE, offset).unwrap(),
+            data[offset..offset + TAIL_SIZE].to_vec()
+        );
+
+        // data changed after the file was verified is rejected
+        source.lock().unwrap()[2 * REMOTE_HASH_CHUNK_SIZE + 5] ^= 1;
+        assert_kind!(
+            TailsHashMismatch,
+            reader
+                .inner
+                .borrow_mut()
+                .read(TAIL_SIZE, 2 * REMOTE_HASH_CHUNK_SIZE)
+        );
+        assert_eq!(
+            reader.inner.borrow_mut().read(4, 2).unwrap(),
+            data[2..6].to_vec()
+        );
+    }
+
+    #[test]
+    fn remote_reader_fetches_on_demand() {
+        use std::sync::{Arc, Mutex};
+
+        let mut data = TAILS_BLOB_VERSION.to_vec();
+        data.extend((0..4 * REMOTE_HASH_CHUNK_SIZE).map(|i| (i / 3) as u8));
+        let fetched = Arc::new(Mutex::new(0usize));
+        let fetch = {
+            let (data, fetched) = (data.clone(), fetched.clone());
+            let mut fetch = fetch_from(data);
+            move |location: &str, offset: u64, size: usize| {
+                let chunk = fetch(location, offset, size)?;
+                *fetched.lock().unwrap() += chunk.len();
+                Ok(chunk)
+            }
+        };
+        let reader = TailsRemoteReader::new_tails_reader("remote", fetch);
+        assert_eq!(*fetched.lock().unwrap(), 0);
+
+        // only the chunk covering the tail is fetched, and only once
+        let offset = 3 * REMOTE_HASH_CHUNK_SIZE + 2;
+        for _ in 0..2 {
+            assert_eq!(
+                reader.inner.borrow_mut().read(TAIL_SIZE, offset).unwrap(),
+                data[offset..offset + TAIL_SIZE].to_vec()
+            );
+        }
+        assert_eq!(*fetched.lock().unwrap(), REMOTE_HASH_CHUNK_SIZE);
+        assert_kind!(IOError, reader.inner.borrow_mut().read(4, data.len() - 2));
+
+        // hashing fetches the whole file
+        *fetched.lock().unwrap() = 0;
+        assert_eq!(reader.hash().unwrap(), Sha256::digest(&data).to_vec());
+        assert_eq!(*fetched.lock().unwrap(), data.len());
+    }
+
+    #[test]
+    fn open_streaming_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").to_string_lossy().into_owned();
+        assert_kind!(IOError, TailsFileReader::open_streaming(&path));
+    }
+}
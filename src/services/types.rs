@@ -1,23 +1,31 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use super::tails::TailsReader;
 pub use crate::data_types::{
     cred_def::{CredentialDefinitionPrivate, CredentialKeyCorrectnessProof, SignatureType},
     cred_offer::CredentialOffer,
     cred_request::{CredentialRequest, CredentialRequestMetadata},
-    credential::{AttributeValues, Credential, CredentialValues},
+    credential::{AttributeValues, Credential, CredentialInfo, CredentialValues},
     master_secret::MasterSecret,
-    pres_request::PresentationRequest,
-    presentation::Presentation,
-    rev_reg::{RevocationRegistry, RevocationRegistryDelta, RevocationStatusList},
+    nonce::{Nonce, NonceRegistry, NonceSeen},
+    pres_request::{PresentationRequest, PresentationRequestBuilder, Query},
+    presentation::{LegacyIdentifierUse, LegacyObjectType, Presentation},
+    rev_reg::{
+        Accumulator, RevocationRegistry, RevocationRegistryDelta, RevocationStatusDelta,
+        RevocationStatusList,
+    },
     rev_reg_def::{
-        RegistryType, RevocationRegistryDefinition, RevocationRegistryDefinitionPrivate,
+        RegistryType, RevocationRegistryDefinition, RevocationRegistryDefinitionId,
+        RevocationRegistryDefinitionPrivate,
     },
-    schema::AttributeNames,
+    schema::{AttributeNames, MAX_ATTRIBUTES_COUNT},
 };
 
-use crate::services::helpers::encode_credential_attribute;
-use crate::ursa::cl::{RevocationRegistry as CryptoRevocationRegistry, Witness};
+use crate::services::helpers::{encode_credential_attribute, encode_string_attribute};
+use crate::ursa::cl::{
+    RevocationRegistry as CryptoRevocationRegistry,
+    RevocationRegistryDelta as CryptoRevocationRegistryDelta, Witness,
+};
 use crate::{
     error::{Error, ValidationError},
     invalid,
@@ -27,16 +35,95 @@ use crate::{
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CredentialDefinitionConfig {
     pub support_revocation: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_attributes: Option<usize>,
 }
 
 impl CredentialDefinitionConfig {
+    /// The default limit on the number of attributes of a credential definition: the
+    /// `MAX_ATTRIBUTES_COUNT` attributes of a schema and the implicit `master_secret`
+    pub const DEFAULT_MAX_ATTRIBUTES: usize = MAX_ATTRIBUTES_COUNT + 1;
+
     pub fn new(support_revocation: bool) -> Self {
-        Self { support_revocation }
+        Self {
+            support_revocation,
+            max_attributes: None,
+        }
+    }
+
+    /// Limit the number of attributes of the credential definition, counting the
+    /// schema attributes and the implicit `master_secret` attribute
+    pub fn with_max_attributes(mut self, max_attributes: usize) -> Self {
+        self.max_attributes = Some(max_attributes);
+        self
+    }
+
+    /// The limit on the number of attributes enforced when creating the credential
+    /// definition, including `master_secret`, `DEFAULT_MAX_ATTRIBUTES` unless configured
+    pub fn max_attributes(&self) -> usize {
+        self.max_attributes.unwrap_or(Self::DEFAULT_MAX_ATTRIBUTES)
     }
 }
 
 impl Validatable for CredentialDefinitionConfig {}
 
+/// Parameters of `verify_presentation`: limits on the size of a presentation,
+/// checked before any of the cryptographic verification is performed, and how the
+/// non-revocation intervals of the request are checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyParams {
+    /// Maximum number of revealed attributes, counting each attribute of a group
+    pub max_revealed_attributes: usize,
+    /// Maximum number of predicates
+    pub max_predicates: usize,
+    /// Maximum number of sub-proofs, one per credential used
+    pub max_sub_proofs: usize,
+    /// Follow the legacy Indy behavior for version 1.0 requests, where only the end
+    /// of the non-revocation intervals is enforced: a registry which was not
+    /// updated during the interval may be presented with an earlier timestamp.
+    /// Requests without a `ver` are version 1.0. Disabled by default, so that both
+    /// bounds are enforced for every request.
+    #[serde(default)]
+    pub legacy_v1_intervals: bool,
+}
+
+impl VerifyParams {
+    pub const DEFAULT_MAX_REVEALED_ATTRIBUTES: usize = 1024;
+    pub const DEFAULT_MAX_PREDICATES: usize = 256;
+    pub const DEFAULT_MAX_SUB_PROOFS: usize = 64;
+
+    pub fn with_max_revealed_attributes(mut self, max_revealed_attributes: usize) -> Self {
+        self.max_revealed_attributes = max_revealed_attributes;
+        self
+    }
+
+    pub fn with_max_predicates(mut self, max_predicates: usize) -> Self {
+        self.max_predicates = max_predicates;
+        self
+    }
+
+    pub fn with_max_sub_proofs(mut self, max_sub_proofs: usize) -> Self {
+        self.max_sub_proofs = max_sub_proofs;
+        self
+    }
+
+    pub fn with_legacy_v1_intervals(mut self, legacy_v1_intervals: bool) -> Self {
+        self.legacy_v1_intervals = legacy_v1_intervals;
+        self
+    }
+}
+
+impl Default for VerifyParams {
+    fn default() -> Self {
+        Self {
+            max_revealed_attributes: Self::DEFAULT_MAX_REVEALED_ATTRIBUTES,
+            max_predicates: Self::DEFAULT_MAX_PREDICATES,
+            max_sub_proofs: Self::DEFAULT_MAX_SUB_PROOFS,
+            legacy_v1_intervals: false,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct MakeCredentialValues(pub(crate) CredentialValues);
 
@@ -68,6 +155,82 @@ impl MakeCredentialValues {
             .insert(name.into(), AttributeValues { raw, encoded });
         Ok(())
     }
+
+    /// Add an integer attribute, encoded as its decimal value so it can be used in
+    /// predicates
+    pub fn add_int(&mut self, name: impl Into<String>, value: i32) {
+        let raw = value.to_string();
+        self.add_encoded(name, raw.clone(), raw);
+    }
+
+    /// Add a string attribute, always encoded as the decimal representation of its
+    /// SHA-256 digest, even when the string looks like an integer.
+    ///
+    /// This is not the standard encoding for strings which parse as a 32-bit integer,
+    /// such as `"12345"`: `encode_credential_attribute` encodes those as the integer.
+    /// Parties checking the raw value of such an attribute must encode it with
+    /// `encode_string_attribute` instead.
+    pub fn add_str(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), Error> {
+        let raw = value.into();
+        let encoded = encode_string_attribute(&raw)?;
+        self.add_encoded(name, raw, encoded);
+        Ok(())
+    }
+
+    /// Add a boolean attribute, with the raw value `true` or `false` encoded as `1`
+    /// or `0`. As for `add_date`, this is not the standard encoding of the raw value.
+    pub fn add_bool(&mut self, name: impl Into<String>, value: bool) {
+        let encoded = if value { "1" } else { "0" };
+        self.add_encoded(name, value.to_string(), encoded.to_string());
+    }
+
+    /// Add a calendar date attribute, with the raw value `YYYY-MM-DD` encoded as the
+    /// integer `YYYYMMDD`, so that dates compare in predicates like the integers do.
+    ///
+    /// The raw value stays human-readable, so `encode_credential_attribute` of the raw
+    /// value (its SHA-256 digest) does not give the encoded value. Parties checking
+    /// the raw value of such an attribute must encode it the same way.
+    pub fn add_date(
+        &mut self,
+        name: impl Into<String>,
+        year: u32,
+        month: u32,
+        day: u32,
+    ) -> Result<(), Error> {
+        if !(1..=9999).contains(&year) || !(1..=12).contains(&month) {
+            return Err(err_msg!(
+                "Invalid date: {:04}-{:02}-{:02}",
+                year,
+                month,
+                day
+            ));
+        }
+        let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days_in_month = match month {
+            2 if leap_year => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        if !(1..=days_in_month).contains(&day) {
+            return Err(err_msg!(
+                "Invalid date: {:04}-{:02}-{:02}",
+                year,
+                month,
+                day
+            ));
+        }
+        self.add_encoded(
+            name,
+            format!("{:04}-{:02}-{:02}", year, month, day),
+            (year * 10000 + month * 100 + day).to_string(),
+        );
+        Ok(())
+    }
 }
 
 impl From<MakeCredentialValues> for CredentialValues {
@@ -76,8 +239,20 @@ impl From<MakeCredentialValues> for CredentialValues {
     }
 }
 
+/// The held credentials which can be used for each referent of a presentation request
+#[derive(Debug, Default)]
+pub struct CredentialMatches<'c> {
+    /// The matching credentials for each requested attribute referent
+    pub attributes: HashMap<String, Vec<&'c CredentialInfo>>,
+    /// The matching credentials for each requested predicate referent
+    pub predicates: HashMap<String, Vec<&'c CredentialInfo>>,
+}
+
 #[derive(Debug, Default)]
-pub struct PresentCredentials<'p>(pub(crate) Vec<PresentCredential<'p>>);
+pub struct PresentCredentials<'p> {
+    pub(crate) creds: Vec<PresentCredential<'p>>,
+    pub(crate) allow_unlinked: bool,
+}
 
 impl<'p> PresentCredentials<'p> {
     #[inline]
@@ -85,22 +260,34 @@ impl<'p> PresentCredentials<'p> {
         Self::default()
     }
 
+    /// Allow presenting credentials bound to different master secrets, for example
+    /// after migrating some credentials from another wallet.
+    ///
+    /// The credentials of a presentation normally prove that they share the same
+    /// master secret. When their master secrets differ this proof cannot be made, and
+    /// creating the presentation fails unless this is allowed, in which case none of
+    /// the credentials are linked to each other.
+    pub fn allow_unlinked_credentials(&mut self) {
+        self.allow_unlinked = true;
+    }
+
     pub fn add_credential(
         &mut self,
         cred: &'p Credential,
         timestamp: Option<u64>,
         rev_state: Option<&'p CredentialRevocationState>,
     ) -> AddCredential<'_, 'p> {
-        let idx = self.0.len();
-        self.0.push(PresentCredential {
+        let idx = self.creds.len();
+        self.creds.push(PresentCredential {
             cred,
             timestamp,
             rev_state,
+            master_secret: None,
             requested_attributes: HashSet::new(),
             requested_predicates: HashSet::new(),
         });
         AddCredential {
-            present: &mut self.0[idx],
+            present: &mut self.creds[idx],
         }
     }
 
@@ -109,7 +296,7 @@ impl<'p> PresentCredentials<'p> {
     }
 
     pub fn len(&self) -> usize {
-        self.0.iter().filter(|c| !c.is_empty()).count()
+        self.creds.iter().filter(|c| !c.is_empty()).count()
     }
 }
 
@@ -118,7 +305,7 @@ impl Validatable for PresentCredentials<'_> {
         let mut attr_names = HashSet::new();
         let mut pred_names = HashSet::new();
 
-        for c in self.0.iter() {
+        for c in self.creds.iter() {
             for (name, _reveal) in c.requested_attributes.iter() {
                 if !attr_names.insert(name.as_str()) {
                     return Err(invalid!("Duplicate requested attribute referent: {}", name));
@@ -147,6 +334,7 @@ pub(crate) struct PresentCredential<'p> {
     pub cred: &'p Credential,
     pub timestamp: Option<u64>,
     pub rev_state: Option<&'p CredentialRevocationState>,
+    pub master_secret: Option<&'p MasterSecret>,
     pub requested_attributes: HashSet<(String, bool)>,
     pub requested_predicates: HashSet<String>,
 }
@@ -173,6 +361,12 @@ impl<'a, 'p> AddCredential<'a, 'p> {
     pub fn add_requested_predicate(&mut self, referent: impl Into<String>) {
         self.present.requested_predicates.insert(referent.into());
     }
+
+    /// Use the master secret the credential was issued under, instead of the
+    /// master secret passed to `create_presentation`
+    pub fn set_master_secret(&mut self, master_secret: &'p MasterSecret) {
+        self.present.master_secret = Some(master_secret);
+    }
 }
 
 #[allow(dead_code)]
@@ -205,6 +399,74 @@ pub struct CredentialRevocationState {
     pub timestamp: u64,
 }
 
+impl CredentialRevocationState {
+    /// Update the witness by applying an ordered list of revocation registry deltas.
+    ///
+    /// The first delta must start from the accumulator of this state, and each
+    /// following delta from the accumulator the previous one ended on. The
+    /// witness is cloned once and updated in place for every delta, avoiding
+    /// the revocation registry conversions and intermediate
+    /// `CredentialRevocationState` values produced by repeated calls to
+    /// `prover::create_or_update_revocation_state`. The state is left
+    /// unchanged if any delta is out of order, a gap is detected or a delta
+    /// contains an index outside of the registry.
+    ///
+    /// The state does not record the revocation index of the credential or the size
+    /// of the registry, which the witness update needs, so they are taken from
+    /// `rev_reg_idx` and `rev_reg_def`. A delta carries no timestamp, so the time of
+    /// the last delta is given as `timestamp`. The tails are read through a
+    /// `TailsReader`, which wraps any `TailsReaderImpl`.
+    pub fn update_from_deltas(
+        &mut self,
+        rev_reg_def: &RevocationRegistryDefinition,
+        rev_reg_idx: u32,
+        deltas: &[RevocationRegistryDelta],
+        timestamp: u64,
+        tails_reader: &TailsReader,
+    ) -> Result<(), Error> {
+        if timestamp < self.timestamp {
+            return Err(err_msg!(
+                "Revocation state timestamp {} is later than the update timestamp {}",
+                self.timestamp,
+                timestamp
+            ));
+        }
+
+        for delta in deltas {
+            delta.validate_indices(rev_reg_def.value.max_cred_num)?;
+        }
+
+        let empty = HashSet::new();
+        let mut rev_reg = self.rev_reg.clone();
+        for (idx, delta) in deltas.iter().enumerate() {
+            CryptoRevocationRegistryDelta::from_parts(None, &rev_reg, &empty, &empty)
+                .merge(&delta.value)
+                .map_err(|_| {
+                    err_msg!(
+                        "Revocation registry delta at position {} does not continue from the previous accumulator",
+                        idx
+                    )
+                })?;
+            rev_reg = CryptoRevocationRegistry::from(delta.value.clone());
+        }
+
+        let mut witness = self.witness.clone();
+        for delta in deltas {
+            witness.update(
+                rev_reg_idx,
+                rev_reg_def.value.max_cred_num,
+                &delta.value,
+                tails_reader,
+            )?;
+        }
+
+        self.witness = witness;
+        self.rev_reg = rev_reg;
+        self.timestamp = timestamp;
+        Ok(())
+    }
+}
+
 impl Validatable for CredentialRevocationState {
     fn validate(&self) -> std::result::Result<(), ValidationError> {
         if self.timestamp == 0 {
@@ -216,22 +478,120 @@ impl Validatable for CredentialRevocationState {
     }
 }
 
+/// A source of the revocation status lists published for a revocation registry,
+/// such as a ledger client or a local cache
+pub trait RevocationStatusListResolver {
+    /// Fetch the status list which was current at `timestamp`: the latest one
+    /// published at or before it
+    fn status_list_at(
+        &self,
+        rev_reg_def_id: &RevocationRegistryDefinitionId,
+        timestamp: u64,
+    ) -> Result<RevocationStatusList, Error>;
+}
+
 pub struct CredentialRevocationConfig<'a> {
     pub reg_def: &'a RevocationRegistryDefinition,
     pub reg_def_private: &'a RevocationRegistryDefinitionPrivate,
     pub registry_idx: u32,
     pub tails_reader: TailsReader,
+    /// Check the tails data against the `tails_hash` of the revocation registry
+    /// definition before issuing the credential
+    pub verify_tails: bool,
 }
 
 impl<'a> std::fmt::Debug for CredentialRevocationConfig<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "CredentialRevocationConfig {{ reg_def: {:?}, private: {:?}, idx: {}, reader: {:?} }}",
+            "CredentialRevocationConfig {{ reg_def: {:?}, private: {:?}, idx: {}, reader: {:?}, verify_tails: {} }}",
             self.reg_def,
             secret!(self.reg_def_private),
             secret!(self.registry_idx),
             self.tails_reader,
+            self.verify_tails,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(values: &MakeCredentialValues, name: &str) -> (String, String) {
+        let value = &values.0 .0[name];
+        (value.raw.clone(), value.encoded.clone())
+    }
+
+    #[test]
+    fn typed_credential_values_encoding() {
+        let mut values = MakeCredentialValues::default();
+        values.add_int("int", 28);
+        values.add_int("negative", -5);
+        values.add_bool("yes", true);
+        values.add_bool("no", false);
+        values.add_str("text", "Alex").unwrap();
+        values.add_str("zip", "12345").unwrap();
+        values.add_date("date", 2008, 2, 29).unwrap();
+        values.add_date("early", 1, 1, 1).unwrap();
+
+        assert_eq!(encoded(&values, "int"), ("28".into(), "28".into()));
+        assert_eq!(encoded(&values, "negative"), ("-5".into(), "-5".into()));
+        assert_eq!(encoded(&values, "yes"), ("true".into(), "1".into()));
+        assert_eq!(encoded(&values, "no"), ("false".into(), "0".into()));
+        assert_eq!(
+            encoded(&values, "text"),
+            (
+                "Alex".into(),
+                "99262857098057710338306967609588410025648622308394250666849665532448612202874"
+                    .into()
+            )
+        );
+        // strings are hashed even when they look like integers
+        assert_eq!(
+            encoded(&values, "zip"),
+            (
+                "12345".into(),
+                "40517827634140982427891826463487354397562163067645485726320510288945209855941"
+                    .into()
+            )
+        );
+        assert_eq!(
+            encoded(&values, "date"),
+            ("2008-02-29".into(), "20080229".into())
+        );
+        assert_eq!(
+            encoded(&values, "early"),
+            ("0001-01-01".into(), "10101".into())
+        );
+
+        // booleans, dates and strings which look like integers do not use the
+        // standard encoding of their raw value
+        for name in ["int", "negative", "text"] {
+            let (raw, encoded) = encoded(&values, name);
+            assert_eq!(encode_credential_attribute(&raw).unwrap(), encoded);
+        }
+        assert_eq!(
+            encode_string_attribute("12345").unwrap(),
+            encoded(&values, "zip").1
+        );
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        let mut values = MakeCredentialValues::default();
+        for (year, month, day) in [
+            (2023, 2, 29),
+            (1900, 2, 29),
+            (2023, 4, 31),
+            (2023, 13, 1),
+            (2023, 0, 1),
+            (2023, 1, 0),
+            (0, 1, 1),
+            (10000, 1, 1),
+        ] {
+            assert!(values.add_date("date", year, month, day).is_err());
+        }
+        values.add_date("date", 2000, 2, 29).unwrap();
+    }
+}
@@ -8,35 +8,69 @@ use super::types::*;
 use crate::data_types::cred_def::CredentialDefinition;
 use crate::data_types::cred_def::CredentialDefinitionId;
 use crate::data_types::issuer_id::IssuerId;
+use crate::data_types::pres_request::{PresentationRequestVersion, Query};
 use crate::data_types::rev_reg_def::RevocationRegistryDefinitionId;
 use crate::data_types::schema::Schema;
 use crate::data_types::schema::SchemaId;
 use crate::data_types::{
-    nonce::Nonce,
-    pres_request::{AttributeInfo, NonRevocedInterval, PredicateInfo, PresentationRequestPayload},
+    nonce::{Nonce, NonceSeen},
+    pres_request::{
+        is_restriction_tag, AttributeInfo, NonRevocedInterval, PredicateInfo,
+        PresentationRequestPayload, RestrictionTag,
+    },
     presentation::{Identifier, RequestedProof, RevealedAttributeInfo},
 };
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::ursa::cl::{
     verifier::Verifier as CryptoVerifier, CredentialPublicKey,
     RevocationRegistry as CryptoRevocationRegistry,
 };
-use crate::utils::query::Query;
 use crate::utils::validation::LEGACY_IDENTIFIER;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Filter {
     schema_id: SchemaId,
-    schema_issuer_id: IssuerId,
-    schema_name: String,
-    schema_version: String,
-    issuer_id: IssuerId,
+    schema_issuer_id: Option<IssuerId>,
+    schema_name: Option<String>,
+    schema_version: Option<String>,
+    issuer_id: Option<IssuerId>,
     cred_def_id: CredentialDefinitionId,
 }
 
+impl Filter {
+    /// The identifiers of a credential which restrictions are checked against. The
+    /// prover may not know the schema or credential definition of every credential,
+    /// in which case the tags derived from them never match.
+    pub(crate) fn new(
+        schema_id: &SchemaId,
+        schema: Option<&Schema>,
+        cred_def_id: &CredentialDefinitionId,
+        cred_def: Option<&CredentialDefinition>,
+    ) -> Self {
+        Self {
+            schema_id: schema_id.to_owned(),
+            schema_issuer_id: schema.map(|schema| schema.issuer_id.to_owned()),
+            schema_name: schema.map(|schema| schema.name.to_owned()),
+            schema_version: schema.map(|schema| schema.version.to_owned()),
+            issuer_id: cred_def.map(|cred_def| cred_def.issuer_id.to_owned()),
+            cred_def_id: cred_def_id.to_owned(),
+        }
+    }
+}
+
 static INTERNAL_TAG_MATCHER: Lazy<Regex> =
     Lazy::new(|| Regex::new("^attr::([^:]+)::(value|marker)$").unwrap());
 
+/// Verify a presentation against its presentation request.
+///
+/// When `nonces` is provided, the nonce of the presentation request is consumed once
+/// the presentation is found valid, and a presentation for an already used nonce is
+/// rejected as a replay.
+///
+/// A presentation exceeding the limits of `params` (or the default `VerifyParams`
+/// when not provided) is rejected with `ErrorKind::PresentationTooLarge` before
+/// it is verified.
+#[allow(clippy::too_many_arguments)]
 pub fn verify_presentation(
     presentation: &Presentation,
     pres_req: &PresentationRequest,
@@ -44,49 +78,320 @@ pub fn verify_presentation(
     cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
     rev_reg_defs: Option<&HashMap<&RevocationRegistryDefinitionId, &RevocationRegistryDefinition>>,
     rev_status_lists: Option<Vec<&RevocationStatusList>>,
+    nonces: Option<&mut dyn NonceSeen>,
+    params: Option<&VerifyParams>,
 ) -> Result<bool> {
     trace!("verify >>> presentation: {:?}, pres_req: {:?}, schemas: {:?}, cred_defs: {:?}, rev_reg_defs: {:?} rev_status_lists: {:?}",
     presentation, pres_req, schemas, cred_defs, rev_reg_defs, rev_status_lists);
 
+    let params = params.copied().unwrap_or_default();
+    check_presentation_size(presentation, &params)?;
+
+    let enforce_from = enforces_interval_from(&pres_req.version(), &params);
     let pres_req = pres_req.value();
-    let received_revealed_attrs: HashMap<String, Identifier> =
-        received_revealed_attrs(presentation)?;
-    let received_unrevealed_attrs: HashMap<String, Identifier> =
-        received_unrevealed_attrs(presentation)?;
-    let received_predicates: HashMap<String, Identifier> = received_predicates(presentation)?;
-    let received_self_attested_attrs: HashSet<String> = received_self_attested_attrs(presentation);
 
-    compare_attr_from_proof_and_request(
+    check_presentation(
+        presentation,
         pres_req,
-        &received_revealed_attrs,
-        &received_unrevealed_attrs,
-        &received_self_attested_attrs,
-        &received_predicates,
+        enforce_from,
+        schemas,
+        cred_defs,
+        &mut |_, err| Err(err),
     )?;
 
-    verify_revealed_attribute_values(pres_req, presentation)?;
+    let valid = verify_proof(
+        presentation,
+        pres_req,
+        schemas,
+        cred_defs,
+        rev_reg_defs,
+        rev_status_lists,
+    )?;
 
-    verify_requested_restrictions(
+    if valid {
+        if let Some(nonces) = nonces {
+            if !nonces.consume(&pres_req.nonce) {
+                return Err(err_msg!(
+                    ProofRejected,
+                    "Presentation request nonce has already been used"
+                ));
+            }
+        }
+    }
+
+    trace!("verify <<< valid: {:?}", valid);
+
+    Ok(valid)
+}
+
+/// The checks performed when verifying a presentation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationCheck {
+    /// The presentation is within the limits of the `VerifyParams`
+    Size,
+    /// The requested predicates are valid
+    RequestedPredicates,
+    /// The credential definitions are consistent with their schemas
+    SchemasAndCredDefs,
+    /// The presentation answers every referent of the request, and only those
+    Referents,
+    /// Self-attested attributes are only used for unrestricted attributes
+    SelfAttested,
+    /// Attribute groups are answered by a single credential
+    AttributeGroups,
+    /// The revealed raw values match their encoded values
+    RevealedValues,
+    /// The credentials satisfy the restrictions of the request
+    Restrictions,
+    /// The non-revocation timestamps fall within the requested intervals
+    NonRevocation,
+    /// The cryptographic proof, including the nonce of the request
+    Proof,
+}
+
+/// A failed presentation verification check
+#[derive(Debug)]
+pub struct VerificationFailure {
+    pub check: VerificationCheck,
+    pub error: Error,
+}
+
+/// Verify a presentation like `verify_presentation`, reporting every failed check
+/// instead of only the first one.
+///
+/// The first failure of each non-cryptographic check is collected. The proof itself
+/// is only verified when all of those checks pass, so a presentation with an invalid
+/// proof and other problems only reports the other problems. A presentation exceeding
+/// the size limits is rejected without running any other check.
+///
+/// When `nonces` is provided, the nonce of the presentation request is consumed once
+/// the presentation is found valid, and a replayed presentation fails the `Proof` check.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_presentation_detailed(
+    presentation: &Presentation,
+    pres_req: &PresentationRequest,
+    schemas: &HashMap<&SchemaId, &Schema>,
+    cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
+    rev_reg_defs: Option<&HashMap<&RevocationRegistryDefinitionId, &RevocationRegistryDefinition>>,
+    rev_status_lists: Option<Vec<&RevocationStatusList>>,
+    nonces: Option<&mut dyn NonceSeen>,
+    params: Option<&VerifyParams>,
+) -> std::result::Result<(), Vec<VerificationFailure>> {
+    let params = params.copied().unwrap_or_default();
+    if let Err(error) = check_presentation_size(presentation, &params) {
+        return Err(vec![VerificationFailure {
+            check: VerificationCheck::Size,
+            error,
+        }]);
+    }
+
+    let enforce_from = enforces_interval_from(&pres_req.version(), &params);
+    let pres_req = pres_req.value();
+
+    let mut failures = Vec::new();
+    // failures are collected, so the checks are never interrupted
+    let _ = check_presentation(
+        presentation,
         pres_req,
+        enforce_from,
         schemas,
         cred_defs,
-        &presentation.requested_proof,
-        &received_revealed_attrs,
-        &received_unrevealed_attrs,
-        &received_predicates,
-        &received_self_attested_attrs,
+        &mut |check, error| {
+            failures.push(VerificationFailure { check, error });
+            Ok(())
+        },
+    );
+    if !failures.is_empty() {
+        return Err(failures);
+    }
+
+    let error = match verify_proof(
+        presentation,
+        pres_req,
+        schemas,
+        cred_defs,
+        rev_reg_defs,
+        rev_status_lists,
+    ) {
+        Ok(true) => match nonces {
+            Some(nonces) if !nonces.consume(&pres_req.nonce) => err_msg!(
+                ProofRejected,
+                "Presentation request nonce has already been used"
+            ),
+            _ => return Ok(()),
+        },
+        Ok(false) => err_msg!(ProofRejected, "Presentation proof is not valid"),
+        Err(error) => error,
+    };
+    Err(vec![VerificationFailure {
+        check: VerificationCheck::Proof,
+        error,
+    }])
+}
+
+// Reject a presentation exceeding the size limits. The sub-proofs are counted from
+// the proof itself as well as the identifiers, as the CL verification works through
+// every one of them. The predicates are counted from the requested proof, as ursa
+// does not expose the predicate proofs of a sub-proof.
+fn check_presentation_size(presentation: &Presentation, params: &VerifyParams) -> Result<()> {
+    let requested_proof = &presentation.requested_proof;
+    let revealed = requested_proof.revealed_attrs.len()
+        + requested_proof
+            .revealed_attr_groups
+            .values()
+            .map(|group| group.values.len())
+            .sum::<usize>();
+    if revealed > params.max_revealed_attributes {
+        return Err(err_msg!(
+            PresentationTooLarge,
+            "Presentation has {} revealed attributes, the maximum is {}",
+            revealed,
+            params.max_revealed_attributes
+        ));
+    }
+
+    let sub_proof_count = presentation
+        .proof
+        .proofs
+        .len()
+        .max(presentation.identifiers.len());
+    if sub_proof_count > params.max_sub_proofs {
+        return Err(err_msg!(
+            PresentationTooLarge,
+            "Presentation has {} sub-proofs, the maximum is {}",
+            sub_proof_count,
+            params.max_sub_proofs
+        ));
+    }
+
+    let predicate_count = requested_proof.predicates.len();
+    if predicate_count > params.max_predicates {
+        return Err(err_msg!(
+            PresentationTooLarge,
+            "Presentation has {} predicates, the maximum is {}",
+            predicate_count,
+            params.max_predicates
+        ));
+    }
+
+    Ok(())
+}
+
+// Whether the start of the non-revocation intervals is enforced. Only version 1.0
+// requests verified with `VerifyParams::legacy_v1_intervals` leave it out.
+pub(crate) fn enforces_interval_from(
+    version: &PresentationRequestVersion,
+    params: &VerifyParams,
+) -> bool {
+    !(params.legacy_v1_intervals && *version == PresentationRequestVersion::V1)
+}
+
+// Run the non-cryptographic checks of a presentation, passing each failure to
+// `on_failure`, which decides whether to stop
+fn check_presentation(
+    presentation: &Presentation,
+    pres_req: &PresentationRequestPayload,
+    enforce_from: bool,
+    schemas: &HashMap<&SchemaId, &Schema>,
+    cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
+    on_failure: &mut dyn FnMut(VerificationCheck, Error) -> Result<()>,
+) -> Result<()> {
+    let mut report = |check: VerificationCheck, res: Result<()>| match res {
+        Ok(()) => Ok(()),
+        Err(err) => on_failure(check, err),
+    };
+
+    for predicate in pres_req.requested_predicates.values() {
+        report(
+            VerificationCheck::RequestedPredicates,
+            predicate.validate_range().map_err(Error::from),
+        )?;
+    }
+
+    report(
+        VerificationCheck::SchemasAndCredDefs,
+        verify_schemas_and_cred_defs(schemas, cred_defs),
     )?;
 
-    // makes sure the for revocable request or attribute,
-    // there is a timestamp in the `Identifier`
-    compare_timestamps_from_proof_and_request(
+    let received = received_revealed_attrs(presentation).and_then(|revealed| {
+        Ok((
+            revealed,
+            received_unrevealed_attrs(presentation)?,
+            received_predicates(presentation)?,
+        ))
+    });
+    let (received_revealed_attrs, received_unrevealed_attrs, received_predicates) = match received {
+        Ok(received) => received,
+        Err(err) => return report(VerificationCheck::Referents, Err(err)),
+    };
+    let received_self_attested_attrs: HashSet<String> = received_self_attested_attrs(presentation);
+
+    // the other checks assume that the presentation answers the requested referents
+    let referents = compare_attr_from_proof_and_request(
         pres_req,
         &received_revealed_attrs,
         &received_unrevealed_attrs,
         &received_self_attested_attrs,
         &received_predicates,
+    );
+    if referents.is_err() {
+        return report(VerificationCheck::Referents, referents);
+    }
+
+    report(
+        VerificationCheck::SelfAttested,
+        verify_self_attested_restrictions(pres_req, &received_self_attested_attrs),
     )?;
 
+    report(
+        VerificationCheck::AttributeGroups,
+        verify_attribute_groups(pres_req, &presentation.requested_proof),
+    )?;
+
+    report(
+        VerificationCheck::RevealedValues,
+        verify_revealed_attribute_values(pres_req, presentation),
+    )?;
+
+    report(
+        VerificationCheck::Restrictions,
+        verify_requested_restrictions(
+            pres_req,
+            schemas,
+            cred_defs,
+            &presentation.requested_proof,
+            &received_revealed_attrs,
+            &received_unrevealed_attrs,
+            &received_predicates,
+            &received_self_attested_attrs,
+        ),
+    )?;
+
+    // makes sure the for revocable request or attribute,
+    // there is a timestamp in the `Identifier`
+    report(
+        VerificationCheck::NonRevocation,
+        compare_timestamps_from_proof_and_request(
+            pres_req,
+            enforce_from,
+            &received_revealed_attrs,
+            &received_unrevealed_attrs,
+            &received_self_attested_attrs,
+            &received_predicates,
+        ),
+    )?;
+
+    Ok(())
+}
+
+fn verify_proof(
+    presentation: &Presentation,
+    pres_req: &PresentationRequestPayload,
+    schemas: &HashMap<&SchemaId, &Schema>,
+    cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
+    rev_reg_defs: Option<&HashMap<&RevocationRegistryDefinitionId, &RevocationRegistryDefinition>>,
+    rev_status_lists: Option<Vec<&RevocationStatusList>>,
+) -> Result<bool> {
     let mut proof_verifier = CryptoVerifier::new_proof_verifier()?;
     let non_credential_schema = build_non_credential_schema()?;
 
@@ -222,15 +527,13 @@ pub fn verify_presentation(
         )?;
     }
 
-    let valid = proof_verifier.verify(&presentation.proof, pres_req.nonce.as_native())?;
-
-    trace!("verify <<< valid: {:?}", valid);
-
-    Ok(valid)
+    proof_verifier
+        .verify(&presentation.proof, pres_req.nonce.as_native())
+        .map_err(Error::from)
 }
 
 pub fn generate_nonce() -> Result<Nonce> {
-    new_nonce()
+    new_nonce(None)
 }
 
 fn get_revealed_attributes_for_credential(
@@ -299,6 +602,50 @@ fn get_predicates_for_credential(
     Ok(predicates_for_credential)
 }
 
+// Check that every credential definition is supplied with its schema, and that
+// both sign the same attributes, before the CL verification would fail on them
+fn verify_schemas_and_cred_defs(
+    schemas: &HashMap<&SchemaId, &Schema>,
+    cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
+) -> Result<()> {
+    for (cred_def_id, cred_def) in cred_defs {
+        let schema = schemas.get(&cred_def.schema_id).ok_or_else(|| {
+            err_msg!(
+                "Schema {} of credential definition {} not provided",
+                cred_def.schema_id,
+                cred_def_id
+            )
+        })?;
+
+        let cred_def_attrs = cred_def.attribute_names()?;
+        let schema_attrs: HashSet<String> = schema
+            .attr_names
+            .0
+            .iter()
+            .map(|attr| attr_common_view(attr))
+            .collect();
+        if cred_def_attrs.len() != schema_attrs.len() {
+            return Err(err_msg!(
+                "Credential definition {} has {} attributes, but its schema {} has {}",
+                cred_def_id,
+                cred_def_attrs.len(),
+                cred_def.schema_id,
+                schema_attrs.len()
+            ));
+        }
+        if let Some(attr) = schema_attrs.difference(&cred_def_attrs).next() {
+            return Err(err_msg!(
+                "Attribute {} of schema {} is not signed by credential definition {}",
+                attr,
+                cred_def.schema_id,
+                cred_def_id
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn compare_attr_from_proof_and_request(
     pres_req: &PresentationRequestPayload,
     received_revealed_attrs: &HashMap<String, Identifier>,
@@ -308,14 +655,28 @@ fn compare_attr_from_proof_and_request(
 ) -> Result<()> {
     let requested_attrs: HashSet<String> = pres_req.requested_attributes.keys().cloned().collect();
 
-    let received_attrs: HashSet<String> = received_revealed_attrs
-        .iter()
-        .chain(received_unrevealed_attrs)
-        .map(|(r, _)| r.to_string())
-        .collect::<HashSet<String>>()
-        .union(received_self_attested_attrs)
-        .cloned()
-        .collect();
+    let mut received_attrs: HashSet<String> = HashSet::new();
+    for referent in received_revealed_attrs
+        .keys()
+        .chain(received_unrevealed_attrs.keys())
+        .chain(received_self_attested_attrs)
+    {
+        if !received_attrs.insert(referent.to_string()) {
+            return Err(err_msg!(
+                "Attribute referent \"{}\" is answered more than once",
+                referent
+            ));
+        }
+    }
+
+    let mut unrequested_attrs: Vec<&String> = received_attrs.difference(&requested_attrs).collect();
+    if !unrequested_attrs.is_empty() {
+        unrequested_attrs.sort();
+        return Err(err_msg!(
+            "Presentation includes attributes which were not requested: {:?}",
+            unrequested_attrs
+        ));
+    }
 
     if requested_attrs != received_attrs {
         return Err(err_msg!(
@@ -329,6 +690,17 @@ fn compare_attr_from_proof_and_request(
 
     let received_predicates_: HashSet<&String> = received_predicates.keys().collect();
 
+    let mut unrequested_predicates: Vec<&&String> = received_predicates_
+        .difference(&requested_predicates)
+        .collect();
+    if !unrequested_predicates.is_empty() {
+        unrequested_predicates.sort();
+        return Err(err_msg!(
+            "Presentation includes predicates which were not requested: {:?}",
+            unrequested_predicates
+        ));
+    }
+
     if requested_predicates != received_predicates_ {
         return Err(err_msg!(
             "Requested predicates {:?} do not correspond to received {:?}",
@@ -340,39 +712,94 @@ fn compare_attr_from_proof_and_request(
     Ok(())
 }
 
-// This does not actually compare the non_revoke interval
-// see `validate_timestamp` function comments
+// A self-attested value carries no issuer binding, so it may only answer
+// requested attributes which declare no restrictions
+fn verify_self_attested_restrictions(
+    pres_req: &PresentationRequestPayload,
+    received_self_attested_attrs: &HashSet<String>,
+) -> Result<()> {
+    for referent in received_self_attested_attrs {
+        if let Some(info) = pres_req.requested_attributes.get(referent) {
+            if has_restrictions(info.restrictions.as_ref()) {
+                return Err(err_msg!(
+                    ProofRejected,
+                    "Self-attested value provided for restricted attribute referent: {}",
+                    referent
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Requested attribute groups must be answered as a group, which is revealed from a
+// single sub-proof, and single attributes must not be answered as a group
+fn verify_attribute_groups(
+    pres_req: &PresentationRequestPayload,
+    requested_proof: &RequestedProof,
+) -> Result<()> {
+    for (referent, info) in pres_req.requested_attributes.iter() {
+        if info.names.is_some() {
+            if requested_proof.revealed_attrs.contains_key(referent)
+                || requested_proof.unrevealed_attrs.contains_key(referent)
+                || requested_proof.self_attested_attrs.contains_key(referent)
+            {
+                return Err(err_msg!(
+                    ProofRejected,
+                    "Attribute group with referent \"{}\" is not presented as a group",
+                    referent
+                ));
+            }
+        } else if requested_proof.revealed_attr_groups.contains_key(referent) {
+            return Err(err_msg!(
+                ProofRejected,
+                "Attribute with referent \"{}\" is presented as a group",
+                referent
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// When `enforce_from` is false, only the end of the intervals is checked
 fn compare_timestamps_from_proof_and_request(
     pres_req: &PresentationRequestPayload,
+    enforce_from: bool,
     received_revealed_attrs: &HashMap<String, Identifier>,
     received_unrevealed_attrs: &HashMap<String, Identifier>,
     received_self_attested_attrs: &HashSet<String>,
     received_predicates: &HashMap<String, Identifier>,
 ) -> Result<()> {
+    let interval = |interval: &Option<NonRevocedInterval>| match interval {
+        Some(interval) if !enforce_from => Some(NonRevocedInterval {
+            from: None,
+            to: interval.to,
+        }),
+        _ => interval.clone(),
+    };
+    let global_interval = interval(&pres_req.non_revoked);
+
     pres_req
         .requested_attributes
         .iter()
         .map(|(referent, info)| {
+            let received = if received_revealed_attrs.contains_key(referent) {
+                received_revealed_attrs
+            } else if received_unrevealed_attrs.contains_key(referent) {
+                received_unrevealed_attrs
+            } else if received_self_attested_attrs.contains(referent) {
+                return Ok(());
+            } else {
+                return Err(err_msg!("Missing referent: {}", referent));
+            };
             validate_timestamp(
-                received_revealed_attrs,
+                received,
                 referent,
-                &pres_req.non_revoked,
-                &info.non_revoked,
+                &global_interval,
+                &interval(&info.non_revoked),
             )
-            .or_else(|_| {
-                validate_timestamp(
-                    received_unrevealed_attrs,
-                    referent,
-                    &pres_req.non_revoked,
-                    &info.non_revoked,
-                )
-            })
-            .or_else(|_| {
-                received_self_attested_attrs
-                    .get(referent)
-                    .map(|_| ())
-                    .ok_or_else(|| err_msg!("Missing referent: {}", referent))
-            })
         })
         .collect::<Result<Vec<()>>>()?;
 
@@ -383,8 +810,8 @@ fn compare_timestamps_from_proof_and_request(
             validate_timestamp(
                 received_predicates,
                 referent,
-                &pres_req.non_revoked,
-                &info.non_revoked,
+                &global_interval,
+                &interval(&info.non_revoked),
             )
         })
         .collect::<Result<Vec<()>>>()?;
@@ -402,25 +829,34 @@ fn compare_timestamps_from_proof_and_request(
 //
 // Otherwise the Identifier for the referent (attribute) has to have a timestamp,
 // which was added by the prover when creating `PresentCredentials`,
-// an arg for `create_presentation`.
-//
-// TODO: this timestamp should be compared with the provided interval
+// an arg for `create_presentation`, and the timestamp must fall within the
+// (local or global) interval.
 fn validate_timestamp(
     received_: &HashMap<String, Identifier>,
     referent: &str,
     global_interval: &Option<NonRevocedInterval>,
     local_interval: &Option<NonRevocedInterval>,
 ) -> Result<()> {
-    if get_non_revoc_interval(global_interval, local_interval).is_none() {
-        return Ok(());
-    }
+    let interval = match get_non_revoc_interval(global_interval, local_interval) {
+        Some(interval) => interval,
+        None => return Ok(()),
+    };
 
-    if !received_
+    let timestamp = received_
         .get(referent)
-        .map(|attr| attr.timestamp.is_some())
-        .unwrap_or(false)
+        .and_then(|attr| attr.timestamp)
+        .ok_or_else(|| err_msg!("Missing timestamp"))?;
+
+    if interval.from.map_or(false, |from| timestamp < from)
+        || interval.to.map_or(false, |to| timestamp > to)
     {
-        return Err(err_msg!("Missing timestamp"));
+        return Err(err_msg!(
+            ProofRejected,
+            "Timestamp {} for referent {} is outside of the requested non-revocation interval {:?}",
+            timestamp,
+            referent,
+            interval
+        ));
     }
 
     Ok(())
@@ -435,10 +871,18 @@ fn received_revealed_attrs(proof: &Presentation) -> Result<HashMap<String, Ident
         );
     }
     for (referent, infos) in proof.requested_proof.revealed_attr_groups.iter() {
-        revealed_identifiers.insert(
-            referent.to_string(),
-            get_proof_identifier(proof, infos.sub_proof_index)?,
-        );
+        if revealed_identifiers
+            .insert(
+                referent.to_string(),
+                get_proof_identifier(proof, infos.sub_proof_index)?,
+            )
+            .is_some()
+        {
+            return Err(err_msg!(
+                "Attribute referent \"{}\" is answered more than once",
+                referent
+            ));
+        }
     }
     Ok(revealed_identifiers)
 }
@@ -640,6 +1084,15 @@ fn verify_requested_restrictions(
         })
         .collect();
 
+    // Unknown restriction keys would never match, so that the restriction would be
+    // silently rejected (or accepted under `$not`)
+    if let Some(tag) = filter_tags.iter().find(|tag| !is_restriction_tag(tag)) {
+        return Err(err_msg!(
+            "Presentation request contains an unknown restriction: {:?}",
+            tag
+        ));
+    }
+
     // We check whether both the `issuer_id` and `issuer_did` are included. Since `issuer_did` will
     // only be used for legacy support and `issuer_id` will be the new restriction tag, we do not
     // allow mixing them.
@@ -755,12 +1208,14 @@ fn is_self_attested(
     info: &AttributeInfo,
     self_attested_attrs: &HashSet<String>,
 ) -> bool {
-    match info.restrictions.as_ref() {
-        Some(&Query::And(ref array)) | Some(&Query::Or(ref array)) if array.is_empty() => {
-            self_attested_attrs.contains(referent)
-        }
-        None => self_attested_attrs.contains(referent),
-        Some(_) => false,
+    !has_restrictions(info.restrictions.as_ref()) && self_attested_attrs.contains(referent)
+}
+
+fn has_restrictions(restrictions: Option<&Query>) -> bool {
+    match restrictions {
+        Some(Query::And(array)) | Some(Query::Or(array)) => !array.is_empty(),
+        Some(_) => true,
+        None => false,
     }
 }
 
@@ -789,31 +1244,38 @@ fn gather_filter_info(
         .get(cred_def_id)
         .ok_or_else(|| err_msg!("cred_def_id {cred_def_id} could not be found in the cred_defs"))?;
 
-    Ok(Filter {
-        schema_id: schema_id.to_owned(),
-        schema_name: schema.name.to_owned(),
-        schema_version: schema.version.to_owned(),
-        schema_issuer_id: schema.issuer_id.to_owned(),
-        issuer_id: cred_def.issuer_id.to_owned(),
-        cred_def_id: cred_def_id.to_owned(),
-    })
+    Ok(Filter::new(
+        schema_id,
+        Some(schema),
+        cred_def_id,
+        Some(cred_def),
+    ))
 }
 
 fn process_operator(
     attr_value_map: &HashMap<String, Option<&str>>,
     restriction_op: &Query,
     filter: &Filter,
+) -> Result<()> {
+    process_query(restriction_op, &|tag, value| {
+        process_filter(attr_value_map, tag, value, filter)
+    })
+}
+
+/// Check a restriction query, where `check_tag` checks a single tag and value. The
+/// prover shares this with the verifier to match credentials to a presentation request.
+pub(crate) fn process_query(
+    restriction_op: &Query,
+    check_tag: &dyn Fn(&str, &str) -> Result<()>,
 ) -> Result<()> {
     match restriction_op {
-        Query::Eq(ref tag_name, ref tag_value) => {
-            process_filter(attr_value_map, tag_name, tag_value, filter).map_err(err_map!(
-                "$eq operator validation failed for tag: \"{}\", value: \"{}\"",
-                tag_name,
-                tag_value
-            ))
-        }
+        Query::Eq(ref tag_name, ref tag_value) => check_tag(tag_name, tag_value).map_err(err_map!(
+            "$eq operator validation failed for tag: \"{}\", value: \"{}\"",
+            tag_name,
+            tag_value
+        )),
         Query::Neq(ref tag_name, ref tag_value) => {
-            if process_filter(attr_value_map, tag_name, tag_value, filter).is_err() {
+            if check_tag(tag_name, tag_value).is_err() {
                 Ok(())
             } else {
                 Err(err_msg!(ProofRejected,
@@ -823,7 +1285,7 @@ fn process_operator(
         Query::In(ref tag_name, ref tag_values) => {
             let res = tag_values
                 .iter()
-                .any(|val| process_filter(attr_value_map, tag_name, val, filter).is_ok());
+                .any(|val| check_tag(tag_name, val).is_ok());
             if res {
                 Ok(())
             } else {
@@ -837,14 +1299,14 @@ fn process_operator(
         }
         Query::And(ref operators) => operators
             .iter()
-            .map(|op| process_operator(attr_value_map, op, filter))
+            .map(|op| process_query(op, check_tag))
             .collect::<Result<Vec<()>>>()
             .map(|_| ())
             .map_err(err_map!("$and operator validation failed.")),
         Query::Or(ref operators) => {
             let res = operators
                 .iter()
-                .any(|op| process_operator(attr_value_map, op, filter).is_ok());
+                .any(|op| process_query(op, check_tag).is_ok());
             if res {
                 Ok(())
             } else {
@@ -855,7 +1317,7 @@ fn process_operator(
             }
         }
         Query::Not(ref operator) => {
-            if process_operator(attr_value_map, operator, filter).is_err() {
+            if process_query(operator, check_tag).is_err() {
                 Ok(())
             } else {
                 Err(err_msg!(
@@ -881,25 +1343,44 @@ fn process_filter(
         tag_value,
         filter
     );
-    match tag {
-        tag_ @ "schema_id" => precess_filed(tag_, filter.schema_id.to_string(), tag_value),
-        tag_ @ "schema_issuer_did" => {
-            precess_filed(tag_, filter.schema_issuer_id.to_owned(), tag_value)
+    match process_identifier_filter(tag, tag_value, filter) {
+        Some(res) => res,
+        None if is_attr_internal_tag(tag, attr_value_map) => {
+            check_internal_tag_revealed_value(tag, tag_value, attr_value_map)
         }
-        tag_ @ "schema_issuer_id" => {
-            precess_filed(tag_, filter.schema_issuer_id.to_owned(), tag_value)
+        None if is_attr_operator(tag) => Ok(()),
+        None => Err(err_msg!("Unknown Filter Type")),
+    }
+}
+
+/// Check a restriction on one of the identifiers of the credential, or `None` when
+/// `tag` is not an identifier tag
+pub(crate) fn process_identifier_filter(
+    tag: &str,
+    tag_value: &str,
+    filter: &Filter,
+) -> Option<Result<()>> {
+    let filter_value = match RestrictionTag::parse(tag)? {
+        RestrictionTag::SchemaId => Some(filter.schema_id.to_string()),
+        RestrictionTag::SchemaIssuerDid | RestrictionTag::SchemaIssuerId => {
+            filter.schema_issuer_id.as_ref().map(ToString::to_string)
         }
-        tag_ @ "schema_name" => precess_filed(tag_, &filter.schema_name, tag_value),
-        tag_ @ "schema_version" => precess_filed(tag_, &filter.schema_version, tag_value),
-        tag_ @ "cred_def_id" => precess_filed(tag_, &filter.cred_def_id.to_string(), tag_value),
-        tag_ @ "issuer_did" => precess_filed(tag_, filter.issuer_id.to_owned(), tag_value),
-        tag_ @ "issuer_id" => precess_filed(tag_, filter.issuer_id.to_owned(), tag_value),
-        x if is_attr_internal_tag(x, attr_value_map) => {
-            check_internal_tag_revealed_value(x, tag_value, attr_value_map)
+        RestrictionTag::SchemaName => filter.schema_name.clone(),
+        RestrictionTag::SchemaVersion => filter.schema_version.clone(),
+        RestrictionTag::CredDefId => Some(filter.cred_def_id.to_string()),
+        RestrictionTag::IssuerDid | RestrictionTag::IssuerId => {
+            filter.issuer_id.as_ref().map(ToString::to_string)
         }
-        x if is_attr_operator(x) => Ok(()),
-        _ => Err(err_msg!("Unknown Filter Type")),
-    }
+        RestrictionTag::AttributeValue(_) | RestrictionTag::AttributeMarker(_) => return None,
+    };
+    Some(match filter_value {
+        Some(filter_value) => precess_filed(tag, filter_value, tag_value),
+        None => Err(err_msg!(
+            ProofRejected,
+            "\"{}\" value is not known for the credential",
+            tag
+        )),
+    })
 }
 
 fn precess_filed(filed: &str, filter_value: impl Into<String>, tag_value: &str) -> Result<()> {
@@ -972,6 +1453,7 @@ fn is_attr_operator(key: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data_types::presentation::{RevealedAttributeGroupInfo, SubProofReferent};
     use crate::data_types::rev_reg::RevocationRegistryId;
 
     pub const SCHEMA_ID: &str = "123";
@@ -1020,11 +1502,11 @@ mod tests {
     fn filter() -> Filter {
         Filter {
             schema_id: SchemaId::new_unchecked(SCHEMA_ID),
-            schema_name: SCHEMA_NAME.to_string(),
-            schema_issuer_id: IssuerId::new_unchecked(SCHEMA_ISSUER_ID),
-            schema_version: SCHEMA_VERSION.to_string(),
+            schema_name: Some(SCHEMA_NAME.to_string()),
+            schema_issuer_id: Some(IssuerId::new_unchecked(SCHEMA_ISSUER_ID)),
+            schema_version: Some(SCHEMA_VERSION.to_string()),
             cred_def_id: CredentialDefinitionId::new_unchecked(CRED_DEF_ID),
-            issuer_id: IssuerId::new_unchecked(ISSUER_ID),
+            issuer_id: Some(IssuerId::new_unchecked(ISSUER_ID)),
         }
     }
 
@@ -1324,4 +1806,205 @@ mod tests {
         validate_timestamp(&_received(), "referent_2", &None, &Some(_interval())).unwrap_err();
         validate_timestamp(&_received(), "referent_3", &None, &Some(_interval())).unwrap_err();
     }
+
+    #[test]
+    fn validate_timestamp_outside_interval() {
+        let before = NonRevocedInterval {
+            from: None,
+            to: Some(1233),
+        };
+        let after = NonRevocedInterval {
+            from: Some(1235),
+            to: None,
+        };
+        assert_kind!(
+            ProofRejected,
+            validate_timestamp(&_received(), "referent_1", &Some(before.clone()), &None)
+        );
+        assert_kind!(
+            ProofRejected,
+            validate_timestamp(&_received(), "referent_1", &None, &Some(after))
+        );
+        // the local interval takes precedence over the global one
+        validate_timestamp(
+            &_received(),
+            "referent_1",
+            &Some(before),
+            &Some(_interval()),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn legacy_intervals_only_apply_to_v1_requests() {
+        let legacy = VerifyParams::default().with_legacy_v1_intervals(true);
+        // requests without `ver` are parsed as version 1.0
+        assert!(enforces_interval_from(
+            &PresentationRequestVersion::V1,
+            &VerifyParams::default()
+        ));
+        assert!(!enforces_interval_from(
+            &PresentationRequestVersion::V1,
+            &legacy
+        ));
+        assert!(enforces_interval_from(
+            &PresentationRequestVersion::V2,
+            &legacy
+        ));
+    }
+
+    fn _self_attested_pres_req(restrictions: Option<Query>) -> PresentationRequestPayload {
+        PresentationRequestPayload {
+            nonce: Nonce::new().unwrap(),
+            name: "Job-Application".to_string(),
+            version: "0.1".to_string(),
+            requested_attributes: vec![(
+                "attr1_referent".to_string(),
+                AttributeInfo {
+                    name: Some("phone".to_string()),
+                    names: None,
+                    restrictions,
+                    non_revoked: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            requested_predicates: HashMap::new(),
+            non_revoked: None,
+        }
+    }
+
+    fn _self_attested() -> HashSet<String> {
+        vec!["attr1_referent".to_string()].into_iter().collect()
+    }
+
+    #[test]
+    fn verify_self_attested_restrictions_works_without_restrictions() {
+        verify_self_attested_restrictions(&_self_attested_pres_req(None), &_self_attested())
+            .unwrap();
+        verify_self_attested_restrictions(
+            &_self_attested_pres_req(Some(Query::And(vec![]))),
+            &_self_attested(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn verify_self_attested_restrictions_rejects_restricted_referent() {
+        let restrictions = Query::Eq(cred_def_id_tag(), CRED_DEF_ID.to_string());
+        assert_kind!(
+            ProofRejected,
+            verify_self_attested_restrictions(
+                &_self_attested_pres_req(Some(restrictions)),
+                &_self_attested()
+            )
+        );
+    }
+
+    #[test]
+    fn verify_attribute_groups_works() {
+        let mut pres_req = _self_attested_pres_req(None);
+        pres_req.requested_attributes.insert(
+            "attr2_referent".to_string(),
+            AttributeInfo {
+                name: None,
+                names: Some(vec!["name".to_string(), "height".to_string()]),
+                restrictions: None,
+                non_revoked: None,
+            },
+        );
+
+        let mut requested_proof = RequestedProof::default();
+        requested_proof.revealed_attr_groups.insert(
+            "attr2_referent".to_string(),
+            RevealedAttributeGroupInfo {
+                sub_proof_index: 0,
+                values: HashMap::new(),
+            },
+        );
+        verify_attribute_groups(&pres_req, &requested_proof).unwrap();
+
+        // a group answered by a single revealed attribute
+        let mut split_proof = RequestedProof::default();
+        split_proof.revealed_attrs.insert(
+            "attr2_referent".to_string(),
+            RevealedAttributeInfo {
+                sub_proof_index: 0,
+                raw: "Alex".to_string(),
+                encoded: "123".to_string(),
+            },
+        );
+        assert_kind!(
+            ProofRejected,
+            verify_attribute_groups(&pres_req, &split_proof)
+        );
+
+        // a group answered by a single unrevealed attribute
+        let mut unrevealed_proof = RequestedProof::default();
+        unrevealed_proof.unrevealed_attrs.insert(
+            "attr2_referent".to_string(),
+            SubProofReferent { sub_proof_index: 0 },
+        );
+        assert_kind!(
+            ProofRejected,
+            verify_attribute_groups(&pres_req, &unrevealed_proof)
+        );
+
+        // a single attribute answered by a group
+        requested_proof.revealed_attr_groups.insert(
+            "attr1_referent".to_string(),
+            RevealedAttributeGroupInfo {
+                sub_proof_index: 0,
+                values: HashMap::new(),
+            },
+        );
+        assert_kind!(
+            ProofRejected,
+            verify_attribute_groups(&pres_req, &requested_proof)
+        );
+    }
+
+    #[test]
+    fn test_verify_schemas_and_cred_defs() {
+        use crate::data_types::schema::AttributeNames;
+        use crate::issuer::{create_credential_definition, create_schema};
+        use crate::types::{CredentialDefinitionConfig, SignatureType};
+
+        let schema = create_schema(
+            "gvt",
+            "1.0",
+            "mock:issuer_id",
+            AttributeNames::from(&["name", "Age"][..]),
+        )
+        .unwrap();
+        let (cred_def, _, _) = create_credential_definition(
+            "mock:schema_id",
+            &schema,
+            "mock:issuer_id",
+            "tag",
+            SignatureType::CL,
+            CredentialDefinitionConfig::new(false),
+        )
+        .unwrap();
+        let schema_id = SchemaId::new_unchecked("mock:schema_id");
+        let cred_def_id = CredentialDefinitionId::new_unchecked("mock:cred_def_id");
+        let cred_defs = HashMap::from([(&cred_def_id, &cred_def)]);
+
+        let schemas = HashMap::from([(&schema_id, &schema)]);
+        verify_schemas_and_cred_defs(&schemas, &cred_defs).unwrap();
+
+        // the schema of the credential definition is missing
+        let other_schema_id = SchemaId::new_unchecked("mock:other_schema_id");
+        let schemas = HashMap::from([(&other_schema_id, &schema)]);
+        assert_kind!(Input, verify_schemas_and_cred_defs(&schemas, &cred_defs));
+
+        // the schema does not match the credential definition
+        let mut other_schema = schema.clone();
+        other_schema.attr_names = AttributeNames::from(&["name", "sex"][..]);
+        let schemas = HashMap::from([(&schema_id, &other_schema)]);
+        assert_kind!(Input, verify_schemas_and_cred_defs(&schemas, &cred_defs));
+        other_schema.attr_names = AttributeNames::from(&["name"][..]);
+        let schemas = HashMap::from([(&schema_id, &other_schema)]);
+        assert_kind!(Input, verify_schemas_and_cred_defs(&schemas, &cred_defs));
+    }
 }
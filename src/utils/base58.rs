@@ -3,3 +3,7 @@ use bs58;
 pub fn encode<T: AsRef<[u8]>>(val: T) -> String {
     bs58::encode(val).into_string()
 }
+
+pub fn decode<T: AsRef<[u8]>>(val: T) -> Result<Vec<u8>, bs58::decode::Error> {
+    bs58::decode(val).into_vec()
+}
@@ -0,0 +1,74 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Serialize a value to canonical JSON: object keys are sorted by their UTF-8
+/// bytes and no whitespace is emitted, so equal values always produce the same
+/// bytes. The output is regular JSON and deserializes as usual.
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let value = serde_json::to_value(value)
+        .map_err(err_map!(Unexpected, "Error serializing to canonical JSON"))?;
+    let mut out = Vec::new();
+    write_canonical(&value, &mut out)?;
+    Ok(out)
+}
+
+fn write_canonical(value: &Value, out: &mut Vec<u8>) -> Result<(), Error> {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+            out.push(b'{');
+            for (idx, (key, value)) in entries.into_iter().enumerate() {
+                if idx > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.push(b':');
+                write_canonical(value, out)?;
+            }
+            out.push(b'}');
+        }
+        Value::Array(items) => {
+            out.push(b'[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(b']');
+        }
+        _ => serde_json::to_writer(&mut *out, value)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_json_sorts_keys() {
+        let value = json!({
+            "b": [3, {"z": null, "a": "x y"}],
+            "a": {"d": true, "c": 1.5},
+            "é": "unicode",
+            "B": "upper"
+        });
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            r#"{"B":"upper","a":{"c":1.5,"d":true},"b":[3,{"a":"x y","z":null}],"é":"unicode"}"#
+                .as_bytes()
+        );
+    }
+
+    #[test]
+    fn canonical_json_round_trip() {
+        let value = json!({"name": "gvt", "nested": {"list": [1, 2], "empty": {}}});
+        let bytes = to_canonical_json(&value).unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&bytes).unwrap(), value);
+        assert_eq!(to_canonical_json(&value).unwrap(), bytes);
+    }
+}
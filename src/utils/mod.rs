@@ -3,9 +3,17 @@ pub mod validation;
 
 pub mod base58;
 
+#[cfg(feature = "cbor")]
+pub mod cbor;
+
 pub mod hash;
 
+pub mod json;
+
 pub mod query;
 
 #[macro_use]
 pub mod macros;
+
+#[cfg(any(feature = "cbor", feature = "ffi"))]
+pub(crate) mod zeroizing;
@@ -2,9 +2,9 @@ use crate::error::ValidationError;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-// TODO: stricten the URI regex.
-// Right now everything after the first colon is allowed,
-// we might want to restrict this
+/// A permissive pattern for URI identifiers, kept for compatibility. It accepts
+/// anything following a colon after the scheme.
+#[deprecated(note = "Use `is_uri_identifier` or `parse_uri_identifier`")]
 pub static URI_IDENTIFIER: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^[a-zA-Z0-9\+\-\.]+:.+$").unwrap());
 
@@ -16,14 +16,198 @@ pub static URI_IDENTIFIER: Lazy<Regex> =
 pub static LEGACY_IDENTIFIER: Lazy<Regex> =
     Lazy::new(|| Regex::new("^[1-9A-HJ-NP-Za-km-z]{21,22}$").unwrap());
 
+/// Check for a legacy unqualified DID, such as `NcYxiDXkpYi6ov5FcYDi1e`
+pub fn is_legacy_did(id: &str) -> bool {
+    LEGACY_IDENTIFIER.is_match(id)
+}
+
+/// Check whether an identifier starts with a legacy DID component. Such identifiers are
+/// only accepted in their legacy structured form and never as a URI.
+pub fn has_legacy_did_prefix(id: &str) -> bool {
+    is_legacy_did(id.split(':').next().unwrap_or_default())
+}
+
+/// Check for a legacy schema identifier: `<did>:2:<name>:<version>`
+pub fn is_legacy_schema_id(id: &str) -> bool {
+    let parts: Vec<&str> = id.split(':').collect();
+    matches!(
+        parts[..],
+        [did, "2", name, version] if is_legacy_did(did) && !name.is_empty() && !version.is_empty()
+    )
+}
+
+/// Check for a legacy credential definition identifier:
+/// `<did>:3:CL:<schema_seq_no>:<tag>`, where the schema reference may also be a full
+/// legacy schema identifier
+pub fn is_legacy_cred_def_id(id: &str) -> bool {
+    let parts: Vec<&str> = id.split(':').collect();
+    match parts[..] {
+        [did, "3", "CL", seq_no, tag] => {
+            is_legacy_did(did)
+                && !seq_no.is_empty()
+                && seq_no.bytes().all(|b| b.is_ascii_digit())
+                && !tag.is_empty()
+        }
+        [did, "3", "CL", ref schema_id @ .., tag] if schema_id.len() == 4 => {
+            is_legacy_did(did) && is_legacy_schema_id(&schema_id.join(":")) && !tag.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// Check for a legacy revocation registry identifier:
+/// `<did>:4:<cred_def_id>:CL_ACCUM:<tag>`
+pub fn is_legacy_rev_reg_id(id: &str) -> bool {
+    let (did, rest) = match id.split_once(':') {
+        Some((did, rest)) => (did, rest),
+        None => return false,
+    };
+    let mut tail = rest.rsplitn(3, ':');
+    match (tail.next(), tail.next(), tail.next()) {
+        (Some(tag), Some("CL_ACCUM"), Some(cred_def_id)) => match cred_def_id.strip_prefix("4:") {
+            Some(cred_def_id) => {
+                is_legacy_did(did) && is_legacy_cred_def_id(cred_def_id) && !tag.is_empty()
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// The components of a URI identifier as described by
+/// https://datatracker.ietf.org/doc/html/rfc3986#section-3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UriParts<'a> {
+    /// The URI scheme, such as `did` or `https`
+    pub scheme: &'a str,
+    /// Everything following the colon after the scheme
+    pub rest: &'a str,
+    /// The authority, present when the remainder starts with `//`
+    pub authority: Option<&'a str>,
+    pub path: &'a str,
+    pub query: Option<&'a str>,
+    pub fragment: Option<&'a str>,
+}
+
+impl UriParts<'_> {
+    pub fn is_did(&self) -> bool {
+        self.scheme.eq_ignore_ascii_case("did")
+    }
+}
+
+/// Split a URI identifier into its components, rejecting malformed schemes,
+/// empty paths and characters not permitted by RFC 3986 (including whitespace
+/// and control characters).
+pub fn parse_uri_identifier(id: &str) -> Result<UriParts<'_>, ValidationError> {
+    let (scheme, rest) = id
+        .split_once(':')
+        .ok_or_else(|| invalid!("URI identifier is missing a scheme: {:?}", id))?;
+
+    // scheme = ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+    let mut scheme_chars = scheme.chars();
+    if !scheme_chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic())
+        || !scheme_chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    {
+        return Err(invalid!("URI identifier has an invalid scheme: {:?}", id));
+    }
+
+    if let Some(pos) = rest.find(|c: char| !is_uri_char(c)) {
+        return Err(invalid!(
+            "URI identifier contains an illegal character at position {}: {:?}",
+            scheme.len() + 1 + pos,
+            id
+        ));
+    }
+    check_percent_encoding(rest)
+        .map_err(|_| invalid!("URI identifier has invalid percent-encoding: {:?}", id))?;
+
+    let (hier, fragment) = match rest.split_once('#') {
+        Some((hier, fragment)) => (hier, Some(fragment)),
+        None => (rest, None),
+    };
+    if fragment.map_or(false, |f| f.contains('#')) {
+        return Err(invalid!("URI identifier has an invalid fragment: {:?}", id));
+    }
+    let (hier, query) = match hier.split_once('?') {
+        Some((hier, query)) => (hier, Some(query)),
+        None => (hier, None),
+    };
+    let (authority, path) = match hier.strip_prefix("//") {
+        Some(hier) => {
+            let end = hier.find('/').unwrap_or(hier.len());
+            (Some(&hier[..end]), &hier[end..])
+        }
+        None => (None, hier),
+    };
+
+    // an empty authority is permitted before a path, as in `file:///path`
+    if path.is_empty() && authority.map_or(true, str::is_empty) {
+        return Err(invalid!("URI identifier has an empty path: {:?}", id));
+    }
+    if authority.map_or(false, |a| a.contains('/')) || path.contains(|c: char| c == '[' || c == ']')
+    {
+        return Err(invalid!("URI identifier is malformed: {:?}", id));
+    }
+
+    Ok(UriParts {
+        scheme,
+        rest,
+        authority,
+        path,
+        query,
+        fragment,
+    })
+}
+
 pub fn is_uri_identifier(id: &str) -> bool {
-    URI_IDENTIFIER.captures(id).is_some()
+    parse_uri_identifier(id).is_ok()
+}
+
+/// Characters permitted after the scheme: unreserved, reserved and `%`
+fn is_uri_char(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(
+            c,
+            // unreserved
+            '-' | '.' | '_' | '~'
+            // gen-delims
+            | ':' | '/' | '?' | '#' | '[' | ']' | '@'
+            // sub-delims
+            | '!' | '$' | '&' | '\'' | '(' | ')' | '*' | '+' | ',' | ';' | '='
+            // pct-encoded
+            | '%'
+        )
 }
 
-/// Macro to return a new `ValidationError` with an optional message
+fn check_percent_encoding(val: &str) -> Result<(), ()> {
+    let mut bytes = val.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            for _ in 0..2 {
+                if !bytes.next().map_or(false, |h| h.is_ascii_hexdigit()) {
+                    return Err(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Macro to return a new `ValidationError` with an optional kind and message
 #[macro_export]
 macro_rules! invalid {
     () => { $crate::error::ValidationError::from(None) };
+    ($kind:ident) => {
+        $crate::error::ValidationError::from($crate::error::ValidationErrorKind::$kind)
+    };
+    ($kind:ident, $($arg:tt)+) => {
+        $crate::error::ValidationError::from_kind_msg(
+            $crate::error::ValidationErrorKind::$kind,
+            format!($($arg)+),
+        )
+    };
     ($($arg:tt)+) => {
         $crate::error::ValidationError::from(format!($($arg)+))
     };
@@ -36,3 +220,207 @@ pub trait Validatable {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_did_indy() {
+        let parts = parse_uri_identifier("did:indy:sovrin:staging:6cgbu8ZPoWTnR5Rv5JcSMB").unwrap();
+        assert!(parts.is_did());
+        assert_eq!(parts.scheme, "did");
+        assert_eq!(parts.rest, "indy:sovrin:staging:6cgbu8ZPoWTnR5Rv5JcSMB");
+        assert_eq!(parts.authority, None);
+        assert_eq!(parts.path, "indy:sovrin:staging:6cgbu8ZPoWTnR5Rv5JcSMB");
+    }
+
+    #[test]
+    fn parse_did_web() {
+        let parts = parse_uri_identifier("did:web:example.com%3A3000:user:alice#key-1").unwrap();
+        assert!(parts.is_did());
+        assert_eq!(parts.path, "web:example.com%3A3000:user:alice");
+        assert_eq!(parts.fragment, Some("key-1"));
+    }
+
+    #[test]
+    fn parse_http() {
+        let parts =
+            parse_uri_identifier("https://example.com/schemas/1?version=1.0#attrs").unwrap();
+        assert!(!parts.is_did());
+        assert_eq!(parts.scheme, "https");
+        assert_eq!(parts.authority, Some("example.com"));
+        assert_eq!(parts.path, "/schemas/1");
+        assert_eq!(parts.query, Some("version=1.0"));
+        assert_eq!(parts.fragment, Some("attrs"));
+
+        let parts = parse_uri_identifier("http://localhost:8080").unwrap();
+        assert_eq!(parts.authority, Some("localhost:8080"));
+        assert_eq!(parts.path, "");
+    }
+
+    #[test]
+    fn parse_empty_authority() {
+        let parts = parse_uri_identifier("file:///tmp/schema.json").unwrap();
+        assert_eq!(parts.scheme, "file");
+        assert_eq!(parts.authority, Some(""));
+        assert_eq!(parts.path, "/tmp/schema.json");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_uri_pattern() {
+        assert!(URI_IDENTIFIER.is_match("did:indy:sovrin"));
+        assert!(!URI_IDENTIFIER.is_match("no-colon"));
+    }
+
+    #[test]
+    fn reject_permissive_cases() {
+        for id in [
+            "did:",
+            "x: ",
+            "did:indy: sovrin",
+            "did:indy:sovrin\n",
+            "did:indy:\u{7f}sovrin",
+            "did:indy:s\u{f6}vrin",
+            "https://",
+            "did:web:example.com#a#b",
+            "did:web:example.com%3",
+            "did:web:example.com%zz",
+            ":indy:sovrin",
+            "1did:indy:sovrin",
+            "d id:indy:sovrin",
+            "::::",
+            "no-colon",
+        ] {
+            assert!(
+                parse_uri_identifier(id).is_err(),
+                "{:?} should be rejected",
+                id
+            );
+            assert!(!is_uri_identifier(id));
+        }
+    }
+
+    #[test]
+    fn invalid_macro_sets_kind() {
+        use crate::error::ValidationErrorKind;
+
+        let err = invalid!();
+        assert_eq!(err.kind(), ValidationErrorKind::Generic);
+        assert_eq!(err.context, None);
+
+        let err = invalid!("value {} is invalid", 1);
+        assert_eq!(err.kind(), ValidationErrorKind::Generic);
+        assert_eq!(err.context.as_deref(), Some("value 1 is invalid"));
+
+        let err = invalid!(MissingField);
+        assert_eq!(err.kind(), ValidationErrorKind::MissingField);
+        assert_eq!(err.context, None);
+
+        let err = invalid!(IdentifierFormat, "identifier {} is invalid", "x");
+        assert_eq!(err.kind(), ValidationErrorKind::IdentifierFormat);
+        assert_eq!(err.context.as_deref(), Some("identifier x is invalid"));
+        assert_eq!(err.kind().to_string(), "Invalid identifier format");
+        assert_eq!(err.to_string(), "Validation error: identifier x is invalid");
+    }
+
+    #[test]
+    fn legacy_identifier_unchanged() {
+        assert!(LEGACY_IDENTIFIER.is_match("NcYxiDXkpYi6ov5FcYDi1e"));
+        assert!(!is_uri_identifier("NcYxiDXkpYi6ov5FcYDi1e"));
+    }
+
+    mod legacy {
+        use super::*;
+
+        const DID: &str = "NcYxiDXkpYi6ov5FcYDi1e";
+        const SCHEMA_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e:2:gvt:1.0";
+        const CRED_DEF_ID: &str = "NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag";
+        const CRED_DEF_ID_FULL: &str =
+            "NcYxiDXkpYi6ov5FcYDi1e:3:CL:NcYxiDXkpYi6ov5FcYDi1e:2:gvt:1.0:tag";
+        const REV_REG_ID: &str =
+            "NcYxiDXkpYi6ov5FcYDi1e:4:NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag:CL_ACCUM:default";
+
+        #[test]
+        fn schema_id() {
+            assert!(is_legacy_schema_id(SCHEMA_ID));
+            assert!(is_legacy_schema_id(
+                "6cgbu8ZPoWTnR5Rv5JcSMB:2:Test Schema:1.0"
+            ));
+            for id in [
+                "NcYxiDXkpYi6ov5FcYDi1e:2:gvt",
+                "NcYxiDXkpYi6ov5FcYDi1e:2::1.0",
+                "NcYxiDXkpYi6ov5FcYDi1e:2:gvt:",
+                "NcYxiDXkpYi6ov5FcYDi1e:3:gvt:1.0",
+                "NcYxiDXkpYi6ov5FcYDi1e:2:gvt:1.0:extra",
+                "NcYxiDXkpYi6ov5FcYDi1:2:gvt:1.0",
+                "NcYxiDXkpYi6ov5FcYDi10:2:gvt:1.0",
+            ] {
+                assert!(!is_legacy_schema_id(id), "{:?} should be rejected", id);
+            }
+        }
+
+        #[test]
+        fn cred_def_id() {
+            assert!(is_legacy_cred_def_id(CRED_DEF_ID));
+            assert!(is_legacy_cred_def_id(CRED_DEF_ID_FULL));
+            for id in [
+                "NcYxiDXkpYi6ov5FcYDi1e:3:CL:1",
+                "NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:",
+                "NcYxiDXkpYi6ov5FcYDi1e:3:CL:x1:tag",
+                "NcYxiDXkpYi6ov5FcYDi1e:3:BBS:1:tag",
+                "NcYxiDXkpYi6ov5FcYDi1e:3:CL:NcYxiDXkpYi6ov5FcYDi1e:2:gvt:tag",
+                "NcYxiDXkpYi6ov5FcYDi1e:3:CL:NcYxiDXkpYi6ov5FcYDi1e:3:gvt:1.0:tag",
+            ] {
+                assert!(!is_legacy_cred_def_id(id), "{:?} should be rejected", id);
+            }
+        }
+
+        #[test]
+        fn rev_reg_id() {
+            assert!(is_legacy_rev_reg_id(REV_REG_ID));
+            assert!(is_legacy_rev_reg_id(
+                "NcYxiDXkpYi6ov5FcYDi1e:4:NcYxiDXkpYi6ov5FcYDi1e:3:CL:NcYxiDXkpYi6ov5FcYDi1e:2:gvt:1.0:tag:CL_ACCUM:default"
+            ));
+            for id in [
+                "NcYxiDXkpYi6ov5FcYDi1e:4:NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag:CL_ACCUM",
+                "NcYxiDXkpYi6ov5FcYDi1e:4:NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag:CL_ACCUM:",
+                "NcYxiDXkpYi6ov5FcYDi1e:4:NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag:ACCUM:default",
+                "NcYxiDXkpYi6ov5FcYDi1e:4:NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:CL_ACCUM:default",
+                "NcYxiDXkpYi6ov5FcYDi1e:5:NcYxiDXkpYi6ov5FcYDi1e:3:CL:1:tag:CL_ACCUM:default",
+            ] {
+                assert!(!is_legacy_rev_reg_id(id), "{:?} should be rejected", id);
+            }
+        }
+
+        #[test]
+        fn no_cross_confusion() {
+            let validators: [(&str, fn(&str) -> bool); 4] = [
+                (DID, is_legacy_did),
+                (SCHEMA_ID, is_legacy_schema_id),
+                (CRED_DEF_ID, is_legacy_cred_def_id),
+                (REV_REG_ID, is_legacy_rev_reg_id),
+            ];
+            for (i, (id, _)) in validators.iter().enumerate() {
+                for (j, (_, check)) in validators.iter().enumerate() {
+                    assert_eq!(check(id), i == j, "{:?} matched validator {}", id, j);
+                }
+            }
+            assert!(is_legacy_cred_def_id(CRED_DEF_ID_FULL));
+            assert!(!is_legacy_schema_id(CRED_DEF_ID_FULL));
+            assert!(!is_legacy_rev_reg_id(CRED_DEF_ID_FULL));
+        }
+
+        #[test]
+        fn legacy_prefix() {
+            for id in [DID, SCHEMA_ID, CRED_DEF_ID, REV_REG_ID] {
+                assert!(has_legacy_did_prefix(id));
+            }
+            assert!(!has_legacy_did_prefix(
+                "did:indy:sovrin:staging:6cgbu8ZPoWTnR5Rv5JcSMB"
+            ));
+            assert!(!has_legacy_did_prefix("https://example.com/schemas/1"));
+        }
+    }
+}
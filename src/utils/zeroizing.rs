@@ -0,0 +1,49 @@
+use std::io;
+
+use zeroize::{Zeroize, Zeroizing};
+
+/// Growable output buffer for serialized values which may contain secrets, such as
+/// a master secret or the private key of a credential definition. The previous
+/// allocation is cleared whenever the buffer grows, and the contents are cleared
+/// when the buffer is dropped.
+#[derive(Debug, Default)]
+pub(crate) struct ZeroizingBuffer(Zeroizing<Vec<u8>>);
+
+impl ZeroizingBuffer {
+    /// Take the contents of the buffer, which the caller is then responsible for clearing
+    pub fn into_vec(mut self) -> Vec<u8> {
+        std::mem::take(&mut *self.0)
+    }
+}
+
+impl io::Write for ZeroizingBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.0.len() + buf.len();
+        if len > self.0.capacity() {
+            let mut grown = Vec::with_capacity(len.max(2 * self.0.capacity()));
+            grown.extend_from_slice(&self.0);
+            std::mem::replace(&mut *self.0, grown).zeroize();
+        }
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn zeroizing_buffer_keeps_contents_when_growing() {
+        let mut buf = ZeroizingBuffer::default();
+        for chunk in (0..=255u8).collect::<Vec<_>>().chunks(7) {
+            buf.write_all(chunk).unwrap();
+        }
+        assert_eq!(buf.into_vec(), (0..=255u8).collect::<Vec<_>>());
+    }
+}
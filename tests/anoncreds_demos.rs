@@ -1,28 +1,40 @@
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     fs::create_dir,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
 };
 
 use anoncreds::{
     data_types::{
         cred_def::{CredentialDefinition, CredentialDefinitionId},
+        master_secret::MasterSecret,
         presentation::Presentation,
         rev_reg::RevocationRegistryId,
         rev_reg_def::RevocationRegistryDefinitionId,
         schema::{Schema, SchemaId},
+        w3c::W3cCredential,
     },
     issuer, prover,
-    tails::{TailsFileReader, TailsFileWriter},
+    tails::{TailsFileReader, TailsFileWriter, TailsReader, TailsReaderImpl},
     types::{
         CredentialDefinitionConfig, CredentialRevocationConfig, CredentialRevocationState,
-        MakeCredentialValues, PresentCredentials, PresentationRequest, RegistryType, SignatureType,
+        CredentialValues, MakeCredentialValues, NonceRegistry, PresentCredentials,
+        PresentationRequest, RegistryType, RevocationRegistryDelta, RevocationStatusList,
+        SignatureType, VerifyParams,
     },
-    verifier,
+    verifier, ErrorKind,
 };
 
 use serde_json::json;
+use sha2::{Digest, Sha256};
 
 use self::utils::anoncreds::ProverWallet;
+use self::utils::fixtures::{gvt_values, json_copy, presentation_request, TestIssuer};
 
 mod utils;
 
@@ -56,15 +68,14 @@ fn anoncreds_works_for_single_issuer_single_prover() {
         ISSUER_ID,
         "tag",
         SignatureType::CL,
-        CredentialDefinitionConfig {
-            support_revocation: false,
-        },
+        CredentialDefinitionConfig::new(false),
     )
     .expect("Error creating gvt credential definition");
 
     // Issuer creates a Credential Offer
-    let cred_offer = issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &cred_def_correctness)
-        .expect("Error creating credential offer");
+    let cred_offer =
+        issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &cred_def_correctness, None)
+            .expect("Error creating credential offer");
 
     // Prover creates a Credential Request
     let (cred_request, cred_request_metadata) = prover::create_credential_request(
@@ -73,6 +84,7 @@ fn anoncreds_works_for_single_issuer_single_prover() {
         &prover_wallet.master_secret,
         "default",
         &cred_offer,
+        None,
     )
     .expect("Error creating credential request");
 
@@ -99,6 +111,8 @@ fn anoncreds_works_for_single_issuer_single_prover() {
         None,
         None,
         None,
+        None,
+        None,
     )
     .expect("Error creating credential");
 
@@ -220,6 +234,8 @@ fn anoncreds_works_for_single_issuer_single_prover() {
         &cred_defs,
         None,
         None,
+        None,
+        None,
     )
     .expect("Error verifying presentation");
     assert!(valid);
@@ -246,9 +262,7 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
         ISSUER_ID,
         "tag",
         SignatureType::CL,
-        CredentialDefinitionConfig {
-            support_revocation: true,
-        },
+        CredentialDefinitionConfig::new(true),
     )
     .expect("Error creating gvt credential definition");
 
@@ -289,8 +303,9 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
     .unwrap();
 
     // Issuer creates a Credential Offer
-    let cred_offer = issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &cred_def_correctness)
-        .expect("Error creating credential offer");
+    let cred_offer =
+        issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &cred_def_correctness, None)
+            .expect("Error creating credential offer");
 
     // Prover creates a Credential Request
     let (cred_request, cred_request_metadata) = prover::create_credential_request(
@@ -299,6 +314,7 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
         &prover_wallet.master_secret,
         "default",
         &cred_offer,
+        None,
     )
     .expect("Error creating credential request");
 
@@ -324,6 +340,21 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
     let location = rev_reg_def_pub.clone().value.tails_location;
     let tr = TailsFileReader::new_tails_reader(location.as_str());
 
+    // The tails file matches the hash recorded in the definition, and a
+    // tampered hash is detected
+    rev_reg_def_pub
+        .verify_tails(&TailsFileReader::new_tails_reader(location.as_str()))
+        .expect("Tails file does not match the revocation registry definition");
+    let mut tampered_rev_reg_def = rev_reg_def_pub.clone();
+    tampered_rev_reg_def.value.tails_hash = bs58::encode([0u8; 32]).into_string();
+    assert_eq!(
+        tampered_rev_reg_def
+            .verify_tails(&TailsFileReader::new_tails_reader(location.as_str()))
+            .unwrap_err()
+            .kind(),
+        ErrorKind::TailsHashMismatch
+    );
+
     let issue_cred = issuer::create_credential(
         &cred_def_pub,
         &cred_def_priv,
@@ -337,7 +368,10 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
             reg_def_private: &rev_reg_def_priv,
             registry_idx: REV_IDX,
             tails_reader: tr,
+            verify_tails: true,
         }),
+        None,
+        None,
     )
     .expect("Error creating credential");
 
@@ -346,6 +380,7 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
         Some(time_after_creating_cred),
         Some(BTreeSet::from([REV_IDX])),
         None,
+        None,
         &rev_reg_def_pub,
         &revocation_status_list,
     )
@@ -423,6 +458,15 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
         Some(&rev_state),
     );
 
+    // Verifier resolves the referenced objects before verifying
+    let identifiers = presentation.identifiers();
+    assert_eq!(identifiers.len(), 1);
+    assert_eq!(identifiers[0].cred_def_id, cred_def_id);
+    assert_eq!(
+        presentation.referenced_revocation(),
+        vec![(rev_reg_def_id.clone(), Some(time_after_creating_cred))]
+    );
+
     // Verifier verifies presentation of not Revoked rev_state
     let rev_reg_def_map = HashMap::from([(&rev_reg_def_id, &rev_reg_def_pub)]);
     let valid = verifier::verify_presentation(
@@ -432,16 +476,47 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
         &cred_defs,
         Some(&rev_reg_def_map),
         Some(rev_status_list.clone()),
+        None,
+        None,
     )
     .expect("Error verifying presentation");
     assert!(valid);
 
+    // The presentation survives a round-trip through the compact encoding
+    #[cfg(feature = "cbor")]
+    {
+        let json = serde_json::to_value(&presentation).unwrap();
+        let cbor = presentation.to_cbor().expect("Error encoding presentation");
+        let json_len = json.to_string().len();
+        println!(
+            "revocation-enabled presentation: {} bytes of JSON, {} bytes of CBOR ({:.0}%)",
+            json_len,
+            cbor.len(),
+            100.0 * cbor.len() as f64 / json_len as f64
+        );
+        assert!(cbor.len() < json_len);
+        let decoded = Presentation::from_cbor(&cbor).expect("Error decoding presentation");
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+        assert!(verifier::verify_presentation(
+            &decoded,
+            &pres_request,
+            &schemas,
+            &cred_defs,
+            Some(&rev_reg_def_map),
+            Some(rev_status_list.clone()),
+            None,
+            None,
+        )
+        .expect("Error verifying presentation"));
+    }
+
     //  ===================== Issuer revokes credential ================
     let time_revoke_cred = time_after_creating_cred + 1;
     let revoked_status_list = issuer::update_revocation_status_list(
         Some(time_revoke_cred),
         None,
         Some(BTreeSet::from([REV_IDX])),
+        None,
         &rev_reg_def_pub,
         &issued_rev_status_list,
     )
@@ -477,11 +552,1353 @@ fn anoncreds_with_revocation_works_for_single_issuer_single_prover() {
         &cred_defs,
         Some(&rev_reg_def_map),
         Some(rev_status_list),
+        None,
+        None,
     )
     .expect("Error verifying presentation");
     assert!(!valid);
 }
 
+#[test]
+fn anoncreds_works_for_range_predicate() {
+    let mut prover_wallet = ProverWallet::default();
+
+    let gvt_schema = issuer::create_schema(
+        GVT_SCHEMA_NAME,
+        "1.0",
+        ISSUER_ID,
+        GVT_SCHEMA_ATTRIBUTES[..].into(),
+    )
+    .expect("Error creating gvt schema for issuer");
+
+    let (cred_def_pub, cred_def_priv, cred_def_correctness) = issuer::create_credential_definition(
+        SCHEMA_ID,
+        &gvt_schema,
+        ISSUER_ID,
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(false),
+    )
+    .expect("Error creating gvt credential definition");
+
+    let cred_offer =
+        issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &cred_def_correctness, None)
+            .expect("Error creating credential offer");
+
+    let (cred_request, cred_request_metadata) = prover::create_credential_request(
+        None,
+        &cred_def_pub,
+        &prover_wallet.master_secret,
+        "default",
+        &cred_offer,
+        None,
+    )
+    .expect("Error creating credential request");
+
+    let mut cred_values = MakeCredentialValues::default();
+    cred_values.add_raw("sex", "male").unwrap();
+    cred_values.add_raw("name", "Alex").unwrap();
+    cred_values.add_raw("height", "175").unwrap();
+    cred_values.add_raw("age", "28").unwrap();
+    let mut recv_cred = issuer::create_credential(
+        &cred_def_pub,
+        &cred_def_priv,
+        &cred_offer,
+        &cred_request,
+        cred_values.into(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .expect("Error creating credential");
+    prover::process_credential(
+        &mut recv_cred,
+        &cred_request_metadata,
+        &prover_wallet.master_secret,
+        &cred_def_pub,
+        None,
+    )
+    .expect("Error processing credential");
+    prover_wallet.credentials.push(recv_cred);
+
+    let nonce = verifier::generate_nonce().expect("Error generating presentation request nonce");
+    let pres_request: PresentationRequest = serde_json::from_value(json!({
+        "nonce": nonce,
+        "name":"pres_req_1",
+        "version":"0.1",
+        "requested_predicates":{
+            "predicate1_referent":{
+                "name":"age",
+                "p_type":">=",
+                "p_value":18,
+                "second_bound":{"p_type":"<=","p_value":65}
+            }
+        }
+    }))
+    .expect("Error creating proof request");
+
+    let mut present = PresentCredentials::default();
+    present
+        .add_credential(&prover_wallet.credentials[0], None, None)
+        .add_requested_predicate("predicate1_referent");
+
+    let mut schemas = HashMap::new();
+    let schema_id = SchemaId::new_unchecked(SCHEMA_ID);
+    schemas.insert(&schema_id, &gvt_schema);
+
+    let mut cred_defs = HashMap::new();
+    let cred_def_id = CredentialDefinitionId::new_unchecked(CRED_DEF_ID);
+    cred_defs.insert(&cred_def_id, &cred_def_pub);
+
+    let presentation = prover::create_presentation(
+        &pres_request,
+        present,
+        None,
+        &prover_wallet.master_secret,
+        &schemas,
+        &cred_defs,
+    )
+    .expect("Error creating presentation");
+
+    let valid = verifier::verify_presentation(
+        &presentation,
+        &pres_request,
+        &schemas,
+        &cred_defs,
+        None,
+        None,
+        None,
+        None,
+    )
+    .expect("Error verifying presentation");
+    assert!(valid);
+
+    // A request with an empty range is rejected by the verifier
+    let mut invalid_request = json!(pres_request);
+    invalid_request["requested_predicates"]["predicate1_referent"]["second_bound"]["p_value"] =
+        json!(17);
+    let invalid_request: PresentationRequest = serde_json::from_value(invalid_request).unwrap();
+    assert!(verifier::verify_presentation(
+        &presentation,
+        &invalid_request,
+        &schemas,
+        &cred_defs,
+        None,
+        None,
+        None,
+        None,
+    )
+    .is_err());
+}
+
+#[test]
+fn anoncreds_credential_w3c_roundtrip() {
+    let prover_wallet = ProverWallet::default();
+
+    let gvt_schema = issuer::create_schema(
+        GVT_SCHEMA_NAME,
+        "1.0",
+        ISSUER_ID,
+        GVT_SCHEMA_ATTRIBUTES[..].into(),
+    )
+    .expect("Error creating gvt schema for issuer");
+
+    let (cred_def_pub, cred_def_priv, cred_def_correctness) = issuer::create_credential_definition(
+        SCHEMA_ID,
+        &gvt_schema,
+        ISSUER_ID,
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(false),
+    )
+    .expect("Error creating gvt credential definition");
+
+    let cred_offer =
+        issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &cred_def_correctness, None)
+            .expect("Error creating credential offer");
+
+    let (cred_request, cred_request_metadata) = prover::create_credential_request(
+        None,
+        &cred_def_pub,
+        &prover_wallet.master_secret,
+        "default",
+        &cred_offer,
+        None,
+    )
+    .expect("Error creating credential request");
+
+    let mut cred_values = MakeCredentialValues::default();
+    cred_values.add_raw("sex", "male").unwrap();
+    cred_values.add_raw("name", "Alex").unwrap();
+    cred_values.add_raw("height", "175").unwrap();
+    // encoded values which do not follow the default encoding must be preserved
+    cred_values.add_encoded("age", "28", "1028".to_owned());
+    let mut credential = issuer::create_credential(
+        &cred_def_pub,
+        &cred_def_priv,
+        &cred_offer,
+        &cred_request,
+        cred_values.into(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .expect("Error creating credential");
+    prover::process_credential(
+        &mut credential,
+        &cred_request_metadata,
+        &prover_wallet.master_secret,
+        &cred_def_pub,
+        None,
+    )
+    .expect("Error processing credential");
+
+    let w3c_cred = credential
+        .to_w3c(&cred_def_pub)
+        .expect("Error converting credential to W3C");
+    let w3c_json = serde_json::to_value(&w3c_cred).unwrap();
+    assert_eq!(w3c_json["proof"]["type"], "DataIntegrityProof");
+    assert_eq!(w3c_json["credentialSubject"]["name"], "Alex");
+
+    let w3c_cred: W3cCredential = serde_json::from_value(w3c_json).unwrap();
+    let legacy = w3c_cred
+        .to_legacy()
+        .expect("Error converting W3C credential");
+    assert_eq!(legacy.values, credential.values);
+    assert_eq!(legacy.values.0["age"].encoded, "1028");
+    assert_eq!(legacy.schema_id, credential.schema_id);
+    assert_eq!(legacy.cred_def_id, credential.cred_def_id);
+
+    // A credential definition for a different set of attributes is rejected
+    let other_schema = issuer::create_schema(
+        GVT_SCHEMA_NAME,
+        "1.0",
+        ISSUER_ID,
+        GVT_SCHEMA_ATTRIBUTES[..3].into(),
+    )
+    .expect("Error creating schema");
+    let (other_cred_def, _, _) = issuer::create_credential_definition(
+        SCHEMA_ID,
+        &other_schema,
+        ISSUER_ID,
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(false),
+    )
+    .expect("Error creating credential definition");
+    assert!(credential.to_w3c(&other_cred_def).is_err());
+
+    // Attribute names which only differ in case are rejected
+    let mut colliding = credential.try_clone().unwrap();
+    let name = colliding.values.0["name"].clone();
+    colliding.values.0.insert("Name".to_owned(), name);
+    assert!(colliding.to_w3c(&cred_def_pub).is_err());
+}
+
+#[test]
+fn anoncreds_works_for_batch_issuance() {
+    let mut prover_wallet = ProverWallet::default();
+
+    let gvt_schema = issuer::create_schema(
+        GVT_SCHEMA_NAME,
+        "1.0",
+        ISSUER_ID,
+        GVT_SCHEMA_ATTRIBUTES[..].into(),
+    )
+    .expect("Error creating gvt schema for issuer");
+
+    let (cred_def_pub, cred_def_priv, cred_def_correctness) = issuer::create_credential_definition(
+        SCHEMA_ID,
+        &gvt_schema,
+        ISSUER_ID,
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(true),
+    )
+    .expect("Error creating gvt credential definition");
+
+    let tf_path = "../.tmp";
+    create_dir(tf_path)
+        .or_else(|e| -> Result<(), std::io::Error> {
+            println!(
+                "Tail file path creation error but test can still proceed {}",
+                e
+            );
+            Ok(())
+        })
+        .unwrap();
+    let mut tf = TailsFileWriter::new(Some(tf_path.to_owned()));
+
+    let (rev_reg_def_pub, rev_reg_def_priv) = issuer::create_revocation_registry_def(
+        &cred_def_pub,
+        CRED_DEF_ID,
+        ISSUER_ID,
+        "some_tag",
+        RegistryType::CL_ACCUM,
+        MAX_CRED_NUM,
+        &mut tf,
+    )
+    .unwrap();
+
+    let revocation_status_list =
+        issuer::create_revocation_status_list(REV_REG_DEF_ID, &rev_reg_def_pub, Some(12), true)
+            .unwrap();
+
+    let offers = (0..5)
+        .map(|_| {
+            issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &cred_def_correctness, None)
+                .expect("Error creating credential offer")
+        })
+        .collect::<Vec<_>>();
+    let requests = offers
+        .iter()
+        .map(|offer| {
+            prover::create_credential_request(
+                None,
+                &cred_def_pub,
+                &prover_wallet.master_secret,
+                "default",
+                offer,
+                None,
+            )
+            .expect("Error creating credential request")
+        })
+        .collect::<Vec<_>>();
+
+    let location = rev_reg_def_pub.value.tails_location.clone();
+    // The third entry reuses the revocation index of the first one, and the
+    // last two are outside of the registry
+    let batch = offers
+        .iter()
+        .zip(requests.iter())
+        .zip([1, 2, 1, 0, MAX_CRED_NUM])
+        .map(|((offer, (request, _)), idx)| {
+            let mut cred_values = MakeCredentialValues::default();
+            cred_values.add_raw("sex", "male").unwrap();
+            cred_values.add_raw("name", "Alex").unwrap();
+            cred_values.add_raw("height", "175").unwrap();
+            cred_values.add_raw("age", "28").unwrap();
+            (
+                offer,
+                request,
+                CredentialValues::from(cred_values),
+                Some(CredentialRevocationConfig {
+                    reg_def: &rev_reg_def_pub,
+                    reg_def_private: &rev_reg_def_priv,
+                    registry_idx: idx,
+                    tails_reader: TailsFileReader::new_tails_reader(location.as_str()),
+                    verify_tails: false,
+                }),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut nonces = NonceRegistry::new(Duration::from_secs(600));
+    let issued = issuer::create_credentials(
+        &cred_def_pub,
+        &cred_def_priv,
+        Some(RevocationRegistryId::new_unchecked(REV_REG_DEF_ID)),
+        Some(&revocation_status_list),
+        batch,
+        None,
+        Some(&mut nonces),
+    )
+    .expect("Error creating credentials");
+    assert_eq!(issued.len(), 5);
+    // Only the offers of the issued credentials are consumed
+    for (i, offer) in offers.iter().enumerate() {
+        assert_eq!(nonces.contains(&offer.nonce), i < 2);
+    }
+
+    let mut issued = issued.into_iter();
+    for (_, metadata) in requests.iter().take(2) {
+        let mut recv_cred = issued.next().unwrap().expect("Error creating credential");
+        prover::process_credential(
+            &mut recv_cred,
+            metadata,
+            &prover_wallet.master_secret,
+            &cred_def_pub,
+            Some(&rev_reg_def_pub),
+        )
+        .expect("Error processing credential");
+        prover_wallet.credentials.push(recv_cred);
+    }
+    assert_eq!(
+        issued.next().unwrap().unwrap_err().kind(),
+        anoncreds::ErrorKind::InvalidUserRevocId
+    );
+    for (rejected, idx) in issued.zip([0, MAX_CRED_NUM]) {
+        assert_eq!(
+            rejected.unwrap_err().kind(),
+            anoncreds::ErrorKind::InvalidRevocationIndex {
+                idx,
+                max: MAX_CRED_NUM
+            }
+        );
+    }
+
+    // The index is also checked when issuing a single credential
+    let issue_single = |idx: u32| {
+        let mut cred_values = MakeCredentialValues::default();
+        cred_values.add_raw("sex", "male").unwrap();
+        cred_values.add_raw("name", "Alex").unwrap();
+        cred_values.add_raw("height", "175").unwrap();
+        cred_values.add_raw("age", "28").unwrap();
+        issuer::create_credential(
+            &cred_def_pub,
+            &cred_def_priv,
+            &offers[3],
+            &requests[3].0,
+            cred_values.into(),
+            Some(RevocationRegistryId::new_unchecked(REV_REG_DEF_ID)),
+            Some(&revocation_status_list),
+            Some(CredentialRevocationConfig {
+                reg_def: &rev_reg_def_pub,
+                reg_def_private: &rev_reg_def_priv,
+                registry_idx: idx,
+                tails_reader: TailsFileReader::new_tails_reader(location.as_str()),
+                verify_tails: false,
+            }),
+            None,
+            None,
+        )
+    };
+    for idx in [0, MAX_CRED_NUM] {
+        assert_eq!(
+            issue_single(idx).unwrap_err().kind(),
+            anoncreds::ErrorKind::InvalidRevocationIndex {
+                idx,
+                max: MAX_CRED_NUM
+            }
+        );
+    }
+}
+
+#[test]
+fn anoncreds_works_for_revocation_state_update_from_deltas() {
+    let gvt_schema = issuer::create_schema(
+        GVT_SCHEMA_NAME,
+        "1.0",
+        ISSUER_ID,
+        GVT_SCHEMA_ATTRIBUTES[..].into(),
+    )
+    .expect("Error creating gvt schema for issuer");
+
+    let (cred_def_pub, _, _) = issuer::create_credential_definition(
+        SCHEMA_ID,
+        &gvt_schema,
+        ISSUER_ID,
+        "tag",
+        SignatureType::CL,
+        CredentialDefinitionConfig::new(true),
+    )
+    .expect("Error creating gvt credential definition");
+
+    let tf_path = "../.tmp";
+    create_dir(tf_path).ok();
+    let mut tf = TailsFileWriter::new(Some(tf_path.to_owned()));
+
+    let (rev_reg_def_pub, _) = issuer::create_revocation_registry_def(
+        &cred_def_pub,
+        CRED_DEF_ID,
+        ISSUER_ID,
+        "some_tag",
+        RegistryType::CL_ACCUM,
+        MAX_CRED_NUM,
+        &mut tf,
+    )
+    .unwrap();
+
+    let status_list_0 =
+        issuer::create_revocation_status_list(REV_REG_DEF_ID, &rev_reg_def_pub, Some(12), true)
+            .unwrap();
+    let status_list_1 = issuer::update_revocation_status_list(
+        Some(13),
+        None,
+        Some(BTreeSet::from([1])),
+        None,
+        &rev_reg_def_pub,
+        &status_list_0,
+    )
+    .unwrap();
+    let status_list_2 = issuer::update_revocation_status_list(
+        Some(14),
+        None,
+        Some(BTreeSet::from([2])),
+        None,
+        &rev_reg_def_pub,
+        &status_list_1,
+    )
+    .unwrap();
+
+    let registry = |list: &RevocationStatusList| -> anoncreds::ursa::cl::RevocationRegistry {
+        Option::<anoncreds::ursa::cl::RevocationRegistry>::from(list).unwrap()
+    };
+    let delta = |from: &RevocationStatusList, to: &RevocationStatusList, revoked: u32| {
+        RevocationRegistryDelta {
+            value: anoncreds::ursa::cl::RevocationRegistryDelta::from_parts(
+                Some(&registry(from)),
+                &registry(to),
+                &HashSet::new(),
+                &HashSet::from([revoked]),
+            ),
+        }
+    };
+    let delta_1 = delta(&status_list_0, &status_list_1, 1);
+    let delta_2 = delta(&status_list_1, &status_list_2, 2);
+
+    let rev_state = prover::create_or_update_revocation_state(
+        &rev_reg_def_pub.value.tails_location,
+        &rev_reg_def_pub,
+        &status_list_0,
+        REV_IDX,
+        None,
+        None,
+    )
+    .unwrap();
+    let expected = prover::create_or_update_revocation_state(
+        &rev_reg_def_pub.value.tails_location,
+        &rev_reg_def_pub,
+        &status_list_2,
+        REV_IDX,
+        Some(&rev_state),
+        Some(&status_list_0),
+    )
+    .unwrap();
+
+    let tails_reader = TailsFileReader::new_tails_reader(&rev_reg_def_pub.value.tails_location);
+    let mut updated = rev_state.clone();
+    updated
+        .update_from_deltas(
+            &rev_reg_def_pub,
+            REV_IDX,
+            &[delta_1.clone(), delta_2.clone()],
+            14,
+            &tails_reader,
+        )
+        .expect("Error applying revocation registry deltas");
+    assert_eq!(updated.timestamp, 14);
+    assert_eq!(
+        serde_json::to_value(&updated.witness).unwrap(),
+        serde_json::to_value(&expected.witness).unwrap()
+    );
+
+    // out of order deltas are rejected and leave the state unchanged
+    let mut unordered = rev_state.clone();
+    unordered
+        .update_from_deltas(
+            &rev_reg_def_pub,
+            REV_IDX,
+            &[delta_2, delta_1],
+            14,
+            &tails_reader,
+        )
+        .expect_err("Expected out of order deltas to be rejected");
+    assert_eq!(unordered.timestamp, rev_state.timestamp);
+
+    // deltas with indices outside of the registry are rejected before any of them
+    // is applied
+    for idx in [0, MAX_CRED_NUM + 1] {
+        let out_of_range = delta(&status_list_0, &status_list_1, idx);
+        let mut rejected = rev_state.clone();
+        let err = rejected
+            .update_from_deltas(
+                &rev_reg_def_pub,
+                REV_IDX,
+                &[out_of_range],
+                13,
+                &tails_reader,
+            )
+            .expect_err("Expected an out of range delta index to be rejected");
+        assert_eq!(err.kind(), anoncreds::ErrorKind::InvalidUserRevocId);
+        assert_eq!(rejected.timestamp, rev_state.timestamp);
+    }
+}
+
+#[test]
+fn anoncreds_works_from_blocking_threads() {
+    // The heavy operations are run on separate threads with owned inputs, as an
+    // asynchronous caller would do with `spawn_blocking`
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let gvt = thread::spawn(|| TestIssuer::gvt(false)).join().unwrap();
+
+    let cred_offer = gvt.offer();
+    let (cred_request, cred_request_metadata) = prover::create_credential_request(
+        None,
+        &gvt.cred_def,
+        &master_secret,
+        "default",
+        &cred_offer,
+        None,
+    )
+    .expect("Error creating credential request");
+
+    let (gvt, credential) = thread::spawn(move || {
+        let credential = issuer::create_credential(
+            &gvt.cred_def,
+            &gvt.cred_def_priv,
+            &cred_offer,
+            &cred_request,
+            gvt_values("Alex"),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        (gvt, credential)
+    })
+    .join()
+    .unwrap();
+    let mut credential = credential.expect("Error creating credential");
+
+    prover::process_credential(
+        &mut credential,
+        &cred_request_metadata,
+        &master_secret,
+        &gvt.cred_def,
+        None,
+    )
+    .expect("Error processing credential");
+
+    let pres_request = presentation_request(json!({
+        "requested_attributes":{
+            "attr1_referent":{"name":"name"}
+        },
+        "requested_predicates":{
+            "predicate1_referent":{"name":"age","p_type":">=","p_value":18}
+        }
+    }));
+
+    let (gvt, pres_request, presentation) = thread::spawn(move || {
+        let mut present = PresentCredentials::default();
+        {
+            let mut cred1 = present.add_credential(&credential, None, None);
+            cred1.add_requested_attribute("attr1_referent", true);
+            cred1.add_requested_predicate("predicate1_referent");
+        }
+        let presentation = gvt.create_presentation(&pres_request, present, None, &master_secret);
+        (gvt, pres_request, presentation)
+    })
+    .join()
+    .unwrap();
+    let presentation = presentation.expect("Error creating presentation");
+
+    let valid = gvt
+        .verify_presentation(&presentation, &pres_request)
+        .expect("Error verifying presentation");
+    assert!(valid);
+}
+
+#[derive(Debug)]
+struct CountingTailsReader {
+    data: Vec<u8>,
+    reads: Arc<AtomicUsize>,
+}
+
+impl TailsReaderImpl for CountingTailsReader {
+    fn hash(&mut self) -> anoncreds::Result<Vec<u8>> {
+        Ok(Sha256::digest(&self.data).to_vec())
+    }
+
+    fn read(&mut self, size: usize, offset: usize) -> anoncreds::Result<Vec<u8>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        Ok(self.data[offset..offset + size].to_vec())
+    }
+}
+
+#[test]
+fn anoncreds_works_for_batch_revocation_states() {
+    let gvt = TestIssuer::gvt(true);
+    let mut registry = gvt.create_registry(MAX_CRED_NUM, 12);
+    registry.update(13, None, Some(BTreeSet::from([2])));
+
+    let indices: Vec<u32> = (1..=MAX_CRED_NUM).collect();
+    let data = std::fs::read(&registry.rev_reg_def.value.tails_location).unwrap();
+    let counting_reader = |reads: &Arc<AtomicUsize>| {
+        TailsReader::new(CountingTailsReader {
+            data: data.clone(),
+            reads: reads.clone(),
+        })
+    };
+
+    let batch_reads = Arc::new(AtomicUsize::new(0));
+    let rev_states = prover::create_revocation_states(
+        &registry.rev_reg_def,
+        &registry.status_list,
+        &indices,
+        &counting_reader(&batch_reads),
+    )
+    .expect("Error creating revocation states");
+    assert_eq!(rev_states.len(), indices.len());
+
+    let single_reads = Arc::new(AtomicUsize::new(0));
+    let single_reader = counting_reader(&single_reads);
+    for idx in indices.iter().copied() {
+        let rev_state = prover::create_or_update_revocation_state_with_tails_reader(
+            &single_reader,
+            &registry.rev_reg_def,
+            &registry.status_list,
+            idx,
+            None,
+            None,
+            false,
+        )
+        .expect("Error creating revocation state");
+        assert_eq!(
+            serde_json::to_value(&rev_state).unwrap(),
+            serde_json::to_value(&rev_states[&idx]).unwrap()
+        );
+    }
+
+    // Each witness reads one tail for every other issued credential, while the
+    // batch only reads the distinct tails shared by all the witnesses
+    let batch_reads = batch_reads.load(Ordering::SeqCst);
+    let single_reads = single_reads.load(Ordering::SeqCst);
+    assert!(batch_reads <= 2 * MAX_CRED_NUM as usize);
+    assert!(single_reads >= indices.len() * (MAX_CRED_NUM as usize - 2));
+    println!(
+        "tails reads for {} revocation states: {} in a batch, {} one at a time",
+        indices.len(),
+        batch_reads,
+        single_reads
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn anoncreds_creates_the_same_revocation_states_on_any_number_of_threads() {
+    let gvt = TestIssuer::gvt(true);
+    let mut registry = gvt.create_registry(MAX_CRED_NUM, 12);
+    registry.update(13, None, Some(BTreeSet::from([3, 7])));
+
+    // Duplicated and unordered indices are accepted
+    let indices: Vec<u32> = (1..=MAX_CRED_NUM).rev().chain([4, 4]).collect();
+    let create_states = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            // The tails reader is not shared between threads
+            let tails_reader =
+                TailsFileReader::new_tails_reader(&registry.rev_reg_def.value.tails_location);
+            prover::create_revocation_states(
+                &registry.rev_reg_def,
+                &registry.status_list,
+                &indices,
+                &tails_reader,
+            )
+        })
+        .expect("Error creating revocation states")
+    };
+
+    let expected = serde_json::to_value(create_states(1)).unwrap();
+    for threads in [2, 4, 8] {
+        assert_eq!(
+            serde_json::to_value(create_states(threads)).unwrap(),
+            expected
+        );
+    }
+    assert_eq!(expected.as_object().unwrap().len(), MAX_CRED_NUM as usize);
+}
+
+#[test]
+fn anoncreds_works_for_credentials_with_different_master_secrets() {
+    // The holder migrated one of its credentials from another wallet, which used
+    // a different master secret
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let old_master_secret = MasterSecret::new().expect("Error creating prover master secret");
+
+    let gvt = TestIssuer::gvt(false);
+    let credential = gvt.issue(&master_secret, gvt_values("Alex"), None);
+    let old_credential = gvt.issue(&old_master_secret, gvt_values("Alexander"), None);
+
+    let pres_request = presentation_request(json!({
+        "requested_attributes":{
+            "attr1_referent":{"name":"name"},
+            "attr2_referent":{"name":"name"}
+        }
+    }));
+
+    let present_credentials = |allow_unlinked: bool| {
+        let mut present = PresentCredentials::default();
+        if allow_unlinked {
+            present.allow_unlinked_credentials();
+        }
+        present
+            .add_credential(&credential, None, None)
+            .add_requested_attribute("attr1_referent", true);
+        let mut old = present.add_credential(&old_credential, None, None);
+        old.add_requested_attribute("attr2_referent", true);
+        old.set_master_secret(&old_master_secret);
+        present
+    };
+
+    // Credentials with different master secrets cannot be linked
+    let res = gvt.create_presentation(
+        &pres_request,
+        present_credentials(false),
+        None,
+        &master_secret,
+    );
+    assert_eq!(res.unwrap_err().kind(), ErrorKind::Input);
+
+    let presentation = gvt
+        .create_presentation(
+            &pres_request,
+            present_credentials(true),
+            None,
+            &master_secret,
+        )
+        .expect("Error creating presentation");
+    let revealed = &presentation.requested_proof.revealed_attrs;
+    assert_eq!(revealed["attr1_referent"].raw, "Alex");
+    assert_eq!(revealed["attr2_referent"].raw, "Alexander");
+
+    let valid = gvt
+        .verify_presentation(&presentation, &pres_request)
+        .expect("Error verifying presentation");
+    assert!(valid);
+}
+
+#[test]
+fn anoncreds_works_for_detailed_presentation_verification() {
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let gvt = TestIssuer::gvt(false);
+    let credential = gvt.issue(&master_secret, gvt_values("Alex"), None);
+
+    let requested = json!({
+        "requested_attributes":{
+            "attr1_referent":{
+                "name":"name",
+                "restrictions": {"attr::name::value": "Alex"}
+            }
+        },
+        "requested_predicates":{
+            "predicate1_referent":{"name":"age","p_type":">=","p_value":18}
+        }
+    });
+    let pres_request = presentation_request(requested.clone());
+
+    let mut present = PresentCredentials::default();
+    {
+        let mut cred1 = present.add_credential(&credential, None, None);
+        cred1.add_requested_attribute("attr1_referent", true);
+        cred1.add_requested_predicate("predicate1_referent");
+    }
+    let presentation = gvt
+        .create_presentation(&pres_request, present, None, &master_secret)
+        .expect("Error creating presentation");
+
+    let (schemas, cred_defs) = (gvt.schemas(), gvt.cred_defs());
+    let verify_detailed = |presentation: &Presentation, pres_request: &PresentationRequest| {
+        verifier::verify_presentation_detailed(
+            presentation,
+            pres_request,
+            &schemas,
+            &cred_defs,
+            None,
+            None,
+            None,
+            None,
+        )
+    };
+    verify_detailed(&presentation, &pres_request).expect("Error verifying presentation");
+
+    // A tampered revealed value fails both the encoding check and the restriction
+    // on the value, and both are reported
+    let mut tampered = json_copy(&presentation);
+    tampered
+        .requested_proof
+        .revealed_attrs
+        .get_mut("attr1_referent")
+        .unwrap()
+        .raw = "Bob".to_string();
+    let failures = verify_detailed(&tampered, &pres_request).unwrap_err();
+    let checks = failures.iter().map(|f| f.check).collect::<Vec<_>>();
+    assert_eq!(
+        checks,
+        [
+            verifier::VerificationCheck::RevealedValues,
+            verifier::VerificationCheck::Restrictions
+        ]
+    );
+    assert!(gvt.verify_presentation(&tampered, &pres_request).is_err());
+
+    // A different nonce is only detected by the cryptographic proof
+    let other_request = presentation_request(requested);
+    let failures = verify_detailed(&presentation, &other_request).unwrap_err();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].check, verifier::VerificationCheck::Proof);
+}
+
+#[test]
+fn anoncreds_rejects_replayed_nonces() {
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let gvt = TestIssuer::gvt(false);
+
+    let cred_offer = gvt.offer();
+    let (cred_request, cred_request_metadata) = prover::create_credential_request(
+        None,
+        &gvt.cred_def,
+        &master_secret,
+        "default",
+        &cred_offer,
+        None,
+    )
+    .expect("Error creating credential request");
+
+    // The issuer consumes the nonce of the offer when issuing, and rejects a
+    // second request for the same offer
+    let mut issuer_nonces = NonceRegistry::new(Duration::from_secs(600));
+    let mut issue = || {
+        issuer::create_credential(
+            &gvt.cred_def,
+            &gvt.cred_def_priv,
+            &cred_offer,
+            &cred_request,
+            gvt_values("Alex"),
+            None,
+            None,
+            None,
+            None,
+            Some(&mut issuer_nonces),
+        )
+    };
+    let mut credential = issue().expect("Error creating credential");
+    assert_eq!(issue().unwrap_err().kind(), ErrorKind::Input);
+
+    // An offer older than the TTL is rejected without consuming its nonce
+    let mut expired_offer = gvt.offer();
+    expired_offer.created = expired_offer.created.map(|created| created - 120);
+    let (expired_request, _) = prover::create_credential_request(
+        None,
+        &gvt.cred_def,
+        &master_secret,
+        "default",
+        &expired_offer,
+        None,
+    )
+    .expect("Error creating credential request");
+    let err = issuer::create_credential(
+        &gvt.cred_def,
+        &gvt.cred_def_priv,
+        &expired_offer,
+        &expired_request,
+        gvt_values("Alex"),
+        None,
+        None,
+        None,
+        Some(60),
+        Some(&mut issuer_nonces),
+    )
+    .expect_err("Expired offer should be rejected");
+    assert_eq!(err.kind(), ErrorKind::Input);
+    assert!(!issuer_nonces.contains(&expired_offer.nonce));
+
+    prover::process_credential(
+        &mut credential,
+        &cred_request_metadata,
+        &master_secret,
+        &gvt.cred_def,
+        None,
+    )
+    .expect("Error processing credential");
+
+    let pres_request = presentation_request(json!({
+        "requested_attributes":{
+            "attr1_referent":{"name":"name"}
+        }
+    }));
+    let mut present = PresentCredentials::default();
+    present
+        .add_credential(&credential, None, None)
+        .add_requested_attribute("attr1_referent", true);
+    let presentation = gvt
+        .create_presentation(&pres_request, present, None, &master_secret)
+        .expect("Error creating presentation");
+
+    // The verifier consumes the nonce of the request once the presentation is
+    // valid, and rejects the same presentation when it is replayed
+    let (schemas, cred_defs) = (gvt.schemas(), gvt.cred_defs());
+    let mut verifier_nonces = NonceRegistry::new(Duration::from_secs(600));
+    let mut verify = || {
+        verifier::verify_presentation(
+            &presentation,
+            &pres_request,
+            &schemas,
+            &cred_defs,
+            None,
+            None,
+            Some(&mut verifier_nonces),
+            None,
+        )
+    };
+    assert!(verify().expect("Error verifying presentation"));
+    assert_eq!(verify().unwrap_err().kind(), ErrorKind::ProofRejected);
+
+    let failures = verifier::verify_presentation_detailed(
+        &presentation,
+        &pres_request,
+        &schemas,
+        &cred_defs,
+        None,
+        None,
+        Some(&mut verifier_nonces),
+        None,
+    )
+    .expect_err("Replayed presentation should be rejected");
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].check, verifier::VerificationCheck::Proof);
+}
+
+#[test]
+fn anoncreds_works_for_typed_credential_values() {
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let typed = TestIssuer::new("typed", &["name", "birthdate", "member", "score"], false);
+
+    let mut cred_values = MakeCredentialValues::default();
+    cred_values
+        .add_str("name", "Alex")
+        .expect("Error encoding attribute");
+    cred_values
+        .add_date("birthdate", 2000, 5, 14)
+        .expect("Error encoding attribute");
+    cred_values.add_bool("member", true);
+    cred_values.add_int("score", 42);
+    let credential = typed.issue(&master_secret, cred_values.into(), None);
+
+    let pres_request = presentation_request(json!({
+        "requested_attributes":{
+            "attr1_referent":{"name":"member"}
+        },
+        "requested_predicates":{
+            "predicate1_referent":{"name":"birthdate","p_type":"<=","p_value":20080101},
+            "predicate2_referent":{"name":"birthdate","p_type":">","p_value":19991231},
+            "predicate3_referent":{"name":"score","p_type":">=","p_value":42}
+        }
+    }));
+    let mut present = PresentCredentials::default();
+    {
+        let mut cred = present.add_credential(&credential, None, None);
+        cred.add_requested_attribute("attr1_referent", true);
+        cred.add_requested_predicate("predicate1_referent");
+        cred.add_requested_predicate("predicate2_referent");
+        cred.add_requested_predicate("predicate3_referent");
+    }
+    let presentation = typed
+        .create_presentation(&pres_request, present, None, &master_secret)
+        .expect("Error creating presentation");
+
+    let revealed = &presentation.requested_proof.revealed_attrs["attr1_referent"];
+    assert_eq!(revealed.raw, "true");
+    assert_eq!(revealed.encoded, "1");
+    assert!(typed
+        .verify_presentation(&presentation, &pres_request)
+        .expect("Error verifying presentation"));
+
+    // A date predicate which is not satisfied cannot be proven
+    let pres_request = presentation_request(json!({
+        "requested_predicates":{
+            "predicate1_referent":{"name":"birthdate","p_type":">=","p_value":20000515}
+        }
+    }));
+    let mut present = PresentCredentials::default();
+    present
+        .add_credential(&credential, None, None)
+        .add_requested_predicate("predicate1_referent");
+    assert!(typed
+        .create_presentation(&pres_request, present, None, &master_secret)
+        .is_err());
+}
+
+#[test]
+fn anoncreds_rejects_presentations_over_the_predicate_limit() {
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let gvt = TestIssuer::gvt(false);
+    let credential = gvt.issue(&master_secret, gvt_values("Alex"), None);
+
+    let pres_request = presentation_request(json!({
+        "requested_predicates":{
+            "predicate1_referent":{"name":"age","p_type":">=","p_value":18},
+            "predicate2_referent":{"name":"age","p_type":"<","p_value":65},
+            "predicate3_referent":{"name":"height","p_type":">","p_value":150}
+        }
+    }));
+    let mut present = PresentCredentials::default();
+    {
+        let mut cred = present.add_credential(&credential, None, None);
+        cred.add_requested_predicate("predicate1_referent");
+        cred.add_requested_predicate("predicate2_referent");
+        cred.add_requested_predicate("predicate3_referent");
+    }
+    let presentation = gvt
+        .create_presentation(&pres_request, present, None, &master_secret)
+        .expect("Error creating presentation");
+
+    // The limits are checked before anything else, so the missing schemas and
+    // credential definitions are never looked up
+    let params = VerifyParams::default().with_max_predicates(2);
+    let err = verifier::verify_presentation(
+        &presentation,
+        &pres_request,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        None,
+        Some(&params),
+    )
+    .expect_err("Presentation over the predicate limit should be rejected");
+    assert_eq!(err.kind(), ErrorKind::PresentationTooLarge);
+
+    let failures = verifier::verify_presentation_detailed(
+        &presentation,
+        &pres_request,
+        &HashMap::new(),
+        &HashMap::new(),
+        None,
+        None,
+        None,
+        Some(&params),
+    )
+    .expect_err("Presentation over the predicate limit should be rejected");
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].check, verifier::VerificationCheck::Size);
+
+    let params = VerifyParams::default().with_max_predicates(3);
+    let valid = verifier::verify_presentation(
+        &presentation,
+        &pres_request,
+        &gvt.schemas(),
+        &gvt.cred_defs(),
+        None,
+        None,
+        None,
+        Some(&params),
+    )
+    .expect("Error verifying presentation");
+    assert!(valid);
+}
+
+#[test]
+fn anoncreds_rejects_unrequested_referents() {
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let gvt = TestIssuer::gvt(false);
+    let credential = gvt.issue(&master_secret, gvt_values("Alex"), None);
+
+    let pres_request = presentation_request(json!({
+        "requested_attributes":{
+            "attr1_referent":{"name":"name"},
+            "attr2_referent":{"name":"phone"}
+        },
+        "requested_predicates":{
+            "predicate1_referent":{"name":"age","p_type":">=","p_value":18}
+        }
+    }));
+
+    let create =
+        |extra_attr: Option<&str>, extra_pred: Option<&str>, self_attested_referent: &str| {
+            let mut present = PresentCredentials::default();
+            {
+                let mut cred = present.add_credential(&credential, None, None);
+                cred.add_requested_attribute("attr1_referent", true);
+                cred.add_requested_predicate("predicate1_referent");
+                if let Some(referent) = extra_attr {
+                    cred.add_requested_attribute(referent, true);
+                }
+                if let Some(referent) = extra_pred {
+                    cred.add_requested_predicate(referent);
+                }
+            }
+            let self_attested =
+                HashMap::from([(self_attested_referent.to_string(), "8-800-300".to_string())]);
+            gvt.create_presentation(&pres_request, present, Some(self_attested), &master_secret)
+        };
+
+    // The prover refuses to answer referents which were not requested
+    for (extra_attr, extra_pred, self_attested_referent) in [
+        (Some("attr9_referent"), None, "attr2_referent"),
+        (None, Some("predicate9_referent"), "attr2_referent"),
+        (None, None, "attr9_referent"),
+        (None, None, "attr1_referent"),
+    ] {
+        let err = create(extra_attr, extra_pred, self_attested_referent)
+            .expect_err("Presentation with unrequested referents should be rejected");
+        assert_eq!(err.kind(), ErrorKind::Input);
+    }
+
+    let presentation = create(None, None, "attr2_referent").expect("Error creating presentation");
+    assert!(gvt
+        .verify_presentation(&presentation, &pres_request)
+        .expect("Error verifying presentation"));
+
+    // The verifier rejects a presentation carrying an extra revealed attribute
+    let mut extra_attr = json_copy(&presentation);
+    let revealed = extra_attr.requested_proof.revealed_attrs["attr1_referent"].clone();
+    extra_attr
+        .requested_proof
+        .revealed_attrs
+        .insert("attr9_referent".to_string(), revealed);
+    let err = gvt
+        .verify_presentation(&extra_attr, &pres_request)
+        .expect_err("Extra revealed attribute should be rejected");
+    assert!(err.to_string().contains("attr9_referent"));
+
+    // .. an extra predicate
+    let mut extra_pred = json_copy(&presentation);
+    let predicate = extra_pred.requested_proof.predicates["predicate1_referent"].clone();
+    extra_pred
+        .requested_proof
+        .predicates
+        .insert("predicate9_referent".to_string(), predicate);
+    let err = gvt
+        .verify_presentation(&extra_pred, &pres_request)
+        .expect_err("Extra predicate should be rejected");
+    assert!(err.to_string().contains("predicate9_referent"));
+
+    // .. and an attribute answered both from the credential and as self-attested
+    let mut duplicate = json_copy(&presentation);
+    duplicate
+        .requested_proof
+        .self_attested_attrs
+        .insert("attr1_referent".to_string(), "Alex".to_string());
+    let err = gvt
+        .verify_presentation(&duplicate, &pres_request)
+        .expect_err("Duplicate attribute referent should be rejected");
+    assert!(err.to_string().contains("more than once"));
+}
+
+#[test]
+fn anoncreds_works_for_recovering_revoked_indices() {
+    let gvt = TestIssuer::gvt(true);
+    let mut registry = gvt.create_registry(MAX_CRED_NUM, 12);
+    let original = registry.update(13, None, Some(BTreeSet::from([1, 2])));
+    let revoked = &registry.status_list;
+    assert_ne!(
+        revoked.accumulator().unwrap(),
+        original.accumulator().unwrap()
+    );
+
+    let recover = |revoked_idxs: &[u32], recovered_idxs: &[u32]| {
+        issuer::update_revocation_status_list(
+            Some(14),
+            None,
+            Some(revoked_idxs.iter().copied().collect()),
+            Some(recovered_idxs.iter().copied().collect()),
+            &registry.rev_reg_def,
+            revoked,
+        )
+    };
+
+    // Recovering every revoked index restores the original accumulator
+    let recovered = recover(&[], &[1, 2]).unwrap();
+    assert_eq!(recovered.is_revoked(1), Some(false));
+    assert_eq!(recovered.is_revoked(2), Some(false));
+    assert_eq!(
+        recovered.accumulator().unwrap(),
+        original.accumulator().unwrap()
+    );
+
+    // The delta moves the recovered indices back to issued
+    let delta = recovered
+        .registry_delta(revoked)
+        .expect("Error building revocation registry delta");
+    let delta = serde_json::to_value(&delta.value).unwrap();
+    let indices = |name: &str| -> BTreeSet<u32> {
+        delta
+            .get(name)
+            .map(|indices| serde_json::from_value(indices.clone()).unwrap())
+            .unwrap_or_default()
+    };
+    assert_eq!(indices("issued"), BTreeSet::from([1, 2]));
+    assert!(indices("revoked").is_empty());
+
+    // Revoking and recovering in the same update
+    let partial = recover(&[3], &[1]).unwrap();
+    assert_eq!(partial.is_revoked(1), Some(false));
+    assert_eq!(partial.is_revoked(2), Some(true));
+    assert_eq!(partial.is_revoked(3), Some(true));
+
+    // An index cannot be both revoked and recovered, only revoked indices can
+    // be recovered, and they must be within the registry
+    assert_eq!(recover(&[1], &[1]).unwrap_err().kind(), ErrorKind::Input);
+    assert_eq!(recover(&[], &[3]).unwrap_err().kind(), ErrorKind::Input);
+    assert_eq!(
+        recover(&[], &[MAX_CRED_NUM + 1]).unwrap_err().kind(),
+        ErrorKind::InvalidUserRevocId
+    );
+}
+
+#[test]
+fn anoncreds_enforces_the_interval_start_unless_legacy_v1_intervals() {
+    let gvt = TestIssuer::gvt(true);
+    let mut registry = gvt.create_registry(MAX_CRED_NUM, 12);
+    let master_secret = MasterSecret::new().expect("Error creating prover master secret");
+    let credential = gvt.issue(
+        &master_secret,
+        gvt_values("Alex"),
+        Some((&mut registry, 1, 13)),
+    );
+    let rev_state = prover::create_or_update_revocation_state_with_tails_reader(
+        &registry.tails_reader(),
+        &registry.rev_reg_def,
+        &registry.status_list,
+        1,
+        None,
+        None,
+        false,
+    )
+    .expect("Error creating revocation state");
+
+    // The registry was last updated at 13, before the requested interval
+    let verify = |ver: &str, params: Option<&VerifyParams>| {
+        let pres_request = presentation_request(json!({
+            "ver": ver,
+            "requested_attributes": {"attr1_referent": {"name": "name"}},
+            "non_revoked": {"from": 20, "to": 30},
+        }));
+        let mut present = PresentCredentials::default();
+        present
+            .add_credential(&credential, Some(13), Some(&rev_state))
+            .add_requested_attribute("attr1_referent", true);
+        let presentation = gvt
+            .create_presentation(&pres_request, present, None, &master_secret)
+            .expect("Error creating presentation");
+        verifier::verify_presentation(
+            &presentation,
+            &pres_request,
+            &gvt.schemas(),
+            &gvt.cred_defs(),
+            Some(&registry.rev_reg_defs()),
+            Some(vec![&registry.status_list]),
+            None,
+            params,
+        )
+    };
+
+    // Both bounds are enforced by default, whatever the request version
+    for ver in ["1.0", "2.0"] {
+        assert_eq!(
+            verify(ver, None).unwrap_err().kind(),
+            ErrorKind::ProofRejected
+        );
+    }
+
+    // The legacy behavior only relaxes version 1.0 requests
+    let legacy = VerifyParams::default().with_legacy_v1_intervals(true);
+    assert!(verify("1.0", Some(&legacy)).expect("Error verifying presentation"));
+    assert_eq!(
+        verify("2.0", Some(&legacy)).unwrap_err().kind(),
+        ErrorKind::ProofRejected
+    );
+}
+
 fn _create_presentation(
     schemas: &HashMap<&SchemaId, &Schema>,
     cred_defs: &HashMap<&CredentialDefinitionId, &CredentialDefinition>,
@@ -138,9 +138,9 @@ fn anoncreds_with_multiple_credentials_per_request() {
 
     mock.ledger.schemas = schemas;
 
-    // This is out of range of revoke interval, should get warning
-    let time_initial_rev_reg = 123u64;
-    let time_after_credential = 124u64;
+    // Both timestamps must fall within the requested non-revocation intervals
+    let time_initial_rev_reg = 12u64;
+    let time_after_credential = 13u64;
     let issuance_by_default = true;
 
     // To test:
@@ -200,3 +200,132 @@ fn anoncreds_with_multiple_credentials_per_request() {
     assert!(results[0]);
     assert!(results[1]);
 }
+
+#[test]
+fn anoncreds_with_attribute_group_from_a_single_credential() {
+    let mut mock = utils::Mock::new(&[ISSUER_ID], &[PROVER_ID], TF_PATH, MAX_CRED_NUM);
+
+    let issuer1_creds: utils::IsserValues = HashMap::from([
+        (
+            CRED_DEF_ID_1,
+            (
+                SCHEMA_ID_1,
+                HashMap::from([
+                    ("sex", "male"),
+                    ("name", "Alex"),
+                    ("height", "175"),
+                    ("age", "28"),
+                ]),
+                true,
+                REV_REG_ID_1,
+                REV_IDX_1,
+            ),
+        ),
+        (
+            CRED_DEF_ID_2,
+            (
+                SCHEMA_ID_2,
+                HashMap::from([
+                    ("wand", "dragon-heart-string"),
+                    ("house", "Hufflepuff"),
+                    ("year", "1990"),
+                ]),
+                true,
+                REV_REG_ID_2,
+                REV_IDX_2,
+            ),
+        ),
+    ]);
+
+    mock.ledger.schemas = HashMap::from([
+        (
+            SchemaId::new_unchecked(SCHEMA_ID_1),
+            serde_json::from_str(SCHEMA_1).unwrap(),
+        ),
+        (
+            SchemaId::new_unchecked(SCHEMA_ID_2),
+            serde_json::from_str(SCHEMA_2).unwrap(),
+        ),
+    ]);
+
+    let time_initial_rev_reg = 12u64;
+    let time_after_credential = 13u64;
+
+    mock.issuer_setup(
+        ISSUER_ID,
+        PROVER_ID,
+        &issuer1_creds,
+        time_initial_rev_reg,
+        true,
+    );
+    mock.issuer_create_credential_and_store_in_prover_wallet(
+        ISSUER_ID,
+        PROVER_ID,
+        &issuer1_creds,
+        time_initial_rev_reg,
+        time_after_credential,
+    );
+    mock.prover_creates_revocation_states(PROVER_ID, time_after_credential);
+
+    let nonce = verifier::generate_nonce().expect("Error generating presentation request nonce");
+    let req: PresentationRequest = serde_json::from_value(json!({
+        "nonce": nonce,
+        "name":"pres_req_1",
+        "version":"0.1",
+        "requested_attributes":{
+            "attr1_referent": {"names": ["name", "height"]},
+            "attr2_referent": {"names": ["name", "house"]},
+        },
+        "non_revoked": {"from": 10, "to": 20}
+    }))
+    .unwrap();
+
+    // the second group mixes attributes of both credentials, so it can not be
+    // satisfied by either of them
+    for (group_1, group_2) in [
+        (vec!["attr1_referent", "attr2_referent"], vec![]),
+        (vec!["attr1_referent"], vec!["attr2_referent"]),
+        (
+            vec!["attr1_referent", "attr2_referent"],
+            vec!["attr2_referent"],
+        ),
+    ] {
+        let prover_values: utils::ProverValues = HashMap::from([
+            (CRED_DEF_ID_1, (group_1, vec![])),
+            (CRED_DEF_ID_2, (group_2, vec![])),
+        ]);
+        mock.prover_tries_to_create_presentation(PROVER_ID, prover_values, HashMap::new(), &req)
+            .expect_err("Attribute group split across credentials must be rejected");
+    }
+
+    // a valid request, with each group satisfied by a single credential
+    let nonce = verifier::generate_nonce().expect("Error generating presentation request nonce");
+    let req: PresentationRequest = serde_json::from_value(json!({
+        "nonce": nonce,
+        "name":"pres_req_1",
+        "version":"0.1",
+        "requested_attributes":{
+            "attr1_referent": {"names": ["name", "height"]},
+            "attr2_referent": {"names": ["wand", "house"]},
+        },
+        "non_revoked": {"from": 10, "to": 20}
+    }))
+    .unwrap();
+    let prover_values: utils::ProverValues = HashMap::from([
+        (CRED_DEF_ID_1, (vec!["attr1_referent"], vec![])),
+        (CRED_DEF_ID_2, (vec!["attr2_referent"], vec![])),
+    ]);
+    let presentation =
+        mock.prover_creates_presentation(PROVER_ID, prover_values, HashMap::new(), &req);
+
+    let groups = &presentation.requested_proof.revealed_attr_groups;
+    assert_eq!(groups["attr1_referent"].values["height"].raw, "175");
+    assert_eq!(groups["attr2_referent"].values["house"].raw, "Hufflepuff");
+    assert_ne!(
+        groups["attr1_referent"].sub_proof_index,
+        groups["attr2_referent"].sub_proof_index
+    );
+
+    let results = mock.verifer_verifies_presentations_for_requests(vec![presentation], &[req]);
+    assert!(results[0]);
+}
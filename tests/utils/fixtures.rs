@@ -0,0 +1,301 @@
+// Not every test binary uses every helper
+#![allow(dead_code)]
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::create_dir,
+};
+
+use anoncreds::{
+    data_types::{
+        cred_def::{CredentialDefinition, CredentialDefinitionId},
+        credential::Credential,
+        master_secret::MasterSecret,
+        presentation::Presentation,
+        rev_reg::RevocationRegistryId,
+        rev_reg_def::{
+            RevocationRegistryDefinition, RevocationRegistryDefinitionId,
+            RevocationRegistryDefinitionPrivate,
+        },
+        schema::{Schema, SchemaId},
+    },
+    issuer, prover,
+    tails::{TailsFileReader, TailsFileWriter, TailsReader},
+    types::{
+        CredentialDefinitionConfig, CredentialDefinitionPrivate, CredentialKeyCorrectnessProof,
+        CredentialOffer, CredentialRevocationConfig, CredentialValues, MakeCredentialValues,
+        PresentCredentials, PresentationRequest, RegistryType, RevocationStatusList, SignatureType,
+    },
+    verifier,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+pub const SCHEMA_ID: &str = "mock:uri";
+pub const CRED_DEF_ID: &str = "mock:uri";
+pub const ISSUER_ID: &str = "mock:issuer_id/path&q=bar";
+pub const REV_REG_DEF_ID: &str = "mock:uri:revregid";
+pub const GVT_SCHEMA_NAME: &str = "gvt";
+pub const GVT_SCHEMA_ATTRIBUTES: &[&str; 4] = &["name", "age", "sex", "height"];
+pub const TAILS_PATH: &str = "../.tmp";
+
+/// Attribute values of a gvt credential
+pub fn gvt_values(name: &str) -> CredentialValues {
+    let mut cred_values = MakeCredentialValues::default();
+    for (attr, raw) in [
+        ("sex", "male"),
+        ("name", name),
+        ("height", "175"),
+        ("age", "28"),
+    ] {
+        cred_values
+            .add_raw(attr, raw)
+            .expect("Error encoding attribute");
+    }
+    cred_values.into()
+}
+
+/// A presentation request with a new nonce, for the requested attributes and
+/// predicates of `request`, which may also set `ver` and `non_revoked`
+pub fn presentation_request(mut request: Value) -> PresentationRequest {
+    let nonce = verifier::generate_nonce().expect("Error generating presentation request nonce");
+    let fields = request.as_object_mut().expect("Expected a JSON object");
+    fields.insert("nonce".to_string(), json!(nonce));
+    fields.insert("name".to_string(), json!("pres_req_1"));
+    fields.insert("version".to_string(), json!("0.1"));
+    for key in ["requested_attributes", "requested_predicates"] {
+        fields.entry(key).or_insert_with(|| json!({}));
+    }
+    serde_json::from_value(request).expect("Error creating proof request")
+}
+
+/// Copy a value which does not implement `Clone`, such as a presentation to be
+/// tampered with
+pub fn json_copy<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    serde_json::from_value(serde_json::to_value(value).unwrap()).unwrap()
+}
+
+/// A single issuer with a credential definition for one schema
+pub struct TestIssuer {
+    pub schema_id: SchemaId,
+    pub schema: Schema,
+    pub cred_def_id: CredentialDefinitionId,
+    pub cred_def: CredentialDefinition,
+    pub cred_def_priv: CredentialDefinitionPrivate,
+    pub key_proof: CredentialKeyCorrectnessProof,
+}
+
+impl TestIssuer {
+    /// An issuer of gvt credentials
+    pub fn gvt(support_revocation: bool) -> Self {
+        Self::new(
+            GVT_SCHEMA_NAME,
+            &GVT_SCHEMA_ATTRIBUTES[..],
+            support_revocation,
+        )
+    }
+
+    pub fn new(schema_name: &str, attr_names: &[&str], support_revocation: bool) -> Self {
+        let schema = issuer::create_schema(schema_name, "1.0", ISSUER_ID, attr_names.into())
+            .expect("Error creating schema for issuer");
+        let (cred_def, cred_def_priv, key_proof) = issuer::create_credential_definition(
+            SCHEMA_ID,
+            &schema,
+            ISSUER_ID,
+            "tag",
+            SignatureType::CL,
+            CredentialDefinitionConfig::new(support_revocation),
+        )
+        .expect("Error creating credential definition");
+        Self {
+            schema_id: SchemaId::new_unchecked(SCHEMA_ID),
+            schema,
+            cred_def_id: CredentialDefinitionId::new_unchecked(CRED_DEF_ID),
+            cred_def,
+            cred_def_priv,
+            key_proof,
+        }
+    }
+
+    pub fn offer(&self) -> CredentialOffer {
+        issuer::create_credential_offer(SCHEMA_ID, CRED_DEF_ID, &self.key_proof, None)
+            .expect("Error creating credential offer")
+    }
+
+    /// Create a revocation registry, tracking its status list as of `timestamp`
+    pub fn create_registry(&self, max_cred_num: u32, timestamp: u64) -> TestRegistry {
+        create_dir(TAILS_PATH).ok();
+        let mut tails_writer = TailsFileWriter::new(Some(TAILS_PATH.to_owned()));
+        let (rev_reg_def, rev_reg_def_priv) = issuer::create_revocation_registry_def(
+            &self.cred_def,
+            CRED_DEF_ID,
+            ISSUER_ID,
+            "some_tag",
+            RegistryType::CL_ACCUM,
+            max_cred_num,
+            &mut tails_writer,
+        )
+        .expect("Error creating revocation registry definition");
+        let status_list = issuer::create_revocation_status_list(
+            REV_REG_DEF_ID,
+            &rev_reg_def,
+            Some(timestamp),
+            true,
+        )
+        .expect("Error creating revocation status list");
+        TestRegistry {
+            rev_reg_def_id: RevocationRegistryDefinitionId::new_unchecked(REV_REG_DEF_ID),
+            rev_reg_def,
+            rev_reg_def_priv,
+            status_list,
+        }
+    }
+
+    /// Issue a credential to the holder of `master_secret` and process it. With a
+    /// registry, the credential is issued for the index `rev_idx` and the status
+    /// list of the registry is updated at `timestamp`.
+    pub fn issue(
+        &self,
+        master_secret: &MasterSecret,
+        values: CredentialValues,
+        registry: Option<(&mut TestRegistry, u32, u64)>,
+    ) -> Credential {
+        let offer = self.offer();
+        let (request, request_metadata) = prover::create_credential_request(
+            None,
+            &self.cred_def,
+            master_secret,
+            "default",
+            &offer,
+            None,
+        )
+        .expect("Error creating credential request");
+
+        let (rev_reg_id, status_list, rev_config) = match &registry {
+            Some((registry, rev_idx, _)) => (
+                Some(RevocationRegistryId::new_unchecked(REV_REG_DEF_ID)),
+                Some(&registry.status_list),
+                Some(CredentialRevocationConfig {
+                    reg_def: &registry.rev_reg_def,
+                    reg_def_private: &registry.rev_reg_def_priv,
+                    registry_idx: *rev_idx,
+                    tails_reader: registry.tails_reader(),
+                    verify_tails: true,
+                }),
+            ),
+            None => (None, None, None),
+        };
+        let mut credential = issuer::create_credential(
+            &self.cred_def,
+            &self.cred_def_priv,
+            &offer,
+            &request,
+            values,
+            rev_reg_id,
+            status_list,
+            rev_config,
+            None,
+            None,
+        )
+        .expect("Error creating credential");
+
+        prover::process_credential(
+            &mut credential,
+            &request_metadata,
+            master_secret,
+            &self.cred_def,
+            registry
+                .as_ref()
+                .map(|(registry, _, _)| &registry.rev_reg_def),
+        )
+        .expect("Error processing credential");
+
+        if let Some((registry, rev_idx, timestamp)) = registry {
+            registry.update(timestamp, Some(BTreeSet::from([rev_idx])), None);
+        }
+        credential
+    }
+
+    pub fn schemas(&self) -> HashMap<&SchemaId, &Schema> {
+        HashMap::from([(&self.schema_id, &self.schema)])
+    }
+
+    pub fn cred_defs(&self) -> HashMap<&CredentialDefinitionId, &CredentialDefinition> {
+        HashMap::from([(&self.cred_def_id, &self.cred_def)])
+    }
+
+    /// Present the requested attributes of a single credential
+    pub fn create_presentation(
+        &self,
+        pres_request: &PresentationRequest,
+        present: PresentCredentials,
+        self_attested: Option<HashMap<String, String>>,
+        master_secret: &MasterSecret,
+    ) -> anoncreds::Result<Presentation> {
+        prover::create_presentation(
+            pres_request,
+            present,
+            self_attested,
+            master_secret,
+            &self.schemas(),
+            &self.cred_defs(),
+        )
+    }
+
+    /// Verify a presentation which does not use revocation
+    pub fn verify_presentation(
+        &self,
+        presentation: &Presentation,
+        pres_request: &PresentationRequest,
+    ) -> anoncreds::Result<bool> {
+        verifier::verify_presentation(
+            presentation,
+            pres_request,
+            &self.schemas(),
+            &self.cred_defs(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+}
+
+/// A revocation registry with its current status list
+pub struct TestRegistry {
+    pub rev_reg_def_id: RevocationRegistryDefinitionId,
+    pub rev_reg_def: RevocationRegistryDefinition,
+    pub rev_reg_def_priv: RevocationRegistryDefinitionPrivate,
+    pub status_list: RevocationStatusList,
+}
+
+impl TestRegistry {
+    pub fn tails_reader(&self) -> TailsReader {
+        TailsFileReader::new_tails_reader(&self.rev_reg_def.value.tails_location)
+    }
+
+    /// Update the status list, returning the previous one
+    pub fn update(
+        &mut self,
+        timestamp: u64,
+        issued: Option<BTreeSet<u32>>,
+        revoked: Option<BTreeSet<u32>>,
+    ) -> RevocationStatusList {
+        let updated = issuer::update_revocation_status_list(
+            Some(timestamp),
+            issued,
+            revoked,
+            None,
+            &self.rev_reg_def,
+            &self.status_list,
+        )
+        .expect("Error updating revocation status list");
+        std::mem::replace(&mut self.status_list, updated)
+    }
+
+    pub fn rev_reg_defs(
+        &self,
+    ) -> HashMap<&RevocationRegistryDefinitionId, &RevocationRegistryDefinition> {
+        HashMap::from([(&self.rev_reg_def_id, &self.rev_reg_def)])
+    }
+}
@@ -93,6 +93,8 @@ impl<'a> Mock<'a> {
                 &cred_defs,
                 Some(&rev_reg_def_map),
                 Some(rev_status_lists.clone()),
+                None,
+                None,
             )
             .expect("Error verifying presentation");
             results.push(valid);
@@ -120,9 +122,7 @@ impl<'a> Mock<'a> {
                     issuer_id,
                     "tag",
                     SignatureType::CL,
-                    CredentialDefinitionConfig {
-                        support_revocation: *support_revocation,
-                    },
+                    CredentialDefinitionConfig::new(*support_revocation),
                 )
                 .expect("Error creating gvt credential definition");
 
@@ -183,6 +183,7 @@ impl<'a> Mock<'a> {
                 schema_id.to_string(),
                 *cred_def_id,
                 &cred_def_correctness,
+                None,
             )
             .expect("Error creating credential offer");
 
@@ -256,6 +257,7 @@ impl<'a> Mock<'a> {
                         reg_def_private: &stored_rev_def.private,
                         registry_idx: rev_idx,
                         tails_reader: tr,
+                        verify_tails: true,
                     }),
                     Some(RevocationRegistryId::new_unchecked(rev_reg_id)),
                 )
@@ -275,6 +277,8 @@ impl<'a> Mock<'a> {
             rev_id,
             revocation_list,
             rev_config,
+            None,
+            None,
         )
         .expect("Error creating credential");
 
@@ -305,6 +309,7 @@ impl<'a> Mock<'a> {
                 &self.prover_wallets[prover_id].master_secret,
                 "default",
                 &offer,
+                None,
             )
             .expect("Error creating credential request");
 
@@ -354,6 +359,7 @@ impl<'a> Mock<'a> {
                     Some(time_new_rev_reg),
                     Some(BTreeSet::from([*rev_idx])),
                     None,
+                    None,
                     rev_def,
                     list,
                 )
@@ -402,6 +408,17 @@ impl<'a> Mock<'a> {
         self_attested: HashMap<String, String>,
         req: &PresentationRequest,
     ) -> Presentation {
+        self.prover_tries_to_create_presentation(prover_id, prover_values, self_attested, req)
+            .expect("Error creating presentation")
+    }
+
+    pub fn prover_tries_to_create_presentation(
+        &self,
+        prover_id: &'static str,
+        prover_values: ProverValues,
+        self_attested: HashMap<String, String>,
+        req: &PresentationRequest,
+    ) -> Result<Presentation, anoncreds::Error> {
         let schemas: HashMap<&SchemaId, &Schema> = HashMap::from_iter(self.ledger.schemas.iter());
         let cred_defs: HashMap<&CredentialDefinitionId, &CredentialDefinition> =
             HashMap::from_iter(self.ledger.cred_defs.iter());
@@ -426,7 +443,7 @@ impl<'a> Mock<'a> {
             }
         }
 
-        let presentation = prover::create_presentation(
+        prover::create_presentation(
             req,
             present,
             Some(self_attested.clone()),
@@ -434,8 +451,5 @@ impl<'a> Mock<'a> {
             &schemas,
             &cred_defs,
         )
-        .expect("Error creating presentation");
-
-        presentation
     }
 }
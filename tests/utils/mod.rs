@@ -1,4 +1,5 @@
 pub mod anoncreds;
+pub mod fixtures;
 pub mod mock;
 
 pub use mock::{IsserValues, Mock, ProverValues};
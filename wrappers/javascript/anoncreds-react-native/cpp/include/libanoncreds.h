@@ -21,6 +21,9 @@ enum ErrorCode
   InvalidUserRevocId = 6,
   ProofRejected = 7,
   RevocationRegistryFull = 8,
+  TooManyAttributes = 9,
+  PresentationTooLarge = 10,
+  TailsHashMismatch = 11,
 };
 #ifndef __cplusplus
 typedef size_t ErrorCode;
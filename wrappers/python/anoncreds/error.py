@@ -13,6 +13,9 @@ class AnoncredsErrorCode(IntEnum):
     INVALID_USER_REVOC_ID = 6
     PROOF_REJECTED = 7
     REVOCATION_REGISTRY_FULL = 8
+    TOO_MANY_ATTRIBUTES = 9
+    PRESENTATION_TOO_LARGE = 10
+    TAILS_HASH_MISMATCH = 11
     WRAPPER = 99
 
 